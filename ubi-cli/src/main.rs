@@ -1,9 +1,19 @@
+mod progress;
+
 use anyhow::{anyhow, Context, Result};
 use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
 use log::{debug, error};
-use std::{env, path::Path, str::FromStr};
+use progress::{human_bytes, ProgressBar};
+use std::{
+    env,
+    io::{self, IsTerminal},
+    path::Path,
+    process,
+    str::FromStr,
+    time::Duration,
+};
 use strum::VariantNames;
-use ubi::{ForgeType, Ubi, UbiBuilder};
+use ubi::{DownloadCache, ForgeType, PostInstall, PostInstallHookError, Ubi, UbiBuilder};
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() {
@@ -15,6 +25,14 @@ async fn main() {
         std::process::exit(126);
     }
 
+    if matches.get_flag("cache-size") || matches.contains_id("prune-cache") {
+        if let Err(e) = run_cache_command(&matches) {
+            error!("{e:?}");
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
     // We use this when `--self-upgrade` is passed. We need to create this String here so that we
     // can make a Ubi<'_> instance that borrows this value. It needs to have the same lifetime as
     // `matches`. If we try to make it in `self_upgrade_ubi` we end up trying to return a reference
@@ -37,7 +55,13 @@ async fn main() {
             }
             Err(e) => {
                 error!("{e:?}");
-                1
+                // The install succeeded, so we use a different exit code to tell the caller that
+                // only the hook failed.
+                if e.downcast_ref::<PostInstallHookError>().is_some() {
+                    2
+                } else {
+                    1
+                }
             }
         },
         Err(e) => {
@@ -104,7 +128,9 @@ fn cmd() -> Command {
                     " file excluding its extension, e.g. `ubi.gz`. By default this is the same as the",
                     " project name, so for houseabsolute/precious we look for `precious` or",
                     " `precious.exe`. When running on Windows the `.exe` suffix will be added, as needed.",
-                    " You cannot pass this when `--extract-all` is passed.",
+                    " If this isn't passed and nothing in an archive file matches the project name, but",
+                    " the archive contains exactly one executable, that file is installed with its own",
+                    " name. You cannot pass this when `--extract-all` is passed.",
                 )),
         )
         .arg(
@@ -134,6 +160,232 @@ fn cmd() -> Command {
                     "  `--rename-exe-to` are passed.",
                 )),
         )
+        .arg(
+            Arg::new("archive-password")
+                .long("archive-password")
+                .help(concat!(
+                    "The password for an encrypted zip or 7z archive. This works with both AES and",
+                    " the older zip encryption scheme. If this isn't passed, the password is taken",
+                    " from the `UBI_ARCHIVE_PASSWORD` env var, if it's set.",
+                )),
+        )
+        .arg(
+            Arg::new("max-extracted-bytes")
+                .long("max-extracted-bytes")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help(concat!(
+                    "The most bytes to extract from the downloaded file, counting every file that's",
+                    " extracted, including nested archives. If this is exceeded, `ubi` exits with an",
+                    " error and nothing is installed. By default there is no limit.",
+                )),
+        )
+        .arg(
+            Arg::new("max-extracted-file-bytes")
+                .long("max-extracted-file-bytes")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help(concat!(
+                    "The most bytes to extract for any one file. If this is exceeded, `ubi` exits",
+                    " with an error and nothing is installed. By default there is no limit.",
+                )),
+        )
+        .arg(
+            Arg::new("max-archive-entries")
+                .long("max-archive-entries")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help(concat!(
+                    "The most entries to read from an archive file, counting the entries in nested",
+                    " archives. If this is exceeded, `ubi` exits with an error and nothing is",
+                    " installed. By default there is no limit.",
+                )),
+        )
+        .arg(
+            Arg::new("stream-download")
+                .long("stream-download")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Extract tarballs as they are downloaded instead of saving the whole download",
+                    " to a temp file first. This uses less disk space and is faster for large",
+                    " tarballs. Assets that are not tarballs are downloaded as usual. Streamed",
+                    " downloads are checked against the asset's SHA-256 digest, but they are not",
+                    " retried or resumed.",
+                )),
+        )
+        .arg(
+            Arg::new("partial-download-dir")
+                .long("partial-download-dir")
+                .help(concat!(
+                    "The directory to keep interrupted downloads in, so that the next attempt can",
+                    " resume them. This defaults to a `ubi-partial-downloads` directory in your",
+                    " cache dir, like `~/.cache`.",
+                )),
+        )
+        .arg(
+            Arg::new("max-retries")
+                .long("max-retries")
+                .value_parser(clap::value_parser!(u32))
+                .help(concat!(
+                    "The most times to retry a request to the forge or a download that fails with",
+                    " a transient error, like a connection reset or a 503 response. Set this to 0",
+                    " to turn off retries. This defaults to 3.",
+                )),
+        )
+        .arg(
+            Arg::new("wait-for-rate-limit")
+                .long("wait-for-rate-limit")
+                .value_parser(clap::value_parser!(u64).range(1..))
+                .help(concat!(
+                    "If a request to the forge's API hits a rate limit that resets within this many",
+                    " seconds, wait for it to reset instead of exiting with an error. By default,",
+                    " `ubi` does not wait.",
+                )),
+        )
+        .arg(
+            Arg::new("cache")
+                .long("cache")
+                .action(ArgAction::SetTrue)
+                .help(concat!(
+                    "Keep downloaded release assets in a local cache, and install from the cache",
+                    " instead of downloading an asset from the same release again. Assets downloaded",
+                    " with `--url` or `--stream-download` are not cached.",
+                )),
+        )
+        .arg(
+            Arg::new("cache-dir")
+                .long("cache-dir")
+                .help(concat!(
+                    "The directory for the download cache. Passing this enables the cache. This",
+                    " defaults to a `ubi` directory in `$XDG_CACHE_HOME`, or `~/.cache/ubi` if that",
+                    " isn't set. On macOS it's `~/Library/Caches/ubi`, and on Windows it's",
+                    " `%LOCALAPPDATA%\\ubi\\cache`.",
+                )),
+        )
+        .arg(
+            Arg::new("cache-size")
+                .long("cache-size")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["exe", "extract-all", "forge", "in", "project", "tag", "url", "self-upgrade", "prune-cache"])
+                .help(concat!(
+                    "Print how many assets are in the download cache and how much space they use,",
+                    " then exit. This uses the directory from `--cache-dir`, if it's passed.",
+                )),
+        )
+        .arg(
+            Arg::new("prune-cache")
+                .long("prune-cache")
+                .value_parser(clap::value_parser!(u32))
+                .conflicts_with_all(["exe", "extract-all", "forge", "in", "project", "tag", "url", "self-upgrade"])
+                .help(concat!(
+                    "Remove assets that have not been used in this many days from the download cache,",
+                    " then exit. This uses the directory from `--cache-dir`, if it's passed.",
+                )),
+        )
+        .arg(
+            Arg::new("allow-platform-mismatch")
+                .long("allow-platform-mismatch")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("extract-all")
+                .help(concat!(
+                    "By default, `ubi` checks that an installed executable was built for your OS",
+                    " and CPU architecture by looking at its header. If it wasn't, `ubi` deletes it",
+                    " and exits with an error. Pass this to only print a warning instead. You",
+                    " cannot pass this when `--extract-all` is passed.",
+                )),
+        )
+        .arg(
+            Arg::new("install-launchers")
+                .long("install-launchers")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("extract-all")
+                .help(concat!(
+                    "Install `.jar`, `.py`, and `.pyz` files in a lib directory and write a launcher",
+                    " for them in the install directory, which runs them with `java -jar` or `python3`",
+                    " (`python` on Windows). On Windows, the launcher is a `.cmd` file. Otherwise it's",
+                    " a shell script. By default, the lib directory is the `lib` directory next to the",
+                    " install directory. You cannot pass this when `--extract-all` is passed.",
+                )),
+        )
+        .arg(
+            Arg::new("extract-appimages")
+                .long("extract-appimages")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("extract-all")
+                .help(concat!(
+                    "Extract the filesystem in an AppImage into a directory in the lib directory",
+                    " and write a launcher for its `AppRun` file in the install directory, instead of",
+                    " installing the AppImage as-is. This lets you use AppImages where FUSE isn't",
+                    " available, like in most containers. You cannot pass this when `--extract-all` is",
+                    " passed.",
+                )),
+        )
+        .arg(
+            Arg::new("launcher-lib-dir")
+                .long("launcher-lib-dir")
+                .requires("launchers-enabled")
+                .help(concat!(
+                    "The lib directory for `--install-launchers` and `--extract-appimages`. This",
+                    " requires one of those flags.",
+                )),
+        )
+        .arg(
+            Arg::new("smoke-test")
+                .long("smoke-test")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("extract-all")
+                .help(concat!(
+                    "Run the installed executable to make sure that it works. By default it is run",
+                    " with `--version` and must exit successfully within 30 seconds. If it doesn't,",
+                    " the new executable is removed and any executable it replaced is restored. This",
+                    " is skipped when installing for a platform other than the one `ubi` is running",
+                    " on. You cannot pass this when `--extract-all` is passed.",
+                )),
+        )
+        .arg(
+            Arg::new("smoke-test-args")
+                .long("smoke-test-args")
+                .allow_hyphen_values(true)
+                .conflicts_with("extract-all")
+                .help(concat!(
+                    "The arguments to pass to the executable for the smoke test, separated by",
+                    " whitespace. Passing this enables the smoke test. Defaults to `--version`.",
+                )),
+        )
+        .arg(
+            Arg::new("smoke-test-timeout")
+                .long("smoke-test-timeout")
+                .value_parser(clap::value_parser!(u64))
+                .requires("smoke-test-enabled")
+                .help(concat!(
+                    "The number of seconds to wait for the executable to exit during the smoke test.",
+                    " Defaults to 30. This requires `--smoke-test` or `--smoke-test-args`.",
+                )),
+        )
+        .arg(
+            Arg::new("smoke-test-check-version")
+                .long("smoke-test-check-version")
+                .action(ArgAction::SetTrue)
+                .requires("smoke-test-enabled")
+                .help(concat!(
+                    "Make the smoke test also check that the executable's output contains the",
+                    " version from the release tag, which is the tag without any leading non-digit",
+                    " characters. This requires `--smoke-test` or `--smoke-test-args`.",
+                )),
+        )
+        .arg(
+            Arg::new("post-install")
+                .long("post-install")
+                .action(ArgAction::Append)
+                .help(concat!(
+                    "A command to run after a successful install. The command is run with `sh -c`,",
+                    " or `cmd /V:ON /C` on Windows. The installed path, the release tag, and the",
+                    " project name are passed in the `UBI_PATH`, `UBI_TAG`, and `UBI_PROJECT` env",
+                    " vars. The placeholders `{path}`, `{tag}`, and `{project}` are replaced with a",
+                    " quoted reference to these env vars, so don't quote them yourself. With",
+                    " `--extract-all`, `{path}` is the directory the archive was extracted into. The",
+                    " tag is empty when `--url` is passed. This can be passed more than once, and the",
+                    " commands are run in order. If a command fails, `ubi` does not run any more",
+                    " commands and exits with a status of 2, leaving the installed files in place.",
+                )),
+        )
         .arg(
             Arg::new("min-age-days")
                 .long("min-age-days")
@@ -216,13 +468,23 @@ fn cmd() -> Command {
                 .short('q')
                 .long("quiet")
                 .action(ArgAction::SetTrue)
-                .help("Suppresses most output."),
+                .help("Suppresses most output, including the download progress bar."),
         )
         .group(
             ArgGroup::new("require one of")
-                .args(["project", "url", "self-upgrade"])
+                .args(["project", "url", "self-upgrade", "cache-size", "prune-cache"])
                 .required(true),
         )
+        .group(
+            ArgGroup::new("launchers-enabled")
+                .args(["install-launchers", "extract-appimages"])
+                .multiple(true),
+        )
+        .group(
+            ArgGroup::new("smoke-test-enabled")
+                .args(["smoke-test", "smoke-test-args"])
+                .multiple(true),
+        )
         .group(
             ArgGroup::new("log-level")
                 .args(["verbose", "debug", "quiet"]),
@@ -244,15 +506,42 @@ pub(crate) fn init_logger_from_matches(matches: &ArgMatches) -> Result<(), log::
     ubi::init_logger(level)
 }
 
+// Handles `--cache-size` and `--prune-cache`, which don't install anything.
+fn run_cache_command(matches: &ArgMatches) -> Result<()> {
+    let cache = match matches.get_one::<String>("cache-dir") {
+        Some(dir) => DownloadCache::new(dir),
+        None => DownloadCache::in_default_dir()?,
+    };
+    if let Some(days) = matches.get_one::<u32>("prune-cache") {
+        let removed = cache.prune(Duration::from_secs(u64::from(*days) * 24 * 60 * 60))?;
+        println!(
+            "Removed {} assets using {} from the download cache at {}.",
+            removed.assets(),
+            human_bytes(removed.bytes()),
+            cache.dir().display(),
+        );
+    } else {
+        let stats = cache.stats()?;
+        println!(
+            "The download cache at {} has {} assets using {}.",
+            cache.dir().display(),
+            stats.assets(),
+            human_bytes(stats.bytes()),
+        );
+    }
+    Ok(())
+}
+
 fn make_ubi<'a>(
     matches: &'a ArgMatches,
     ubi_exe_path: &'a Path,
 ) -> Result<(Ubi<'a>, Option<impl FnOnce()>)> {
+    let show_progress = !matches.get_flag("quiet") && io::stderr().is_terminal();
     if matches.get_flag("self-upgrade") {
-        return self_upgrade_ubi(ubi_exe_path);
+        return self_upgrade_ubi(ubi_exe_path, show_progress);
     }
 
-    let mut builder = UbiBuilder::new();
+    let mut builder = with_progress_bar(UbiBuilder::new(), show_progress);
     if let Some(p) = matches.get_one::<String>("project") {
         builder = builder.project(p);
     }
@@ -280,6 +569,49 @@ fn make_ubi<'a>(
     if matches.get_flag("extract-all") {
         builder = builder.extract_all();
     }
+    if let Some(p) = matches.get_one::<String>("archive-password") {
+        builder = builder.archive_password(p);
+    }
+    if let Some(bytes) = matches.get_one::<u64>("max-extracted-bytes") {
+        builder = builder.max_extracted_bytes(*bytes);
+    }
+    if let Some(bytes) = matches.get_one::<u64>("max-extracted-file-bytes") {
+        builder = builder.max_extracted_file_bytes(*bytes);
+    }
+    if let Some(entries) = matches.get_one::<u64>("max-archive-entries") {
+        builder = builder.max_archive_entries(*entries);
+    }
+    builder = with_download_options(builder, matches);
+    if matches.get_flag("allow-platform-mismatch") {
+        builder = builder.allow_platform_mismatch();
+    }
+    if matches.get_flag("install-launchers") {
+        builder = builder.install_launchers();
+    }
+    if matches.get_flag("extract-appimages") {
+        builder = builder.extract_appimages();
+    }
+    if let Some(dir) = matches.get_one::<String>("launcher-lib-dir") {
+        builder = builder.launcher_lib_dir(dir);
+    }
+    if matches.get_flag("smoke-test") {
+        builder = builder.smoke_test();
+    }
+    if let Some(args) = matches.get_one::<String>("smoke-test-args") {
+        builder = builder.smoke_test_args(&args.split_whitespace().collect::<Vec<_>>());
+    }
+    if let Some(secs) = matches.get_one::<u64>("smoke-test-timeout") {
+        builder = builder.smoke_test_timeout(Duration::from_secs(*secs));
+    }
+    if matches.get_flag("smoke-test-check-version") {
+        builder = builder.smoke_test_checks_version();
+    }
+    if let Some(commands) = matches.get_many::<String>("post-install") {
+        for command in commands {
+            builder =
+                builder.post_install_hook(move |info| run_post_install_command(command, info));
+        }
+    }
     if let Some(ft) = matches.get_one::<String>("forge") {
         builder = builder.forge(
             ForgeType::from_str(ft).with_context(|| format!("failed to parse forge type: {ft}"))?,
@@ -295,9 +627,122 @@ fn make_ubi<'a>(
     Ok((builder.build()?, None))
 }
 
-fn self_upgrade_ubi(ubi_exe_path: &Path) -> Result<(Ubi<'_>, Option<impl FnOnce()>)> {
+fn with_download_options<'a>(
+    mut builder: UbiBuilder<'a>,
+    matches: &'a ArgMatches,
+) -> UbiBuilder<'a> {
+    if matches.get_flag("stream-download") {
+        builder = builder.stream_download();
+    }
+    if let Some(dir) = matches.get_one::<String>("partial-download-dir") {
+        builder = builder.partial_download_dir(dir);
+    }
+    if let Some(max_retries) = matches.get_one::<u32>("max-retries") {
+        builder = builder.max_retries(*max_retries);
+    }
+    if let Some(secs) = matches.get_one::<u64>("wait-for-rate-limit") {
+        builder = builder.wait_for_rate_limit(Duration::from_secs(*secs));
+    }
+    if matches.get_flag("cache") {
+        builder = builder.use_download_cache();
+    }
+    if let Some(dir) = matches.get_one::<String>("cache-dir") {
+        builder = builder.download_cache_dir(dir);
+    }
+    builder
+}
+
+fn with_progress_bar(builder: UbiBuilder<'_>, show_progress: bool) -> UbiBuilder<'_> {
+    if !show_progress {
+        return builder;
+    }
+    let bar = ProgressBar::default();
+    builder.download_progress_hook(move |p| bar.update(p))
+}
+
+fn run_post_install_command(template: &str, info: &PostInstall) -> Result<()> {
+    let (command, mut cmd) = post_install_command(
+        template,
+        info.path(),
+        info.tag().unwrap_or_default(),
+        info.project(),
+    );
+    debug!("running post-install command `{command}`");
+
+    let status = cmd
+        .status()
+        .with_context(|| format!("failed to run `{command}`"))?;
+    if !status.success() {
+        return Err(anyhow!("`{command}` failed with {status}"));
+    }
+
+    Ok(())
+}
+
+// The release tag comes from whoever published the release, so we never let the shell parse it, or
+// any of the other values. They're passed to the command in env vars, and each placeholder is
+// replaced with a quoted reference to its env var.
+const POST_INSTALL_VARS: [(&str, &str); 3] = [
+    ("{path}", "UBI_PATH"),
+    ("{tag}", "UBI_TAG"),
+    ("{project}", "UBI_PROJECT"),
+];
+
+// Returns the command with its placeholders replaced, along with the process that runs it.
+fn post_install_command(
+    template: &str,
+    path: &Path,
+    tag: &str,
+    project: &str,
+) -> (String, process::Command) {
+    let mut command = template.to_string();
+    for (placeholder, var) in POST_INSTALL_VARS {
+        command = command.replace(placeholder, &shell_var_ref(var));
+    }
+
+    let mut cmd = shell_command(&command);
+    cmd.env("UBI_PATH", path)
+        .env("UBI_TAG", tag)
+        .env("UBI_PROJECT", project);
+    (command, cmd)
+}
+
+// With delayed expansion, `cmd` expands `!VAR!` after it has parsed the command, so the value can't
+// change how the command is parsed, unlike `%VAR%`.
+#[cfg(target_os = "windows")]
+fn shell_var_ref(var: &str) -> String {
+    format!("\"!{var}!\"")
+}
+
+#[cfg(not(target_os = "windows"))]
+fn shell_var_ref(var: &str) -> String {
+    format!("\"${var}\"")
+}
+
+#[cfg(target_os = "windows")]
+fn shell_command(command: &str) -> process::Command {
+    use std::os::windows::process::CommandExt;
+
+    // `cmd` doesn't follow the quoting rules that `Command` uses for arguments, so we pass the
+    // command to it as-is.
+    let mut cmd = process::Command::new("cmd");
+    cmd.args(["/V:ON", "/C"]).raw_arg(command);
+    cmd
+}
+
+#[cfg(not(target_os = "windows"))]
+fn shell_command(command: &str) -> process::Command {
+    let mut cmd = process::Command::new("sh");
+    cmd.args(["-c", command]);
+    cmd
+}
+
+fn self_upgrade_ubi(
+    ubi_exe_path: &Path,
+    show_progress: bool,
+) -> Result<(Ubi<'_>, Option<impl FnOnce()>)> {
     let ubi =
-        UbiBuilder::new()
+        with_progress_bar(UbiBuilder::new(), show_progress)
             .project("houseabsolute/ubi")
             .install_dir(ubi_exe_path.parent().ok_or_else(|| {
                 anyhow!("executable path `{}` has no parent", ubi_exe_path.display())
@@ -331,3 +776,45 @@ fn self_upgrade_ubi(ubi_exe_path: &Path) -> Result<(Ubi<'_>, Option<impl FnOnce(
 
     Ok((ubi, post_run))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[rstest]
+    #[case::plain("v1.0.0")]
+    #[case::semicolon("v1;touch pwned")]
+    #[case::command_substitution("v1$(touch pwned)")]
+    #[case::backticks("v1`touch pwned`")]
+    #[case::quotes("v1\"'; touch pwned; '\"")]
+    #[cfg(not(target_os = "windows"))]
+    fn post_install_command_does_not_let_the_shell_parse_values(#[case] tag: &str) -> Result<()> {
+        let td = tempdir()?;
+        let (command, mut cmd) = post_install_command(
+            "printf '%s\\n' {path} {tag} {project} > out.txt",
+            Path::new("/some path/bin/project"),
+            tag,
+            "some/project",
+        );
+        assert_eq!(
+            command,
+            r#"printf '%s\n' "$UBI_PATH" "$UBI_TAG" "$UBI_PROJECT" > out.txt"#
+        );
+
+        let status = cmd.current_dir(td.path()).status()?;
+        assert!(status.success());
+        assert_eq!(
+            fs::read_to_string(td.path().join("out.txt"))?,
+            format!("/some path/bin/project\n{tag}\nsome/project\n"),
+        );
+        assert!(
+            !td.path().join("pwned").exists(),
+            "the tag was not run as a command"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use std::{
+    cell::Cell,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+use ubi::DownloadProgress;
+
+const BAR_WIDTH: u64 = 30;
+
+// We get a progress update for every chunk of the download, which is far more often than anyone
+// can read, so we only redraw the bar this often.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+// Draws a progress bar for the download on stderr, on a single line that is redrawn in place.
+#[derive(Debug, Default)]
+pub(crate) struct ProgressBar {
+    last_draw: Cell<Option<Instant>>,
+}
+
+impl ProgressBar {
+    pub(crate) fn update(&self, progress: &DownloadProgress<'_>) {
+        let now = Instant::now();
+        if !progress.is_finished()
+            && self
+                .last_draw
+                .get()
+                .is_some_and(|last| now.duration_since(last) < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        self.last_draw.set(Some(now));
+
+        let mut line = render(progress);
+        if progress.is_finished() {
+            line.push('\n');
+        }
+        let mut stderr = io::stderr().lock();
+        // There's nothing useful to do if we can't write the progress bar.
+        let _ = write!(stderr, "\r{line}");
+        let _ = stderr.flush();
+    }
+}
+
+fn render(progress: &DownloadProgress<'_>) -> String {
+    let downloaded = progress.downloaded();
+    match progress.total() {
+        Some(total) if total > 0 => {
+            let downloaded = downloaded.min(total);
+            let filled = downloaded * BAR_WIDTH / total;
+            let percent = downloaded * 100 / total;
+            format!(
+                "{} [{}{}] {percent:>3}% {} / {}",
+                progress.asset_name(),
+                "#".repeat(usize::try_from(filled).unwrap_or_default()),
+                " ".repeat(usize::try_from(BAR_WIDTH - filled).unwrap_or_default()),
+                human_bytes(downloaded),
+                human_bytes(total),
+            )
+        }
+        _ => format!("{} {}", progress.asset_name(), human_bytes(downloaded)),
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
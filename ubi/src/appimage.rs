@@ -0,0 +1,403 @@
+// An AppImage is an ELF executable, the AppImage runtime, followed by a SquashFS filesystem image
+// that contains the application. When it's run, the runtime uses FUSE to mount the filesystem and
+// then runs the `AppRun` file at its root. FUSE isn't available in most containers, so instead we
+// can extract the filesystem into a directory and run `AppRun` from there. See
+// https://github.com/AppImage/AppImageSpec/blob/master/draft.md for details.
+use crate::{
+    binary,
+    limits::ExtractionBudget,
+    safety::{check_extraction_path, check_member, MemberKind},
+};
+use anyhow::{anyhow, Context, Result};
+use backhand::{
+    compression::{CompressionAction, Compressor},
+    kind::Kind,
+    BackhandError, FilesystemCompressor, FilesystemReader, InnerNode, SuperBlock,
+};
+use flate2::read::ZlibDecoder;
+use log::debug;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+use xz2::{read::XzDecoder, stream::Stream as XzStream};
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+// This is at offset 8 in the ELF header, in bytes that the ELF spec leaves unused.
+const APPIMAGE_TYPE_2_MAGIC: &[u8; 3] = b"AI\x02";
+const SQUASHFS_MAGIC: &[u8; 4] = b"hsqs";
+
+pub(crate) const APP_RUN: &str = "AppRun";
+
+// Returns true if the file at `path` is a type 2 AppImage, which is the only type that uses
+// SquashFS.
+pub(crate) fn is_appimage(path: &Path) -> Result<bool> {
+    let mut header = [0u8; 11];
+    let mut file =
+        File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    match file.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+    }
+    Ok(header.starts_with(b"\x7fELF") && &header[8..] == APPIMAGE_TYPE_2_MAGIC)
+}
+
+// Extracts the filesystem embedded in the AppImage at `appimage` into `dir`, which must not exist
+// yet. Each node is checked with the same rules we use when extracting an entire archive, including
+// making sure that it isn't written through a symlink extracted before it, and the extracted files
+// count against `budget`.
+pub(crate) fn extract(appimage: &Path, dir: &Path, budget: &ExtractionBudget) -> Result<()> {
+    debug!(
+        "extracting AppImage filesystem from {} into {}",
+        appimage.display(),
+        dir.display()
+    );
+
+    let mut reader = BufReader::new(
+        File::open(appimage).with_context(|| format!("failed to open {}", appimage.display()))?,
+    );
+    let offset = squashfs_offset(&mut reader)
+        .with_context(|| format!("failed to find the filesystem in {}", appimage.display()))?;
+    debug!("AppImage filesystem starts at offset {offset}");
+
+    let filesystem = FilesystemReader::from_reader_with_offset_and_kind(
+        reader,
+        offset,
+        Kind::new(&Decompressor),
+    )
+    .with_context(|| {
+        format!(
+            "failed to read the SquashFS filesystem in {}",
+            appimage.display()
+        )
+    })?;
+
+    fs::create_dir_all(dir)
+        .with_context(|| format!("could not create a directory at {}", dir.display()))?;
+    for node in filesystem.files() {
+        budget.add_entry()?;
+        let Some(relative) = relative_path(&node.fullpath) else {
+            continue;
+        };
+        let kind = match &node.inner {
+            InnerNode::Dir(_) => MemberKind::Dir,
+            InnerNode::File(_) => MemberKind::File,
+            InnerNode::Symlink(symlink) => MemberKind::Symlink(&symlink.link),
+            InnerNode::CharacterDevice(_) => MemberKind::Special("character device"),
+            InnerNode::BlockDevice(_) => MemberKind::Special("block device"),
+            InnerNode::NamedPipe => MemberKind::Special("FIFO"),
+            InnerNode::Socket => MemberKind::Special("socket"),
+        };
+        let not_safe = || {
+            format!(
+                "the AppImage at {} is not safe to extract",
+                appimage.display()
+            )
+        };
+        let relative = check_member(&relative, kind, Some(u32::from(node.header.permissions)))
+            .with_context(not_safe)?;
+        let path = check_extraction_path(dir, &relative).with_context(not_safe)?;
+        match &node.inner {
+            InnerNode::Dir(_) => {
+                fs::create_dir_all(&path).with_context(|| {
+                    format!("could not create a directory at {}", path.display())
+                })?;
+            }
+            InnerNode::File(file) => {
+                create_parent_dir(&path)?;
+                let mut writer = File::create(&path)
+                    .with_context(|| format!("failed to create {}", path.display()))?;
+                io::copy(
+                    &mut budget.reader(&relative, filesystem.file(file).reader()),
+                    &mut writer,
+                )
+                .with_context(|| {
+                    format!(
+                        "failed to extract {} from {}",
+                        relative.display(),
+                        appimage.display()
+                    )
+                })?;
+                set_permissions(&path, node.header.permissions)?;
+            }
+            InnerNode::Symlink(symlink) => {
+                create_parent_dir(&path)?;
+                make_symlink(&symlink.link, &path)?;
+            }
+            // `check_member` has already rejected these.
+            InnerNode::CharacterDevice(_)
+            | InnerNode::BlockDevice(_)
+            | InnerNode::NamedPipe
+            | InnerNode::Socket => {}
+        }
+    }
+
+    if fs::symlink_metadata(dir.join(APP_RUN)).is_err() {
+        return Err(anyhow!(
+            "the AppImage at {} does not contain an {APP_RUN} file",
+            appimage.display()
+        ));
+    }
+
+    Ok(())
+}
+
+// The filesystem starts right after the AppImage runtime, which is an ELF file.
+fn squashfs_offset(reader: &mut (impl Read + Seek)) -> Result<u64> {
+    let mut header = vec![];
+    reader
+        .by_ref()
+        .take(binary::HEADER_LEN)
+        .read_to_end(&mut header)?;
+    let offset = binary::elf_end(&header)
+        .ok_or_else(|| anyhow!("could not parse the ELF header of the AppImage runtime"))?;
+
+    let mut magic = [0u8; 4];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut magic)?;
+    if &magic != SQUASHFS_MAGIC {
+        return Err(anyhow!(
+            "there is no SquashFS filesystem after the AppImage runtime at offset {offset}"
+        ));
+    }
+
+    Ok(offset)
+}
+
+// Node paths are absolute, with `/` being the filesystem's root, so we strip the root to get a path
+// relative to the directory we're extracting into. This returns `None` for the root itself.
+fn relative_path(path: &Path) -> Option<PathBuf> {
+    let relative = path.strip_prefix("/").unwrap_or(path);
+    (!relative.as_os_str().is_empty()).then(|| relative.to_path_buf())
+}
+
+fn create_parent_dir(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("could not create a directory at {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(target_family = "unix")]
+fn set_permissions(path: &Path, mode: u16) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(u32::from(mode) & 0o777))
+        .with_context(|| format!("failed to set permissions on {}", path.display()))
+}
+
+#[cfg(target_family = "windows")]
+fn set_permissions(_path: &Path, _mode: u16) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(target_family = "unix")]
+fn make_symlink(target: &Path, path: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, path).with_context(|| {
+        format!(
+            "failed to create a symlink at {} pointing to {}",
+            path.display(),
+            target.display()
+        )
+    })
+}
+
+// AppImages only run on Linux, so there's no point in trying to make symlinks on Windows, which
+// requires special privileges.
+#[cfg(target_family = "windows")]
+fn make_symlink(target: &Path, path: &Path) -> Result<()> {
+    debug!(
+        "skipping symlink at {} pointing to {}",
+        path.display(),
+        target.display()
+    );
+    Ok(())
+}
+
+// `backhand` can decompress some formats itself, but we use the same decompression crates as the
+// rest of `ubi` instead. This keeps us from linking two copies of liblzma.
+struct Decompressor;
+
+impl CompressionAction for Decompressor {
+    fn decompress(
+        &self,
+        bytes: &[u8],
+        out: &mut Vec<u8>,
+        compressor: Compressor,
+    ) -> Result<(), BackhandError> {
+        match compressor {
+            Compressor::None => out.extend_from_slice(bytes),
+            Compressor::Gzip => {
+                ZlibDecoder::new(bytes).read_to_end(out)?;
+            }
+            Compressor::Xz => {
+                XzDecoder::new(bytes).read_to_end(out)?;
+            }
+            Compressor::Lzma => {
+                let stream = XzStream::new_lzma_decoder(u64::MAX).map_err(io::Error::other)?;
+                XzDecoder::new_stream(bytes, stream).read_to_end(out)?;
+            }
+            Compressor::Zstd => {
+                ZstdDecoder::new(bytes)?.read_to_end(out)?;
+            }
+            Compressor::Lz4 => {
+                // The output buffer's capacity is the filesystem's block size, which is the most that
+                // a block can decompress to.
+                out.resize(out.capacity(), 0);
+                let len = lz4_flex::block::decompress_into(bytes, out)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                out.truncate(len);
+            }
+            Compressor::Lzo => return Err(BackhandError::UnsupportedCompression(compressor)),
+        }
+        Ok(())
+    }
+
+    fn compress(
+        &self,
+        _bytes: &[u8],
+        _fc: FilesystemCompressor,
+        _block_size: u32,
+    ) -> Result<Vec<u8>, BackhandError> {
+        Err(cannot_write())
+    }
+
+    fn compression_options(
+        &self,
+        _superblock: &mut SuperBlock,
+        _kind: &Kind,
+        _fs_compressor: FilesystemCompressor,
+    ) -> Result<Vec<u8>, BackhandError> {
+        Err(cannot_write())
+    }
+}
+
+// We only ever read SquashFS filesystems, so `backhand` should never call our compression methods.
+fn cannot_write() -> BackhandError {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "ubi cannot write SquashFS filesystems",
+    )
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::ExtractionLimits;
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    #[rstest]
+    #[case::gzip("test-data/project-squashfs-gzip.AppImage")]
+    #[case::xz("test-data/project-squashfs-xz.AppImage")]
+    #[case::zstd("test-data/project-squashfs-zstd.AppImage")]
+    fn extract(#[case] appimage: &str) -> Result<()> {
+        crate::test_log::init_logging();
+
+        assert!(is_appimage(Path::new(appimage))?);
+
+        let td = tempdir()?;
+        let dir = td.path().join("project");
+        super::extract(Path::new(appimage), &dir, &ExtractionBudget::default())?;
+
+        assert!(fs::read_to_string(dir.join(APP_RUN))?.starts_with("#!/bin/sh"));
+        assert_eq!(
+            fs::read_to_string(dir.join("usr/bin/project"))?,
+            "#!/bin/sh\necho project 1.2.3\n",
+        );
+        #[cfg(target_family = "unix")]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = |p: &str| -> Result<u32> {
+                Ok(fs::metadata(dir.join(p))?.permissions().mode() & 0o777)
+            };
+            assert_eq!(mode(APP_RUN)?, 0o755);
+            assert_eq!(mode("project.desktop")?, 0o644);
+            assert_eq!(
+                fs::read_link(dir.join("usr/bin/project-link"))?,
+                Path::new("project"),
+            );
+        }
+
+        Ok(())
+    }
+
+    // The filesystem in this AppImage has two entries named `x` in its root, a symlink to `.` and a
+    // directory, and the directory has two entries named `y`, a symlink to `..` and a directory
+    // containing `evil`. Each node is safe on its own, but extracting them all in order would
+    // write `evil` to the parent of the directory we extract into.
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn extract_rejects_chained_symlinks() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let res = super::extract(
+            Path::new("test-data/project-squashfs-with-chained-symlinks.AppImage"),
+            &td.path().join("project"),
+            &ExtractionBudget::default(),
+        );
+
+        let err = format!("{:#}", res.unwrap_err());
+        let expect_err =
+            "the archive member x would be written through a symlink extracted before it";
+        assert!(
+            err.contains(expect_err),
+            "error `{err}` contains `{expect_err}`"
+        );
+        assert!(!td.path().join("evil").exists(), "nothing escaped the root");
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::fake_appimage("test-data/project.AppImage")]
+    #[case::elf("test-data/project-linux-x86-64")]
+    fn is_not_appimage(#[case] path: &str) -> Result<()> {
+        assert!(!is_appimage(Path::new(path))?);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::file_bytes(
+        ExtractionLimits { file_bytes: Some(10), ..Default::default() },
+        "for a single extracted file"
+    )]
+    #[case::total_bytes(
+        ExtractionLimits { total_bytes: Some(10), ..Default::default() },
+        "for all extracted files"
+    )]
+    #[case::entries(
+        ExtractionLimits { entries: Some(2), ..Default::default() },
+        "more than the limit of 2 entries"
+    )]
+    fn extract_respects_limits(#[case] limits: ExtractionLimits, #[case] msg: &str) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let res = super::extract(
+            Path::new("test-data/project-squashfs-gzip.AppImage"),
+            &td.path().join("project"),
+            &ExtractionBudget::new(limits),
+        );
+        let err = format!("{:#}", res.unwrap_err());
+        assert!(err.contains(msg), "error `{err}` contains `{msg}`");
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::root("/", None)]
+    #[case::file("/usr/bin/project", Some("usr/bin/project"))]
+    fn relative_path(#[case] path: &str, #[case] expect: Option<&str>) {
+        assert_eq!(
+            super::relative_path(Path::new(path)),
+            expect.map(PathBuf::from)
+        );
+    }
+}
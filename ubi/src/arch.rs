@@ -1,5 +1,6 @@
 use itertools::Itertools;
 use lazy_regex::{regex, Lazy};
+use platforms::{Arch, Endian};
 use regex::Regex;
 use std::sync::LazyLock;
 
@@ -422,6 +423,32 @@ pub(crate) fn cpu_64_bit_re() -> &'static Lazy<Regex> {
     )
 }
 
+// Returns the regex that matches names for the given CPU architecture, or `None` if this is an
+// architecture we don't know how to match.
+pub(crate) fn arch_re(arch: Arch, endian: Endian) -> Option<&'static Lazy<Regex>> {
+    Some(match (arch, endian) {
+        (Arch::AArch64, _) => aarch64_re(),
+        (Arch::Arm, _) => arm_re(),
+        (Arch::Mips, Endian::Little) => mipsle_re(),
+        (Arch::Mips, Endian::Big) => mips_re(),
+        (Arch::Mips64, Endian::Little) => mips64le_re(),
+        (Arch::Mips64, Endian::Big) => mips64_re(),
+        (Arch::PowerPc, _) => ppc32_re(),
+        (Arch::PowerPc64, Endian::Big) => ppc64_re(),
+        (Arch::PowerPc64, Endian::Little) => ppc64le_re(),
+        //(Arch::Riscv32, _) => regex!(r"(?i:(?:\b|_)riscv(?:32)?(?:\b|_))"),
+        (Arch::Riscv64, _) => riscv64_re(),
+        (Arch::S390X, _) => s390x_re(),
+        // Sparc is not supported by Go. 32-bit Sparc is not supported
+        // by Rust, AFAICT.
+        //(Arch::Sparc, _) => regex!(r"(?i:(?:\b|_)sparc(?:\b|_))"),
+        (Arch::Sparc64, _) => sparc64_re(),
+        (Arch::X86, _) => x86_32_re(),
+        (Arch::X86_64, _) => x86_64_re(),
+        _ => return None,
+    })
+}
+
 pub(crate) static ALL_ARCHES_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(
         &[
@@ -2,14 +2,74 @@
 //
 // It provides traits that archive file are then implemented for various archive file types. This
 // makes it easier to add support for new archive formats in the future.
+use crate::{
+    binary,
+    cpio::{CpioArchive, CpioEntry},
+};
 use anyhow::{Context, Result};
 use std::io::{self, Read};
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
 pub(crate) trait ArchiveEntry {
     fn path(&self) -> Result<PathBuf>;
     fn is_file(&self) -> bool;
     fn is_executable(&self) -> Result<Option<bool>>;
+    fn unix_mode(&self) -> Result<Option<u32>>;
+    fn link(&self) -> Result<Option<ArchiveLink>>;
+    // Returns the first `binary::HEADER_LEN` bytes of a file entry's content, so we can check
+    // whether it's a binary executable. This returns `None` if the entry isn't a file or if its
+    // content can't be read cheaply.
+    fn header(&mut self) -> Result<Option<Vec<u8>>>;
+    // Returns a reader for the rest of a file entry's content, after the bytes returned by
+    // `header`. This is only available for archives that we can't seek in, like compressed
+    // tarballs, where the content can only be read while we're looking at the entry.
+    fn rest_of_content(&mut self) -> Option<&mut dyn Read> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ArchiveLink {
+    // The target of a symlink is relative to the directory containing the link.
+    Symlink(PathBuf),
+    // The target of a hard link is relative to the root of the archive.
+    HardLink(PathBuf),
+}
+
+impl ArchiveLink {
+    // Returns the normalized path of the link's target within the archive, or `None` if the target
+    // points outside of the archive.
+    pub(crate) fn target_path(&self, link_path: &Path) -> Option<PathBuf> {
+        match self {
+            ArchiveLink::Symlink(target) => {
+                if target.is_absolute() {
+                    return None;
+                }
+                normalize_member_path(&link_path.parent().unwrap_or(Path::new("")).join(target))
+            }
+            ArchiveLink::HardLink(target) => normalize_member_path(target),
+        }
+    }
+}
+
+// Archive member paths can contain things like a leading `./` or `..` components. This normalizes
+// the path so that we can compare paths to each other when resolving links. It returns `None` if
+// the path is absolute or if it would go outside of the archive's root.
+pub(crate) fn normalize_member_path(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => normalized.push(c),
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
 }
 
 pub(crate) struct TarEntriesIterator<'a, R: Read> {
@@ -47,14 +107,51 @@ impl<R: Read> ArchiveEntry for binstall_tar::Entry<'_, R> {
     }
 
     fn is_executable(&self) -> Result<Option<bool>> {
+        Ok(self.unix_mode()?.map(|mode| mode & 0o111 != 0))
+    }
+
+    fn unix_mode(&self) -> Result<Option<u32>> {
         Ok(Some(
             self.header()
                 .mode()
-                .context("failed to get mode from tar entry header")?
-                & 0o111
-                != 0,
+                .context("failed to get mode from tar entry header")?,
         ))
     }
+
+    fn link(&self) -> Result<Option<ArchiveLink>> {
+        let entry_type = self.header().entry_type();
+        if !(entry_type.is_symlink() || entry_type.is_hard_link()) {
+            return Ok(None);
+        }
+
+        let Some(target) = self
+            .link_name()
+            .context("failed to get link name from tar entry")?
+        else {
+            return Ok(None);
+        };
+        let target = target.to_path_buf();
+
+        Ok(Some(if entry_type.is_symlink() {
+            ArchiveLink::Symlink(target)
+        } else {
+            ArchiveLink::HardLink(target)
+        }))
+    }
+
+    fn header(&mut self) -> Result<Option<Vec<u8>>> {
+        if !ArchiveEntry::is_file(self) {
+            return Ok(None);
+        }
+        read_header(self).map(Some)
+    }
+
+    fn rest_of_content(&mut self) -> Option<&mut dyn Read> {
+        if !ArchiveEntry::is_file(self) {
+            return None;
+        }
+        Some(self)
+    }
 }
 
 pub(crate) struct SevenZipEntriesIterator<R: Read + io::Seek> {
@@ -98,20 +195,71 @@ impl ArchiveEntry for sevenz_rust2::ArchiveEntry {
     }
 
     fn is_executable(&self) -> Result<Option<bool>> {
-        // SevenZip entries do not mark whether something is executable.
+        Ok(self.unix_mode()?.map(|mode| mode & 0o111 != 0))
+    }
+
+    fn unix_mode(&self) -> Result<Option<u32>> {
+        Ok(sevenz_unix_mode(self))
+    }
+
+    fn link(&self) -> Result<Option<ArchiveLink>> {
+        // A symlink in a 7z file is stored as a file containing the link's target, so we'd need to
+        // decompress the entry to resolve it.
         Ok(None)
     }
+
+    fn header(&mut self) -> Result<Option<Vec<u8>>> {
+        // 7z files usually compress all of their entries as a single stream, so reading the start
+        // of an entry can mean decompressing everything before it.
+        Ok(None)
+    }
+}
+
+// This is the `FILE_ATTRIBUTE_UNIX_EXTENSION` flag used by p7zip and 7-Zip. When it's set, the high
+// 16 bits of the attributes contain the Unix mode of the file.
+const SEVENZ_UNIX_EXTENSION: u32 = 0x8000;
+
+pub(crate) fn sevenz_unix_mode(entry: &sevenz_rust2::ArchiveEntry) -> Option<u32> {
+    if !entry.has_windows_attributes {
+        return None;
+    }
+    let attributes = entry.windows_attributes();
+    if attributes & SEVENZ_UNIX_EXTENSION == 0 {
+        return None;
+    }
+    Some(attributes >> 16)
+}
+
+// Returns the 7z password to use when opening an archive. Unencrypted archives ignore this, so we
+// can always pass it.
+pub(crate) fn sevenz_password(password: Option<&str>) -> sevenz_rust2::Password {
+    password.map_or_else(sevenz_rust2::Password::empty, sevenz_rust2::Password::new)
+}
+
+// Returns the zip entry at `index`, decrypting it with `password` if one is given. The `zip` crate
+// ignores the password for entries that are not encrypted.
+pub(crate) fn zip_entry<'a, R: Read + io::Seek>(
+    archive: &'a mut zip::ZipArchive<R>,
+    index: usize,
+    password: Option<&str>,
+) -> zip::result::ZipResult<zip::read::ZipFile<'a, R>> {
+    match password {
+        Some(password) => archive.by_index_decrypt(index, password.as_bytes()),
+        None => archive.by_index(index),
+    }
 }
 
 pub(crate) struct ZipEntriesIterator<'a, R: Read + io::Seek> {
     archive: &'a mut zip::ZipArchive<R>,
+    password: Option<&'a str>,
     current_index: usize,
 }
 
 impl<'a, R: Read + io::Seek> ZipEntriesIterator<'a, R> {
-    pub(crate) fn new(archive: &'a mut zip::ZipArchive<R>) -> Self {
+    pub(crate) fn new(archive: &'a mut zip::ZipArchive<R>, password: Option<&'a str>) -> Self {
         Self {
             archive,
+            password,
             current_index: 0,
         }
     }
@@ -125,19 +273,44 @@ impl<R: Read + io::Seek> Iterator for ZipEntriesIterator<'_, R> {
             return None;
         }
 
-        let result = self
-            .archive
-            .by_index(self.current_index)
-            .map(|file| OwnedZipEntry {
-                name: file.name().to_string(),
-                is_file: file.is_file(),
+        let result = zip_entry(self.archive, self.current_index, self.password)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut file| {
+                // A symlink in a zip file is stored as a file containing the link's target.
+                let link = if file.is_symlink() {
+                    let mut target = String::new();
+                    file.read_to_string(&mut target).with_context(|| {
+                        format!(
+                            "failed to read symlink target for zip entry {}",
+                            file.name()
+                        )
+                    })?;
+                    Some(ArchiveLink::Symlink(PathBuf::from(target)))
+                } else {
+                    None
+                };
+                let header = if link.is_none() && file.is_file() {
+                    Some(read_header(&mut file).with_context(|| {
+                        format!("failed to read the start of zip entry {}", file.name())
+                    })?)
+                } else {
+                    None
+                };
+
+                Ok(OwnedZipEntry {
+                    name: file.name().to_string(),
+                    is_file: file.is_file(),
+                    unix_mode: file.unix_mode(),
+                    link,
+                    header,
+                })
             });
 
         self.current_index += 1;
 
         match result {
             Ok(entry) => Some(Ok(Box::new(entry))),
-            Err(e) => Some(Err(e.into())),
+            Err(e) => Some(Err(e)),
         }
     }
 }
@@ -146,6 +319,9 @@ impl<R: Read + io::Seek> Iterator for ZipEntriesIterator<'_, R> {
 pub(crate) struct OwnedZipEntry {
     name: String,
     is_file: bool,
+    unix_mode: Option<u32>,
+    link: Option<ArchiveLink>,
+    header: Option<Vec<u8>>,
 }
 
 impl ArchiveEntry for OwnedZipEntry {
@@ -158,7 +334,147 @@ impl ArchiveEntry for OwnedZipEntry {
     }
 
     fn is_executable(&self) -> Result<Option<bool>> {
-        // Zip entries do not mark whether something is executable.
-        Ok(None)
+        // Zip files created on Unix record the file's mode, but the `zip` crate synthesizes a mode
+        // of 0o664 for entries created on other systems, and it doesn't tell us which system
+        // created an entry. So we can only trust the mode when it says the entry is executable.
+        Ok(match self.unix_mode {
+            Some(mode) if mode & 0o111 != 0 => Some(true),
+            _ => None,
+        })
+    }
+
+    fn unix_mode(&self) -> Result<Option<u32>> {
+        Ok(self.unix_mode)
+    }
+
+    fn link(&self) -> Result<Option<ArchiveLink>> {
+        Ok(self.link.clone())
+    }
+
+    fn header(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(self.header.take())
+    }
+}
+
+pub(crate) struct CpioEntriesIterator<'a, R: Read> {
+    archive: &'a mut CpioArchive<R>,
+}
+
+impl<'a, R: Read> CpioEntriesIterator<'a, R> {
+    pub(crate) fn new(archive: &'a mut CpioArchive<R>) -> Self {
+        Self { archive }
+    }
+}
+
+impl<R: Read> Iterator for CpioEntriesIterator<'_, R> {
+    type Item = Result<Box<dyn ArchiveEntry>, anyhow::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.archive.next_entry() {
+            Ok(Some(entry)) => entry,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(e)),
+        };
+        let header = if entry.is_file() {
+            match read_header(&mut self.archive.content()) {
+                Ok(header) => Some(header),
+                Err(e) => {
+                    return Some(Err(e.context(format!(
+                        "failed to read the start of cpio entry {}",
+                        entry.path.display()
+                    ))))
+                }
+            }
+        } else {
+            None
+        };
+
+        Some(Ok(Box::new(OwnedCpioEntry { entry, header })))
+    }
+}
+
+// The content of a cpio entry is only available until we move on to the next entry, so we read the
+// header while we can.
+#[derive(Debug, Clone)]
+pub(crate) struct OwnedCpioEntry {
+    entry: CpioEntry,
+    header: Option<Vec<u8>>,
+}
+
+impl ArchiveEntry for OwnedCpioEntry {
+    fn path(&self) -> Result<PathBuf> {
+        Ok(self.entry.path.clone())
+    }
+
+    fn is_file(&self) -> bool {
+        self.entry.is_file()
+    }
+
+    fn is_executable(&self) -> Result<Option<bool>> {
+        Ok(self.unix_mode()?.map(|mode| mode & 0o111 != 0))
+    }
+
+    fn unix_mode(&self) -> Result<Option<u32>> {
+        Ok(Some(self.entry.mode & 0o7777))
+    }
+
+    fn link(&self) -> Result<Option<ArchiveLink>> {
+        Ok(self.entry.link_target.clone().map(ArchiveLink::Symlink))
+    }
+
+    fn header(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(self.header.take())
+    }
+}
+
+fn read_header(reader: &mut impl Read) -> Result<Vec<u8>> {
+    let mut header = vec![];
+    reader
+        .take(binary::HEADER_LEN)
+        .read_to_end(&mut header)
+        .context("failed to read the start of an archive entry")?;
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::symlink_in_same_dir(
+        ArchiveLink::Symlink(PathBuf::from("tool-1.2.3")),
+        "project/bin/tool",
+        Some("project/bin/tool-1.2.3")
+    )]
+    #[case::symlink_to_parent_dir(
+        ArchiveLink::Symlink(PathBuf::from("../libexec/tool-1.2.3")),
+        "./project/bin/tool",
+        Some("project/libexec/tool-1.2.3")
+    )]
+    #[case::symlink_outside_of_archive(
+        ArchiveLink::Symlink(PathBuf::from("../../../usr/bin/tool")),
+        "project/bin/tool",
+        None
+    )]
+    #[case::absolute_symlink(
+        ArchiveLink::Symlink(PathBuf::from("/usr/bin/tool")),
+        "project/bin/tool",
+        None
+    )]
+    #[case::hard_link(
+        ArchiveLink::HardLink(PathBuf::from("./project/libexec/tool-1.2.3")),
+        "project/bin/tool",
+        Some("project/libexec/tool-1.2.3")
+    )]
+    fn link_target_path(
+        #[case] link: ArchiveLink,
+        #[case] link_path: &str,
+        #[case] expect: Option<&str>,
+    ) {
+        assert_eq!(
+            link.target_path(Path::new(link_path)),
+            expect.map(PathBuf::from),
+        );
     }
 }
@@ -0,0 +1,557 @@
+// Asset names aren't always accurate, and our heuristics for picking an asset can be fooled, so
+// after installing an executable we look at its header to check that it can run on the platform we
+// installed it for. We recognize ELF, Mach-O (including universal binaries), and PE executables. Any
+// other file, like a script or a jar, is not checked.
+//
+// We only need a handful of fields from each format, so we parse them by hand. See
+// https://refspecs.linuxfoundation.org/elf/gabi4+/ch4.eheader.html,
+// https://github.com/apple-oss-distributions/xnu/blob/main/EXTERNAL_HEADERS/mach-o/loader.h, and
+// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format for details.
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use log::debug;
+use platforms::{Arch, Endian, Platform, PointerWidth, OS};
+use std::{fmt, fs::File, io::Read, path::Path};
+
+// This is enough to see the headers of all the formats we recognize, including the PE header, which
+// starts at an offset given in the DOS header. In practice that offset is always well under 4KiB.
+pub(crate) const HEADER_LEN: u64 = 4096;
+
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const ELF_CLASS_32: u8 = 1;
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_LSB: u8 = 1;
+const ELF_DATA_MSB: u8 = 2;
+
+const MACHO_MAGIC_32: u32 = 0xfeed_face;
+const MACHO_MAGIC_64: u32 = 0xfeed_facf;
+const MACHO_FAT_MAGIC: u32 = 0xcafe_babe;
+const MACHO_FAT_MAGIC_64: u32 = 0xcafe_babf;
+const MACHO_CPU_ARCH_ABI64: u32 = 0x0100_0000;
+// Java class files start with the same magic number as a Mach-O universal binary. The next field is
+// the class file version, which is always much larger than the number of architectures in a
+// universal binary. The `file` command uses the same check to tell them apart.
+const MACHO_MAX_FAT_ARCHES: u32 = 20;
+
+const DOS_MAGIC: &[u8; 2] = b"MZ";
+const DOS_PE_OFFSET: usize = 0x3c;
+const PE_MAGIC: &[u8; 4] = b"PE\0\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Elf,
+    MachO,
+    Pe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Target {
+    arch: Arch,
+    pointer_width: PointerWidth,
+    endian: Endian,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct BinaryHeader {
+    format: Format,
+    // This is only set for ELF binaries that say which OS they're for. Many don't, in which case
+    // they could be for any OS that uses ELF.
+    os: Option<OS>,
+    // A Mach-O universal binary has more than one target.
+    targets: Vec<Target>,
+}
+
+// Returns a description of why the executable at `path` cannot run on `platform`, or `None` if it
+// can run there or if it's not a binary that we know how to check.
+pub(crate) fn platform_mismatch(path: &Path, platform: &Platform) -> Result<Option<String>> {
+    let mut header = vec![];
+    File::open(path)
+        .and_then(|f| f.take(HEADER_LEN).read_to_end(&mut header))
+        .with_context(|| format!("failed to read the start of {}", path.display()))?;
+
+    let Some(binary) = parse_header(&header) else {
+        debug!(
+            "{} is not a binary executable that we recognize, so its platform will not be checked",
+            path.display(),
+        );
+        return Ok(None);
+    };
+    debug!("{} is {binary}", path.display());
+
+    if binary.runs_on(platform) {
+        return Ok(None);
+    }
+    Ok(Some(format!(
+        "the executable at {} is {binary}, which cannot run on the requested platform, {}",
+        path.display(),
+        platform.target_triple,
+    )))
+}
+
+// Returns whether the binary that starts with `header` can run on `platform`, or `None` if it's not
+// a binary that we know how to check.
+pub(crate) fn runs_on_platform(header: &[u8], platform: &Platform) -> Option<bool> {
+    parse_header(header).map(|binary| binary.runs_on(platform))
+}
+
+fn parse_header(header: &[u8]) -> Option<BinaryHeader> {
+    if header.starts_with(ELF_MAGIC) {
+        parse_elf_header(header)
+    } else if header.starts_with(DOS_MAGIC) {
+        parse_pe_header(header)
+    } else {
+        parse_macho_header(header)
+    }
+}
+
+fn parse_elf_header(header: &[u8]) -> Option<BinaryHeader> {
+    let pointer_width = match *header.get(4)? {
+        ELF_CLASS_32 => PointerWidth::U32,
+        ELF_CLASS_64 => PointerWidth::U64,
+        _ => return None,
+    };
+    let endian = match *header.get(5)? {
+        ELF_DATA_LSB => Endian::Little,
+        ELF_DATA_MSB => Endian::Big,
+        _ => return None,
+    };
+    let os = match *header.get(7)? {
+        // This is `ELFOSABI_GNU`, which is set by binaries that use GNU extensions.
+        3 => Some(OS::Linux),
+        2 => Some(OS::NetBSD),
+        6 => Some(OS::Solaris),
+        9 => Some(OS::FreeBSD),
+        12 => Some(OS::OpenBSD),
+        _ => None,
+    };
+
+    let machine = read_u16(header, 18, endian)?;
+    let is_64 = pointer_width == PointerWidth::U64;
+    let arch = match machine {
+        2 | 18 => Arch::Sparc,
+        3 => Arch::X86,
+        8 if is_64 => Arch::Mips64,
+        8 => Arch::Mips,
+        20 => Arch::PowerPc,
+        21 => Arch::PowerPc64,
+        22 => Arch::S390X,
+        40 => Arch::Arm,
+        43 => Arch::Sparc64,
+        // The x32 ABI uses 32-bit pointers with the x86-64 instruction set.
+        62 => Arch::X86_64,
+        183 => Arch::AArch64,
+        243 if is_64 => Arch::Riscv64,
+        243 => Arch::Riscv32,
+        258 => Arch::Loongarch64,
+        _ => {
+            debug!("unknown ELF machine type {machine}");
+            return None;
+        }
+    };
+
+    Some(BinaryHeader {
+        format: Format::Elf,
+        os,
+        targets: vec![Target {
+            arch,
+            pointer_width,
+            endian,
+        }],
+    })
+}
+
+// Returns the offset of the end of the ELF file whose header is in `header`, which is the end of its
+// section header table. Anything after this, like the filesystem image in an AppImage, isn't part
+// of the ELF file.
+pub(crate) fn elf_end(header: &[u8]) -> Option<u64> {
+    if header.get(..ELF_MAGIC.len())? != ELF_MAGIC {
+        return None;
+    }
+    let endian = match *header.get(5)? {
+        ELF_DATA_LSB => Endian::Little,
+        ELF_DATA_MSB => Endian::Big,
+        _ => return None,
+    };
+    let (sh_offset, sh_entry_size, sh_count) = match *header.get(4)? {
+        ELF_CLASS_32 => (
+            u64::from(read_u32(header, 0x20, endian)?),
+            read_u16(header, 0x2e, endian)?,
+            read_u16(header, 0x30, endian)?,
+        ),
+        ELF_CLASS_64 => (
+            read_u64(header, 0x28, endian)?,
+            read_u16(header, 0x3a, endian)?,
+            read_u16(header, 0x3c, endian)?,
+        ),
+        _ => return None,
+    };
+    sh_offset.checked_add(u64::from(sh_entry_size) * u64::from(sh_count))
+}
+
+fn parse_macho_header(header: &[u8]) -> Option<BinaryHeader> {
+    let magic = read_u32(header, 0, Endian::Big)?;
+    let targets = if magic == MACHO_FAT_MAGIC || magic == MACHO_FAT_MAGIC_64 {
+        let count = read_u32(header, 4, Endian::Big)?;
+        if count == 0 || count > MACHO_MAX_FAT_ARCHES {
+            return None;
+        }
+        let entry_len = if magic == MACHO_FAT_MAGIC { 20 } else { 32 };
+        (0..usize::try_from(count).ok()?)
+            .map(|i| read_u32(header, 8 + i * entry_len, Endian::Big).and_then(macho_target))
+            .collect::<Option<Vec<_>>>()?
+    } else if magic == MACHO_MAGIC_32 || magic == MACHO_MAGIC_64 {
+        vec![macho_target(read_u32(header, 4, Endian::Big)?)?]
+    } else if magic.swap_bytes() == MACHO_MAGIC_32 || magic.swap_bytes() == MACHO_MAGIC_64 {
+        vec![macho_target(read_u32(header, 4, Endian::Little)?)?]
+    } else {
+        return None;
+    };
+
+    Some(BinaryHeader {
+        format: Format::MachO,
+        os: Some(OS::MacOS),
+        targets,
+    })
+}
+
+fn macho_target(cpu_type: u32) -> Option<Target> {
+    let is_64 = cpu_type & MACHO_CPU_ARCH_ABI64 != 0;
+    let (arch, endian) = match (cpu_type & !MACHO_CPU_ARCH_ABI64, is_64) {
+        (7, true) => (Arch::X86_64, Endian::Little),
+        (7, false) => (Arch::X86, Endian::Little),
+        (12, true) => (Arch::AArch64, Endian::Little),
+        (12, false) => (Arch::Arm, Endian::Little),
+        (18, true) => (Arch::PowerPc64, Endian::Big),
+        (18, false) => (Arch::PowerPc, Endian::Big),
+        _ => {
+            debug!("unknown Mach-O CPU type {cpu_type:#x}");
+            return None;
+        }
+    };
+    let pointer_width = if is_64 {
+        PointerWidth::U64
+    } else {
+        PointerWidth::U32
+    };
+
+    Some(Target {
+        arch,
+        pointer_width,
+        endian,
+    })
+}
+
+fn parse_pe_header(header: &[u8]) -> Option<BinaryHeader> {
+    let pe_offset = usize::try_from(read_u32(header, DOS_PE_OFFSET, Endian::Little)?).ok()?;
+    if !header.get(pe_offset..)?.starts_with(PE_MAGIC) {
+        return None;
+    }
+
+    let machine = read_u16(header, pe_offset + PE_MAGIC.len(), Endian::Little)?;
+    let (arch, pointer_width) = match machine {
+        0x014c => (Arch::X86, PointerWidth::U32),
+        0x01c0 | 0x01c4 => (Arch::Arm, PointerWidth::U32),
+        0x8664 => (Arch::X86_64, PointerWidth::U64),
+        0xaa64 => (Arch::AArch64, PointerWidth::U64),
+        _ => {
+            debug!("unknown PE machine type {machine:#x}");
+            return None;
+        }
+    };
+
+    Some(BinaryHeader {
+        format: Format::Pe,
+        os: Some(OS::Windows),
+        targets: vec![Target {
+            arch,
+            pointer_width,
+            endian: Endian::Little,
+        }],
+    })
+}
+
+fn read_u16(header: &[u8], offset: usize, endian: Endian) -> Option<u16> {
+    let bytes = header.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if endian == Endian::Big {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(header: &[u8], offset: usize, endian: Endian) -> Option<u32> {
+    let bytes = header.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if endian == Endian::Big {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+fn read_u64(header: &[u8], offset: usize, endian: Endian) -> Option<u64> {
+    let bytes = header.get(offset..offset + 8)?.try_into().ok()?;
+    Some(if endian == Endian::Big {
+        u64::from_be_bytes(bytes)
+    } else {
+        u64::from_le_bytes(bytes)
+    })
+}
+
+impl BinaryHeader {
+    fn runs_on(&self, platform: &Platform) -> bool {
+        self.os_matches(platform.target_os) && self.targets.iter().any(|t| t.runs_on(platform))
+    }
+
+    fn os_matches(&self, os: OS) -> bool {
+        match self.format {
+            Format::Elf => match self.os {
+                // Android uses the Linux kernel. Illumos is descended from Solaris and uses the
+                // same ABI.
+                Some(OS::Linux) => os == OS::Linux || os == OS::Android,
+                Some(OS::Solaris) => os == OS::Solaris || os == OS::IllumOS,
+                Some(elf_os) => os == elf_os,
+                None => os != OS::MacOS && os != OS::Windows,
+            },
+            Format::MachO => os == OS::MacOS,
+            Format::Pe => os == OS::Windows,
+        }
+    }
+}
+
+impl Target {
+    fn runs_on(&self, platform: &Platform) -> bool {
+        if self.arch == platform.target_arch
+            && self.pointer_width == platform.target_pointer_width
+            && self.endian == platform.target_endian
+        {
+            return true;
+        }
+
+        // Some platforms can run binaries for another architecture, either natively or with an
+        // emulator that ships with the OS. macOS dropped support for 32-bit binaries in 10.15, so
+        // running x86 binaries on x86-64 only works elsewhere.
+        let os = platform.target_os;
+        let runs_emulated = match (self.arch, platform.target_arch) {
+            (Arch::X86, Arch::X86_64) => os != OS::MacOS,
+            (Arch::X86_64, Arch::AArch64) => os == OS::MacOS || os == OS::Windows,
+            (Arch::X86, Arch::AArch64) => os == OS::Windows,
+            _ => false,
+        };
+        if runs_emulated {
+            debug!(
+                "{} binaries can run on {} {} using emulation",
+                self.arch, platform.target_os, platform.target_arch,
+            );
+        }
+        runs_emulated
+    }
+}
+
+impl fmt::Display for BinaryHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = match self.format {
+            Format::Elf => "an ELF",
+            Format::MachO if self.targets.len() > 1 => "a Mach-O universal",
+            Format::MachO => "a Mach-O",
+            Format::Pe => "a PE",
+        };
+        write!(f, "{format} binary")?;
+        if let Some(os) = self.os {
+            write!(f, " for {os}")?;
+        }
+        write!(
+            f,
+            " ({})",
+            self.targets
+                .iter()
+                .map(|t| format!("{}, {}-bit, {}-endian", t.arch, t.pointer_width, t.endian))
+                .join("; "),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn elf_header(class: u8, data: u8, os_abi: u8, machine: u16) -> Vec<u8> {
+        let mut header = vec![0u8; 64];
+        header[0..4].copy_from_slice(ELF_MAGIC);
+        header[4] = class;
+        header[5] = data;
+        header[7] = os_abi;
+        let machine = if data == ELF_DATA_MSB {
+            machine.to_be_bytes()
+        } else {
+            machine.to_le_bytes()
+        };
+        header[18..20].copy_from_slice(&machine);
+        header
+    }
+
+    fn macho_header(cpu_types: &[u32]) -> Vec<u8> {
+        if let [cpu_type] = cpu_types {
+            let mut header = MACHO_MAGIC_64.to_le_bytes().to_vec();
+            header.extend(cpu_type.to_le_bytes());
+            header.resize(32, 0);
+            return header;
+        }
+
+        let mut header = MACHO_FAT_MAGIC.to_be_bytes().to_vec();
+        header.extend(u32::try_from(cpu_types.len()).unwrap().to_be_bytes());
+        for cpu_type in cpu_types {
+            header.extend(cpu_type.to_be_bytes());
+            header.extend([0u8; 16]);
+        }
+        header
+    }
+
+    fn pe_header(machine: u16) -> Vec<u8> {
+        let mut header = vec![0u8; 0x80];
+        header[0..2].copy_from_slice(DOS_MAGIC);
+        header[DOS_PE_OFFSET..DOS_PE_OFFSET + 4].copy_from_slice(&0x40u32.to_le_bytes());
+        header[0x40..0x44].copy_from_slice(PE_MAGIC);
+        header[0x44..0x46].copy_from_slice(&machine.to_le_bytes());
+        header
+    }
+
+    #[rstest]
+    #[case::linux_x86_64_on_linux_x86_64(
+        elf_header(ELF_CLASS_64, ELF_DATA_LSB, 0, 62),
+        "x86_64-unknown-linux-gnu",
+        true
+    )]
+    #[case::linux_abi_on_linux(
+        elf_header(ELF_CLASS_64, ELF_DATA_LSB, 3, 62),
+        "x86_64-unknown-linux-musl",
+        true
+    )]
+    #[case::linux_aarch64_on_linux_x86_64(
+        elf_header(ELF_CLASS_64, ELF_DATA_LSB, 0, 183),
+        "x86_64-unknown-linux-gnu",
+        false
+    )]
+    #[case::linux_x86_on_linux_x86_64(
+        elf_header(ELF_CLASS_32, ELF_DATA_LSB, 0, 3),
+        "x86_64-unknown-linux-gnu",
+        true
+    )]
+    #[case::linux_arm_on_linux_aarch64(
+        elf_header(ELF_CLASS_32, ELF_DATA_LSB, 0, 40),
+        "aarch64-unknown-linux-gnu",
+        false
+    )]
+    #[case::mips_big_endian_on_mips_little_endian(
+        elf_header(ELF_CLASS_32, ELF_DATA_MSB, 0, 8),
+        "mipsel-unknown-linux-gnu",
+        false
+    )]
+    #[case::mips64_on_mips64(
+        elf_header(ELF_CLASS_64, ELF_DATA_MSB, 0, 8),
+        "mips64-unknown-linux-gnuabi64",
+        true
+    )]
+    #[case::powerpc64_little_endian_on_powerpc64_big_endian(
+        elf_header(ELF_CLASS_64, ELF_DATA_LSB, 0, 21),
+        "powerpc64-unknown-linux-gnu",
+        false
+    )]
+    #[case::freebsd_on_linux(
+        elf_header(ELF_CLASS_64, ELF_DATA_LSB, 9, 62),
+        "x86_64-unknown-linux-gnu",
+        false
+    )]
+    #[case::freebsd_on_freebsd(
+        elf_header(ELF_CLASS_64, ELF_DATA_LSB, 9, 62),
+        "x86_64-unknown-freebsd",
+        true
+    )]
+    #[case::elf_on_macos(
+        elf_header(ELF_CLASS_64, ELF_DATA_LSB, 0, 183),
+        "aarch64-apple-darwin",
+        false
+    )]
+    #[case::macos_aarch64_on_macos_aarch64(macho_header(&[0x0100_000c]), "aarch64-apple-darwin", true)]
+    #[case::macos_x86_64_on_macos_aarch64(macho_header(&[0x0100_0007]), "aarch64-apple-darwin", true)]
+    #[case::macos_aarch64_on_macos_x86_64(
+        macho_header(&[0x0100_000c]),
+        "x86_64-apple-darwin",
+        false
+    )]
+    #[case::macos_universal_on_macos_x86_64(
+        macho_header(&[0x0100_0007, 0x0100_000c]),
+        "x86_64-apple-darwin",
+        true
+    )]
+    #[case::macos_on_linux(macho_header(&[0x0100_0007]), "x86_64-unknown-linux-gnu", false)]
+    #[case::windows_x86_64_on_windows_x86_64(pe_header(0x8664), "x86_64-pc-windows-msvc", true)]
+    #[case::windows_x86_64_on_windows_aarch64(pe_header(0x8664), "aarch64-pc-windows-msvc", true)]
+    #[case::windows_aarch64_on_windows_x86_64(pe_header(0xaa64), "x86_64-pc-windows-msvc", false)]
+    #[case::windows_on_linux(pe_header(0x8664), "x86_64-unknown-linux-gnu", false)]
+    fn runs_on(#[case] header: Vec<u8>, #[case] platform_name: &str, #[case] expect: bool) {
+        crate::test_log::init_logging();
+
+        let platform = Platform::find(platform_name).unwrap();
+        let binary = parse_header(&header).unwrap();
+        assert_eq!(
+            binary.runs_on(platform),
+            expect,
+            "{binary} on {platform_name}"
+        );
+    }
+
+    #[rstest]
+    #[case::script(b"#!/bin/sh\necho hello\n".to_vec())]
+    #[case::truncated_elf(b"\x7fELF\x02\x01".to_vec())]
+    #[case::unknown_elf_machine(elf_header(ELF_CLASS_64, ELF_DATA_LSB, 0, 0xffff))]
+    #[case::dos_without_pe_header(b"MZ\x90\x00".to_vec())]
+    // This is the start of a Java class file for Java 17.
+    #[case::java_class_file(vec![0xca, 0xfe, 0xba, 0xbe, 0x00, 0x00, 0x00, 0x3d])]
+    fn unrecognized_header(#[case] header: Vec<u8>) {
+        assert_eq!(parse_header(&header), None);
+    }
+
+    #[rstest]
+    #[case::elf_64_lsb(ELF_CLASS_64, ELF_DATA_LSB, Some(0x1000 + 64 * 30))]
+    #[case::elf_64_msb(ELF_CLASS_64, ELF_DATA_MSB, Some(0x1000 + 64 * 30))]
+    #[case::elf_32_lsb(ELF_CLASS_32, ELF_DATA_LSB, Some(0x1000 + 40 * 30))]
+    #[case::unknown_class(3, ELF_DATA_LSB, None)]
+    fn elf_end(#[case] class: u8, #[case] data: u8, #[case] expect: Option<u64>) {
+        let mut header = elf_header(class, data, 0, 62);
+        let (offset, entry_size, count) = if class == ELF_CLASS_32 {
+            ((0x20, 4), (0x2e, 40u16), (0x30, 30u16))
+        } else {
+            ((0x28, 8), (0x3a, 64u16), (0x3c, 30u16))
+        };
+        let write = |header: &mut Vec<u8>, at: usize, bytes: &[u8]| {
+            header[at..at + bytes.len()].copy_from_slice(bytes);
+        };
+        let be = data == ELF_DATA_MSB;
+        let sh_offset = 0x1000u64.to_le_bytes();
+        let mut sh_offset = sh_offset[..offset.1].to_vec();
+        if be {
+            sh_offset.reverse();
+        }
+        write(&mut header, offset.0, &sh_offset);
+        let to_bytes = |n: u16| if be { n.to_be_bytes() } else { n.to_le_bytes() };
+        write(&mut header, entry_size.0, &to_bytes(entry_size.1));
+        write(&mut header, count.0, &to_bytes(count.1));
+
+        assert_eq!(super::elf_end(&header), expect);
+    }
+
+    #[test]
+    fn elf_end_of_non_elf_file() {
+        assert_eq!(super::elf_end(b"#!/bin/sh\necho hello\n"), None);
+    }
+
+    #[test]
+    fn display() {
+        let binary = parse_header(&macho_header(&[0x0100_0007, 0x0100_000c])).unwrap();
+        assert_eq!(
+            binary.to_string(),
+            "a Mach-O universal binary for macos (x86_64, 64-bit, little-endian; aarch64, 64-bit, little-endian)",
+        );
+    }
+}
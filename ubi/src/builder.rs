@@ -1,12 +1,20 @@
 /// The `builder` module contains the `UbiBuilder` struct which is used to create a `Ubi` instance.
 use crate::{
+    cache::DownloadCache,
     forge::ForgeType,
     installer::{ArchiveInstaller, ExeInstaller, Installer},
+    launcher::LauncherConfig,
+    limits::ExtractionLimits,
+    partial_download,
     picker::AssetPicker,
+    post_install::{PostInstall, PostInstallHook},
+    progress::{DownloadProgress, ProgressHook},
+    retry::{Retry, DEFAULT_MAX_RETRIES, DEFAULT_RETRY_DELAY},
+    smoke_test::{SmokeTest, DEFAULT_SMOKE_TEST_ARGS, DEFAULT_SMOKE_TEST_TIMEOUT},
     ubi::Ubi,
 };
 use anyhow::{anyhow, Context, Result};
-use log::debug;
+use log::{debug, info};
 use platforms::{Platform, PlatformReq, OS};
 use reqwest::{
     header::{HeaderMap, HeaderValue, USER_AGENT},
@@ -16,10 +24,14 @@ use std::{
     env,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::Arc,
+    time::Duration,
 };
 use url::Url;
 use which::which;
 
+const ARCHIVE_PASSWORD_ENV_VAR: &str = "UBI_ARCHIVE_PASSWORD";
+
 /// `UbiBuilder` is used to create a [`Ubi`] instance.
 #[derive(Debug, Default)]
 #[allow(clippy::module_name_repetitions)]
@@ -33,6 +45,27 @@ pub struct UbiBuilder<'a> {
     exe: Option<&'a str>,
     rename_exe_to: Option<&'a str>,
     extract_all: bool,
+    archive_password: Option<&'a str>,
+    max_extracted_bytes: Option<u64>,
+    max_extracted_file_bytes: Option<u64>,
+    max_archive_entries: Option<u64>,
+    stream_download: bool,
+    partial_download_dir: Option<PathBuf>,
+    max_retries: Option<u32>,
+    retry_delay: Option<Duration>,
+    max_rate_limit_wait: Option<Duration>,
+    use_download_cache: bool,
+    download_cache_dir: Option<PathBuf>,
+    allow_platform_mismatch: bool,
+    install_launchers: bool,
+    extract_appimages: bool,
+    launcher_lib_dir: Option<PathBuf>,
+    smoke_test: bool,
+    smoke_test_args: Option<Vec<&'a str>>,
+    smoke_test_timeout: Option<Duration>,
+    smoke_test_checks_version: bool,
+    post_install_hooks: Vec<PostInstallHook<'a>>,
+    download_progress_hook: Option<ProgressHook<'a>>,
     token: Option<&'a str>,
     platform: Option<&'a Platform>,
     is_musl: Option<bool>,
@@ -114,6 +147,9 @@ impl<'a> UbiBuilder<'a> {
     /// the project name, so for `houseabsolute/precious` we look for `precious` or
     /// `precious.exe`. When running on Windows the ".exe" suffix will be added as needed.
     ///
+    /// If this isn't set and no file in the archive matches the project name, but the archive
+    /// contains exactly one executable file, then that file is installed with its own name.
+    ///
     /// You cannot call `extract_all` if you set this.
     #[must_use]
     pub fn exe(mut self, exe: &'a str) -> Self {
@@ -145,6 +181,276 @@ impl<'a> UbiBuilder<'a> {
         self
     }
 
+    /// Set the password used to decrypt an encrypted zip or 7z archive. For zip files, this works
+    /// with both AES encryption and the older zip encryption scheme. If this is not set, then this
+    /// will be taken from the `UBI_ARCHIVE_PASSWORD` env var if it is set. The password is ignored
+    /// for archives that are not encrypted.
+    #[must_use]
+    pub fn archive_password(mut self, password: &'a str) -> Self {
+        self.archive_password = Some(password);
+        self
+    }
+
+    /// Set the most bytes that `ubi` will extract from the downloaded file, counting every file it
+    /// extracts, including any nested archives. If a release asset decompresses to more than this,
+    /// `Ubi::install_binary` returns an error and nothing is installed. By default there is no
+    /// limit.
+    #[must_use]
+    pub fn max_extracted_bytes(mut self, bytes: u64) -> Self {
+        self.max_extracted_bytes = Some(bytes);
+        self
+    }
+
+    /// Set the most bytes that `ubi` will extract for any one file. If a file in the release asset
+    /// is larger than this, `Ubi::install_binary` returns an error and nothing is installed. By
+    /// default there is no limit.
+    #[must_use]
+    pub fn max_extracted_file_bytes(mut self, bytes: u64) -> Self {
+        self.max_extracted_file_bytes = Some(bytes);
+        self
+    }
+
+    /// Set the most entries that `ubi` will read from an archive file, counting the entries in any
+    /// nested archives. If an archive has more than this, `Ubi::install_binary` returns an error
+    /// and nothing is installed. By default there is no limit.
+    #[must_use]
+    pub fn max_archive_entries(mut self, entries: u64) -> Self {
+        self.max_archive_entries = Some(entries);
+        self
+    }
+
+    /// Call this to extract tarballs as they're downloaded, instead of writing the whole download to
+    /// a temp file first. This uses less disk space and is faster for large tarballs, since the
+    /// download and the decompression happen at the same time. When looking for an executable, the
+    /// download stops as soon as the executable is found, unless the release lists a SHA-256
+    /// digest for the asset. Then the whole asset is downloaded and checked against the digest, and
+    /// the install fails if it doesn't match.
+    ///
+    /// Streamed downloads are not retried or resumed. If one fails partway through, the install
+    /// fails.
+    ///
+    /// This is only used for assets whose names end in a tarball extension, like `.tar.gz` or
+    /// `.tar.xz`, including `.apk` and `.pkg.tar.zst` packages. Other assets are downloaded to a
+    /// temp file as usual.
+    #[must_use]
+    pub fn stream_download(mut self) -> Self {
+        self.stream_download = true;
+        self
+    }
+
+    /// Set the directory where interrupted downloads are kept. When a download fails partway
+    /// through, `ubi` keeps what it downloaded here, and the next attempt to download the same URL
+    /// asks the server for just the rest of it. If the server doesn't support range requests, or
+    /// the asset has changed since the download started, the whole asset is downloaded again.
+    ///
+    /// If this isn't set, it defaults to a `ubi-partial-downloads` directory in the user's cache
+    /// dir, like `~/.cache`. On Unix-like systems, the directory is created so that only the
+    /// current user can read it, and a directory that belongs to another user is refused.
+    /// Downloads that are extracted as they're downloaded, because `stream_download` is set, are
+    /// never resumed.
+    #[must_use]
+    pub fn partial_download_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.partial_download_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the most times to retry a request that fails with a transient error. This applies to
+    /// requests to the forge's API and to downloads of release assets. A request is retried when
+    /// the connection fails, it times out, or the server responds with a 502, 503, or 504 status.
+    /// A download that fails partway through is retried too, picking up where it left off. Set
+    /// this to 0 to turn off retries.
+    ///
+    /// Retries use an exponential backoff with some random jitter. If the server sends a
+    /// `Retry-After` header, `ubi` waits as long as it asks instead, unless that's more than a
+    /// minute, in which case it gives up. Each retry is logged at the info level.
+    ///
+    /// If this isn't set, it defaults to 3.
+    #[must_use]
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Set how long to wait before the first retry of a request that failed. The wait doubles with
+    /// each retry after that, up to 30 seconds. If this isn't set, it defaults to 1 second.
+    #[must_use]
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = Some(retry_delay);
+        self
+    }
+
+    /// Call this to wait for the forge's API rate limit to reset when a request hits it, as long as
+    /// it resets within `max_wait`. If it resets later than that, or the forge doesn't say when it
+    /// resets, `Ubi::install_binary` returns a [`RateLimitError`](crate::RateLimitError) instead.
+    /// `ubi` only waits once for each request.
+    ///
+    /// By default, `ubi` never waits for a rate limit to reset.
+    #[must_use]
+    pub fn wait_for_rate_limit(mut self, max_wait: Duration) -> Self {
+        self.max_rate_limit_wait = Some(max_wait);
+        self
+    }
+
+    /// Call this to keep downloaded release assets in a local cache and install from the cache
+    /// instead of downloading an asset again. Assets are cached by forge, project, release tag, and
+    /// asset name, along with the asset's digest when the forge provides one. Only assets picked
+    /// from a release are cached. Assets downloaded from a URL set with `url` are not, and neither
+    /// are assets extracted as they're downloaded because `stream_download` is set. An asset that's
+    /// already in the cache is never streamed.
+    ///
+    /// When the asset's digest is a SHA-256 hash, which is what GitHub provides, the download is
+    /// checked against it before it's cached, and a mismatch is an error. The cached file is
+    /// checked again each time it's used. If it no longer matches, it's removed from the cache and
+    /// the asset is downloaded again.
+    ///
+    /// The cache is kept in the directory returned by
+    /// [`DownloadCache::in_default_dir`](crate::DownloadCache::in_default_dir), unless you set
+    /// `download_cache_dir`. Use [`DownloadCache`](crate::DownloadCache) to see how big the cache
+    /// is or to prune it.
+    #[must_use]
+    pub fn use_download_cache(mut self) -> Self {
+        self.use_download_cache = true;
+        self
+    }
+
+    /// Set the directory for the download cache. This implies `use_download_cache`.
+    #[must_use]
+    pub fn download_cache_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.download_cache_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Call this to tell `ubi` to install an executable even when its header says that it was built
+    /// for a different platform. By default, `ubi` checks the header of an installed ELF, Mach-O, or
+    /// PE executable against the platform it's installing for. If the executable cannot run on that
+    /// platform, it is deleted and `Ubi::install_binary` returns an error. If this is set, `ubi`
+    /// logs a warning and leaves the executable in place instead.
+    ///
+    /// This has no effect when `extract_all` is set.
+    #[must_use]
+    pub fn allow_platform_mismatch(mut self) -> Self {
+        self.allow_platform_mismatch = true;
+        self
+    }
+
+    /// Call this to install a launcher for release artifacts that need an interpreter to run. This
+    /// applies to `.jar` files, which are run with `java -jar`, and `.py` and `.pyz` files, which are
+    /// run with `python3`, or `python` on Windows. The artifact is installed in a lib directory, and
+    /// a launcher that runs it with the interpreter, passing along any arguments, is installed in
+    /// the install directory. On Windows, the launcher is a `.cmd` file. Otherwise it's a shell
+    /// script.
+    ///
+    /// For example, if the install directory is `/usr/local/bin`, then `tool.jar` is installed as
+    /// `/usr/local/lib/tool.jar`, and the launcher is installed as `/usr/local/bin/tool`.
+    ///
+    /// Artifacts with any other extension are installed as usual. Note that if you set
+    /// `rename_exe_to` to a name without one of these extensions, no launcher is installed.
+    ///
+    /// You cannot call `extract_all` if you call this.
+    #[must_use]
+    pub fn install_launchers(mut self) -> Self {
+        self.install_launchers = true;
+        self
+    }
+
+    /// Call this to extract AppImages instead of installing them as-is. Running an AppImage
+    /// requires FUSE, which isn't available in most containers. When this is set, `ubi` extracts
+    /// the filesystem embedded in an AppImage into a directory under the lib directory, and installs
+    /// a launcher that runs the extracted `AppRun` file in the install directory.
+    ///
+    /// For example, if the install directory is `/usr/local/bin`, then `tool.AppImage` is extracted
+    /// into `/usr/local/lib/tool`, and the launcher is installed as `/usr/local/bin/tool`.
+    ///
+    /// You cannot call `extract_all` if you call this.
+    #[must_use]
+    pub fn extract_appimages(mut self) -> Self {
+        self.extract_appimages = true;
+        self
+    }
+
+    /// Set the lib directory used by `install_launchers` and `extract_appimages`. By default, this is
+    /// the `lib` directory next to the install directory.
+    ///
+    /// You must also call `install_launchers` or `extract_appimages` if you set this.
+    #[must_use]
+    pub fn launcher_lib_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.launcher_lib_dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Call this to run the installed executable after installing it, to make sure that it actually
+    /// works. By default, `ubi` runs it with `--version` and checks that it exits successfully
+    /// within 30 seconds. If it doesn't, the new executable is removed, any executable it replaced
+    /// is restored, and `Ubi::install_binary` returns an error.
+    ///
+    /// The smoke test is only run when installing for the platform `ubi` is running on. For any
+    /// other platform, it is skipped.
+    ///
+    /// You cannot call `extract_all` if you call this.
+    #[must_use]
+    pub fn smoke_test(mut self) -> Self {
+        self.smoke_test = true;
+        self
+    }
+
+    /// Set the arguments to pass to the executable when running the smoke test. Calling this
+    /// enables the smoke test. The default is `--version`.
+    #[must_use]
+    pub fn smoke_test_args(mut self, args: &[&'a str]) -> Self {
+        self.smoke_test = true;
+        self.smoke_test_args = Some(args.to_vec());
+        self
+    }
+
+    /// Set how long to wait for the executable to exit when running the smoke test. If it is still
+    /// running after this, it is killed and the smoke test fails. The default is 30 seconds.
+    ///
+    /// You must also call `smoke_test` or `smoke_test_args` if you set this.
+    #[must_use]
+    pub fn smoke_test_timeout(mut self, timeout: Duration) -> Self {
+        self.smoke_test_timeout = Some(timeout);
+        self
+    }
+
+    /// Call this to make the smoke test also check that the executable's output contains the version
+    /// from the release's tag. The version is the tag with any leading non-digit characters removed,
+    /// so the tag `v1.2.3` becomes `1.2.3`. If the tag has no version in it, this check is skipped.
+    ///
+    /// You must also call `smoke_test` or `smoke_test_args` if you set this.
+    #[must_use]
+    pub fn smoke_test_checks_version(mut self) -> Self {
+        self.smoke_test_checks_version = true;
+        self
+    }
+
+    /// Add a hook to run after a successful install. The hook is passed a [`PostInstall`] with the
+    /// installed path, the release tag, and the project name. This is useful for things like
+    /// generating shell completions or signing the executable.
+    ///
+    /// Hooks run in the order they were added, after the smoke test, if there is one. If a hook
+    /// returns an error, no further hooks are run, and `Ubi::install_binary` returns a
+    /// [`PostInstallHookError`](crate::PostInstallHookError). The installed files are left in place
+    /// when this happens.
+    #[must_use]
+    pub fn post_install_hook(mut self, hook: impl Fn(&PostInstall) -> Result<()> + 'a) -> Self {
+        self.post_install_hooks.push(PostInstallHook::new(hook));
+        self
+    }
+
+    /// Set a hook to call as the release asset is downloaded. The hook is passed a
+    /// [`DownloadProgress`] with the number of bytes downloaded so far and the size of the asset,
+    /// if it's known. It's called when the download starts, again after each chunk of the download
+    /// arrives, and one last time when the download is done, fails, or stops early. If the download
+    /// fails partway through and is retried, it's called again when each retry starts, and the last
+    /// call only comes after the final retry. This is useful for showing a progress bar.
+    ///
+    /// If this is called more than once, only the last hook is used.
+    #[must_use]
+    pub fn download_progress_hook(mut self, hook: impl Fn(&DownloadProgress<'_>) + 'a) -> Self {
+        self.download_progress_hook = Some(ProgressHook::new(hook));
+        self
+    }
+
     /// Set the minimum age in days for releases. Only releases at least this many days old will be
     /// installed. This is useful for mitigating supply chain attacks. It's especially useful for
     /// projects that use GitHub's immutable releases feature.
@@ -247,19 +553,40 @@ impl<'a> UbiBuilder<'a> {
                 "You cannot set rename_exe_to and enable extract_all"
             ));
         }
-        if let Some(days) = self.min_age_days {
-            if self.url.is_some() {
-                return Err(anyhow!("You cannot set min_age_days with url"));
-            }
-            if self.tag.is_some() {
-                return Err(anyhow!("You cannot set min_age_days with tag"));
+        if self.install_launchers && self.extract_all {
+            return Err(anyhow!(
+                "You cannot enable install_launchers and enable extract_all"
+            ));
+        }
+        if self.extract_appimages && self.extract_all {
+            return Err(anyhow!(
+                "You cannot enable extract_appimages and enable extract_all"
+            ));
+        }
+        if self.launcher_lib_dir.is_some() && !(self.install_launchers || self.extract_appimages) {
+            return Err(anyhow!(
+                "You cannot set launcher_lib_dir without enabling install_launchers or extract_appimages"
+            ));
+        }
+        if self.smoke_test && self.extract_all {
+            return Err(anyhow!(
+                "You cannot enable smoke_test and enable extract_all"
+            ));
+        }
+        if !self.smoke_test {
+            if self.smoke_test_timeout.is_some() {
+                return Err(anyhow!(
+                    "You cannot set smoke_test_timeout without enabling smoke_test"
+                ));
             }
-            if days == 0 {
+            if self.smoke_test_checks_version {
                 return Err(anyhow!(
-                    "min_age_days must be a positive number (greater than 0)"
+                    "You cannot enable smoke_test_checks_version without enabling smoke_test"
                 ));
             }
         }
+        self.check_extraction_limits()?;
+        self.check_min_age_days()?;
 
         let platform = self.determine_platform()?;
 
@@ -274,13 +601,21 @@ impl<'a> UbiBuilder<'a> {
         let (project_name, forge_type) =
             parse_project_name(self.project, asset_url.as_ref(), self.forge.clone())?;
         let installer = self.new_installer(&project_name, &platform)?;
+        let retry = Retry::new(
+            self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            self.retry_delay.unwrap_or(DEFAULT_RETRY_DELAY),
+        );
         let forge = forge_type.new_forge(
             project_name,
             self.tag.map(String::from),
             self.api_base_url.map(String::from),
             self.token.map(String::from),
+            retry,
+            self.max_rate_limit_wait,
         )?;
         let is_musl = self.is_musl.unwrap_or_else(|| platform_is_musl(&platform));
+        let smoke_test = self.new_smoke_test(&platform);
+        let download_cache = self.new_download_cache()?;
 
         Ok(Ubi::new(
             forge,
@@ -295,18 +630,62 @@ impl<'a> UbiBuilder<'a> {
             installer,
             reqwest_client()?,
             self.min_age_days,
+            smoke_test,
+            self.post_install_hooks,
+            self.download_progress_hook,
+            self.stream_download,
+            self.partial_download_dir
+                .clone()
+                .unwrap_or_else(partial_download::default_dir),
+            retry,
+            download_cache,
         ))
     }
 
-    fn new_installer(&self, project_name: &str, platform: &Platform) -> Result<Box<dyn Installer>> {
+    fn new_download_cache(&self) -> Result<Option<DownloadCache>> {
+        match &self.download_cache_dir {
+            Some(dir) => Ok(Some(DownloadCache::new(dir))),
+            None if self.use_download_cache => DownloadCache::in_default_dir().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn new_smoke_test(&self, platform: &Platform) -> Option<SmokeTest> {
+        if !self.smoke_test {
+            return None;
+        }
+        if !is_host_platform(platform) {
+            info!(
+                "Not running the smoke test because the executable is for {}, which is not the platform ubi is running on",
+                platform.target_triple,
+            );
+            return None;
+        }
+
+        Some(SmokeTest::new(
+            self.smoke_test_args
+                .as_deref()
+                .unwrap_or(DEFAULT_SMOKE_TEST_ARGS)
+                .iter()
+                .map(|a| (*a).to_string())
+                .collect(),
+            self.smoke_test_timeout
+                .unwrap_or(DEFAULT_SMOKE_TEST_TIMEOUT),
+            self.smoke_test_checks_version,
+        ))
+    }
+
+    fn new_installer(&self, project_name: &str, platform: &Platform) -> Result<Arc<dyn Installer>> {
         if self.extract_all {
             let install_path =
                 install_path(self.install_dir.as_deref(), None).with_context(|| {
                     format!("failed to determine install path for project {project_name}")
                 })?;
-            Ok(Box::new(ArchiveInstaller::new(
+            Ok(Arc::new(ArchiveInstaller::new(
                 project_name.to_string(),
                 install_path,
+                self.resolve_archive_password(),
+                self.extraction_limits(),
             )))
         } else {
             let expect_exe_stem_name = expect_exe_stem_name(self.exe, project_name);
@@ -317,15 +696,59 @@ impl<'a> UbiBuilder<'a> {
             .with_context(|| {
                 format!("failed to determine install path for executable {expect_exe_stem_name}")
             })?;
-            Ok(Box::new(ExeInstaller::new(
+            let launcher_config = if self.install_launchers || self.extract_appimages {
+                Some(LauncherConfig {
+                    lib_dir: self.resolve_launcher_lib_dir(&install_path)?,
+                    for_interpreted_files: self.install_launchers,
+                    for_appimages: self.extract_appimages,
+                })
+            } else {
+                None
+            };
+            Ok(Arc::new(ExeInstaller::new(
                 install_path,
                 self.rename_exe_to.is_some(),
                 expect_exe_stem_name.to_string(),
-                platform.target_os == OS::Windows,
+                self.exe.is_none(),
+                platform.clone(),
+                self.allow_platform_mismatch,
+                launcher_config,
+                self.resolve_archive_password(),
+                self.extraction_limits(),
             )))
         }
     }
 
+    fn extraction_limits(&self) -> ExtractionLimits {
+        ExtractionLimits {
+            total_bytes: self.max_extracted_bytes,
+            file_bytes: self.max_extracted_file_bytes,
+            entries: self.max_archive_entries,
+        }
+    }
+
+    fn resolve_archive_password(&self) -> Option<String> {
+        self.archive_password
+            .map(String::from)
+            .or_else(|| env::var(ARCHIVE_PASSWORD_ENV_VAR).ok())
+    }
+
+    fn resolve_launcher_lib_dir(&self, install_path: &Path) -> Result<PathBuf> {
+        if let Some(dir) = &self.launcher_lib_dir {
+            return Ok(dir.clone());
+        }
+
+        let Some(install_dir) = install_path.parent() else {
+            return Err(anyhow!(
+                "install path at {} has no parent",
+                install_path.display()
+            ));
+        };
+        let lib_dir = install_dir.parent().unwrap_or(install_dir).join("lib");
+        debug!("launcher lib dir = {}", lib_dir.display());
+        Ok(lib_dir)
+    }
+
     fn determine_platform(&self) -> Result<Platform> {
         if let Some(p) = self.platform {
             Ok(p.clone())
@@ -343,6 +766,36 @@ impl<'a> UbiBuilder<'a> {
         }
     }
 
+    fn check_min_age_days(&self) -> Result<()> {
+        if let Some(days) = self.min_age_days {
+            if self.url.is_some() {
+                return Err(anyhow!("You cannot set min_age_days with url"));
+            }
+            if self.tag.is_some() {
+                return Err(anyhow!("You cannot set min_age_days with tag"));
+            }
+            if days == 0 {
+                return Err(anyhow!(
+                    "min_age_days must be a positive number (greater than 0)"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_extraction_limits(&self) -> Result<()> {
+        for (name, value) in [
+            ("max_extracted_bytes", self.max_extracted_bytes),
+            ("max_extracted_file_bytes", self.max_extracted_file_bytes),
+            ("max_archive_entries", self.max_archive_entries),
+        ] {
+            if value == Some(0) {
+                return Err(anyhow!("{name} must be a positive number (greater than 0)"));
+            }
+        }
+        Ok(())
+    }
+
     fn check_musl_setting(&self, platform: &Platform) -> Result<()> {
         if self.is_musl.unwrap_or_default() && platform.target_os != OS::Linux {
             return Err(anyhow!(
@@ -419,6 +872,13 @@ fn expect_exe_stem_name<'a>(exe: Option<&'a str>, project_name: &'a str) -> &'a
     name
 }
 
+// We only compare the OS and arch, not the whole target triple, since a binary for the same OS and
+// arch but a different libc or ABI is still worth running.
+fn is_host_platform(platform: &Platform) -> bool {
+    platform.target_os.as_str() == env::consts::OS
+        && platform.target_arch.as_str() == env::consts::ARCH
+}
+
 fn platform_is_musl(platform: &Platform) -> bool {
     if platform.target_os != OS::Linux {
         return false;
@@ -521,6 +981,24 @@ mod test {
         assert_eq!(super::expect_exe_stem_name(exe, project_name), expect);
     }
 
+    #[rstest]
+    #[case::with_extract_all(
+        UbiBuilder::new().project("houseabsolute/ubi").smoke_test().extract_all(),
+        "You cannot enable smoke_test and enable extract_all"
+    )]
+    #[case::timeout_without_smoke_test(
+        UbiBuilder::new().project("houseabsolute/ubi").smoke_test_timeout(Duration::from_secs(1)),
+        "You cannot set smoke_test_timeout without enabling smoke_test"
+    )]
+    #[case::checks_version_without_smoke_test(
+        UbiBuilder::new().project("houseabsolute/ubi").smoke_test_checks_version(),
+        "You cannot enable smoke_test_checks_version without enabling smoke_test"
+    )]
+    fn smoke_test_validation(#[case] builder: UbiBuilder<'static>, #[case] expect: &str) {
+        let err = builder.build().unwrap_err().to_string();
+        assert!(err.contains(expect), "error `{err}` contains `{expect}`");
+    }
+
     #[test]
     fn min_age_days_zero_validation() {
         let result = UbiBuilder::new()
@@ -534,4 +1012,25 @@ mod test {
             .to_string()
             .contains("min_age_days must be a positive number"));
     }
+
+    #[rstest]
+    #[case::max_extracted_bytes(
+        UbiBuilder::new().project("houseabsolute/ubi").max_extracted_bytes(0),
+        "max_extracted_bytes must be a positive number"
+    )]
+    #[case::max_extracted_file_bytes(
+        UbiBuilder::new().project("houseabsolute/ubi").max_extracted_file_bytes(0),
+        "max_extracted_file_bytes must be a positive number"
+    )]
+    #[case::max_archive_entries(
+        UbiBuilder::new().project("houseabsolute/ubi").max_archive_entries(0),
+        "max_archive_entries must be a positive number"
+    )]
+    fn extraction_limits_zero_validation(
+        #[case] builder: UbiBuilder<'static>,
+        #[case] expect: &str,
+    ) {
+        let err = builder.build().unwrap_err().to_string();
+        assert!(err.contains(expect), "error `{err}` contains `{expect}`");
+    }
 }
@@ -0,0 +1,489 @@
+// Release assets are cached by where they came from: the forge, the project, the release tag, and
+// the asset's name. When the forge tells us the asset's digest, that's part of the key too, so an
+// asset that was replaced in a release isn't served from the cache. We only cache assets from a
+// release we looked up, since an asset URL might point at something like the latest release, which
+// changes over time.
+//
+// When the digest is a SHA-256 hash, we check it when an asset is added to the cache and again each
+// time it's used, so a corrupted or tampered with file in the cache is never installed.
+use crate::{digest::DigestChecker, forge::ForgeType};
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use std::{
+    env,
+    fmt::Write,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tempfile::NamedTempFile;
+use walkdir::WalkDir;
+
+// This is used in place of the digest in the cache path when the forge doesn't give us one.
+const NO_DIGEST: &str = "no-digest";
+
+/// An on-disk cache of downloaded release assets. See
+/// [`UbiBuilder::use_download_cache`](crate::UbiBuilder::use_download_cache) for details.
+#[derive(Clone, Debug)]
+pub struct DownloadCache {
+    dir: PathBuf,
+}
+
+/// The number of assets in a download cache and the bytes they use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    assets: u64,
+    bytes: u64,
+}
+
+impl CacheStats {
+    /// The number of assets.
+    #[must_use]
+    pub fn assets(&self) -> u64 {
+        self.assets
+    }
+
+    /// The total size of the assets in bytes.
+    #[must_use]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    fn add(&mut self, bytes: u64) {
+        self.assets += 1;
+        self.bytes += bytes;
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CacheKey<'a> {
+    pub(crate) forge_type: &'a ForgeType,
+    pub(crate) project_name: &'a str,
+    pub(crate) tag: &'a str,
+    pub(crate) asset_name: &'a str,
+    pub(crate) digest: Option<&'a str>,
+}
+
+impl DownloadCache {
+    /// Returns a cache that keeps assets in `dir`. The directory is created when the first asset
+    /// is added to the cache.
+    #[must_use]
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns a cache in the default location. This is a `ubi` directory in `$XDG_CACHE_HOME`, if
+    /// that's set. Otherwise it's `%LOCALAPPDATA%\ubi\cache` on Windows, `~/Library/Caches/ubi` on
+    /// macOS, and `~/.cache/ubi` everywhere else.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if neither `$XDG_CACHE_HOME` nor the home directory env var for the
+    /// platform is set.
+    pub fn in_default_dir() -> Result<Self> {
+        default_dir().map(Self::new).ok_or_else(|| {
+            anyhow!("could not find a directory for the download cache because no cache or home directory env var is set")
+        })
+    }
+
+    /// The directory the cache keeps assets in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the number of assets in the cache and the bytes they use.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the cache directory can't be read.
+    pub fn stats(&self) -> Result<CacheStats> {
+        let mut stats = CacheStats::default();
+        for entry in self.files() {
+            let (_, meta) = entry?;
+            stats.add(meta.len());
+        }
+        Ok(stats)
+    }
+
+    /// Removes the assets that haven't been used in `max_age`, along with any directories that
+    /// are empty after that. An asset is used when it's added to the cache and each time it's
+    /// installed from the cache. This returns the number of assets removed and the bytes they
+    /// used.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the cache directory can't be read, or if an asset can't be
+    /// removed.
+    pub fn prune(&self, max_age: Duration) -> Result<CacheStats> {
+        let cutoff = SystemTime::now()
+            .checked_sub(max_age)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let mut removed = CacheStats::default();
+        for entry in self.files() {
+            let (path, meta) = entry?;
+            let used = meta
+                .modified()
+                .with_context(|| format!("failed to get mtime for {}", path.display()))?;
+            if used >= cutoff {
+                continue;
+            }
+            debug!("removing {} from the download cache", path.display());
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+            removed.add(meta.len());
+        }
+
+        // Removing a directory that's not empty fails, so this only removes the empty ones.
+        for entry in WalkDir::new(&self.dir)
+            .min_depth(1)
+            .contents_first(true)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_dir())
+        {
+            let _ = fs::remove_dir(entry.path());
+        }
+
+        Ok(removed)
+    }
+
+    pub(crate) fn contains(&self, key: &CacheKey<'_>) -> bool {
+        self.path_for(key).is_file()
+    }
+
+    // Returns the path of the cached asset, if it's in the cache. If the cached file doesn't match
+    // the asset's digest, it's removed, and this returns `None` so that the asset is downloaded
+    // again.
+    pub(crate) fn get(&self, key: &CacheKey<'_>) -> Option<PathBuf> {
+        let path = self.path_for(key);
+        if !path.is_file() {
+            debug!("{} is not in the download cache", key.asset_name);
+            return None;
+        }
+        debug!(
+            "found {} in the download cache at {}",
+            key.asset_name,
+            path.display()
+        );
+        if let Err(e) = check_digest(&path, key.digest) {
+            warn!("removing {} from the download cache: {e:#}", key.asset_name);
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("could not remove {}: {e}", path.display());
+            }
+            return None;
+        }
+
+        // We use the mtime to track when an asset was last used, so that `prune` doesn't remove
+        // assets that are still in use.
+        if let Err(e) = File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|f| f.set_modified(SystemTime::now()))
+        {
+            debug!("could not update the mtime of {}: {e}", path.display());
+        }
+        Some(path)
+    }
+
+    // Adds the file at `from` to the cache. If it doesn't match the asset's digest, this returns a
+    // `DigestMismatchError`.
+    pub(crate) fn put(&self, key: &CacheKey<'_>, from: &Path) -> Result<()> {
+        check_digest(from, key.digest)?;
+
+        let path = self.path_for(key);
+        let dir = path
+            .parent()
+            .expect("a cache path always has a parent directory");
+        fs::create_dir_all(dir).with_context(|| {
+            format!(
+                "failed to create download cache directory at {}",
+                dir.display()
+            )
+        })?;
+
+        // We copy to a temp file and then rename it, so that no one ever sees a partial file in
+        // the cache.
+        let mut temp = NamedTempFile::new_in(dir).with_context(|| {
+            format!(
+                "failed to create temp file in download cache directory at {}",
+                dir.display()
+            )
+        })?;
+        let mut file = File::open(from)
+            .with_context(|| format!("failed to open {} to cache it", from.display()))?;
+        std::io::copy(&mut file, &mut temp).with_context(|| {
+            format!(
+                "failed to copy {} to the download cache at {}",
+                from.display(),
+                temp.path().display()
+            )
+        })?;
+        temp.persist(&path).with_context(|| {
+            format!(
+                "failed to move downloaded asset into the cache at {}",
+                path.display()
+            )
+        })?;
+        debug!(
+            "added {} to the download cache at {}",
+            key.asset_name,
+            path.display()
+        );
+
+        Ok(())
+    }
+
+    fn path_for(&self, key: &CacheKey<'_>) -> PathBuf {
+        [
+            key.forge_type.as_ref(),
+            key.project_name,
+            key.tag,
+            key.digest.unwrap_or(NO_DIGEST),
+            key.asset_name,
+        ]
+        .iter()
+        .fold(self.dir.clone(), |path, part| {
+            path.join(path_component(part))
+        })
+    }
+
+    fn files(&self) -> impl Iterator<Item = Result<(PathBuf, fs::Metadata)>> {
+        // The cache directory doesn't exist until the first asset is added.
+        self.dir
+            .exists()
+            .then(|| WalkDir::new(&self.dir).into_iter())
+            .into_iter()
+            .flatten()
+            .filter(|e| e.as_ref().map_or(true, |e| e.file_type().is_file()))
+            .map(|e| {
+                let e = e.context("failed to read download cache directory")?;
+                let meta = e.metadata().with_context(|| {
+                    format!("failed to get metadata for {}", e.path().display())
+                })?;
+                Ok((e.into_path(), meta))
+            })
+    }
+}
+
+fn default_dir() -> Option<PathBuf> {
+    let dir = user_cache_dir()?.join("ubi");
+    // Apps keep more than caches in `%LOCALAPPDATA%`, so we use a subdirectory there.
+    if cfg!(target_os = "windows") && xdg_cache_home().is_none() {
+        return Some(dir.join("cache"));
+    }
+    Some(dir)
+}
+
+// Returns the directory for the current user's caches. This is `$XDG_CACHE_HOME`, if that's set.
+// Otherwise it's `%LOCALAPPDATA%` on Windows, `~/Library/Caches` on macOS, and `~/.cache`
+// everywhere else.
+pub(crate) fn user_cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = xdg_cache_home() {
+        return Some(dir);
+    }
+    if cfg!(target_os = "windows") {
+        return env::var_os("LOCALAPPDATA").map(PathBuf::from);
+    }
+    let home = PathBuf::from(env::var_os("HOME")?);
+    if cfg!(target_os = "macos") {
+        Some(home.join("Library").join("Caches"))
+    } else {
+        Some(home.join(".cache"))
+    }
+}
+
+fn xdg_cache_home() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|d| d.is_absolute())
+}
+
+// Checks that the file at `path` matches `digest`, if it's a digest we can check.
+fn check_digest(path: &Path, digest: Option<&str>) -> Result<()> {
+    let Some(mut checker) = DigestChecker::new(digest) else {
+        return Ok(());
+    };
+
+    let mut file = File::open(path)
+        .with_context(|| format!("failed to open {} to check its digest", path.display()))?;
+    io::copy(&mut file, &mut checker)
+        .with_context(|| format!("failed to read {} to check its digest", path.display()))?;
+    checker.check(&path.display().to_string())?;
+    debug!("{} matches its digest", path.display());
+
+    Ok(())
+}
+
+// Turns a part of the cache key into a single path component. Project names and tags can contain
+// slashes, and none of the parts can be allowed to point outside of the cache, so we percent-encode
+// anything that isn't a plain character.
+fn path_component(part: &str) -> String {
+    if part.is_empty() || part == "." || part == ".." {
+        return part.bytes().fold(String::from("%"), |mut s, b| {
+            let _ = write!(s, "{b:02X}");
+            s
+        });
+    }
+    let mut component = String::with_capacity(part.len());
+    for b in part.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_' | b'+' | b'@') {
+            component.push(char::from(b));
+        } else {
+            let _ = write!(component, "%{b:02X}");
+        }
+    }
+    component
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digest::DigestMismatchError;
+    use rstest::rstest;
+    use sha2::{Digest, Sha256};
+    use tempfile::tempdir;
+
+    fn key<'a>(tag: &'a str, digest: Option<&'a str>) -> CacheKey<'a> {
+        CacheKey {
+            forge_type: &ForgeType::GitHub,
+            project_name: "houseabsolute/precious",
+            tag,
+            asset_name: "precious-Linux-x86_64-musl.tar.gz",
+            digest,
+        }
+    }
+
+    #[rstest]
+    #[case::plain("v1.0.0", "v1.0.0")]
+    #[case::slash("release/1.0", "release%2F1.0")]
+    #[case::parent_dir("..", "%2E2E")]
+    #[case::empty("", "%")]
+    #[case::colon("sha256:abc", "sha256%3Aabc")]
+    fn path_component(#[case] part: &str, #[case] expect: &str) {
+        assert_eq!(super::path_component(part), expect);
+    }
+
+    #[test]
+    fn path_for() {
+        let cache = DownloadCache::new("/cache");
+        assert_eq!(
+            cache.path_for(&key("v1.0.0", None)),
+            Path::new("/cache/github/houseabsolute%2Fprecious/v1.0.0/no-digest/precious-Linux-x86_64-musl.tar.gz"),
+        );
+        assert_eq!(
+            cache.path_for(&key("v1.0.0", Some("sha256:abc"))),
+            Path::new("/cache/github/houseabsolute%2Fprecious/v1.0.0/sha256%3Aabc/precious-Linux-x86_64-musl.tar.gz"),
+        );
+    }
+
+    #[test]
+    fn put_and_get() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let cache = DownloadCache::new(td.path().join("cache"));
+        assert_eq!(cache.stats()?, CacheStats::default());
+
+        let asset = td.path().join("asset");
+        fs::write(&asset, "some bytes")?;
+
+        let key = key("v1.0.0", None);
+        assert_eq!(cache.get(&key), None);
+        cache.put(&key, &asset)?;
+        let cached = cache.get(&key).expect("asset is in the cache");
+        assert_eq!(fs::read_to_string(cached)?, "some bytes");
+        assert_eq!(
+            cache.get(&super::tests::key("v1.0.0", Some("sha256:abc"))),
+            None,
+            "an asset with a different digest is not in the cache"
+        );
+        assert_eq!(
+            cache.stats()?,
+            CacheStats {
+                assets: 1,
+                bytes: 10
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn digest_is_checked() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let cache = DownloadCache::new(td.path().join("cache"));
+        let asset = td.path().join("asset");
+        fs::write(&asset, "some bytes")?;
+        let digest = format!("sha256:{:x}", Sha256::digest(b"some bytes"));
+
+        let wrong = format!("sha256:{:x}", Sha256::digest(b"other bytes"));
+        let err = cache.put(&key("v1.0.0", Some(&wrong)), &asset).unwrap_err();
+        assert!(err.is::<DigestMismatchError>());
+        assert_eq!(cache.stats()?.assets(), 0, "nothing was cached");
+
+        let key = key("v1.0.0", Some(&digest));
+        cache.put(&key, &asset)?;
+        assert!(cache.get(&key).is_some());
+
+        fs::write(cache.path_for(&key), "corrupted")?;
+        assert_eq!(cache.get(&key), None);
+        assert!(
+            !cache.path_for(&key).exists(),
+            "a cached asset that doesn't match its digest is removed"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn prune() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let cache = DownloadCache::new(td.path());
+        let asset = td.path().join("asset");
+        fs::write(&asset, "some bytes")?;
+
+        let old = key("v1.0.0", None);
+        cache.put(&old, &asset)?;
+        let new = key("v2.0.0", None);
+        cache.put(&new, &asset)?;
+        fs::remove_file(&asset)?;
+
+        let old_path = cache.path_for(&old);
+        File::options()
+            .write(true)
+            .open(&old_path)?
+            .set_modified(SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60))?;
+
+        let removed = cache.prune(Duration::from_secs(24 * 60 * 60))?;
+        assert_eq!(
+            removed,
+            CacheStats {
+                assets: 1,
+                bytes: 10
+            }
+        );
+        assert!(!old_path.exists());
+        assert!(
+            !old_path.parent().unwrap().exists(),
+            "empty directories are removed"
+        );
+        assert!(cache.path_for(&new).exists());
+        assert_eq!(
+            cache.stats()?,
+            CacheStats {
+                assets: 1,
+                bytes: 10
+            }
+        );
+
+        Ok(())
+    }
+}
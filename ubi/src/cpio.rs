@@ -0,0 +1,211 @@
+// The payload of an `.rpm` package is a cpio archive in the "new ASCII" (newc) format. Like `ar`,
+// this format is simple enough that it's not worth pulling in another dependency to read it. See
+// https://man.archlinux.org/man/cpio.5 for details.
+//
+// Each entry is a 110 byte header made of ASCII hex fields, followed by the entry's NUL-terminated
+// name, followed by its content. The name and content are each padded to a multiple of 4 bytes. The
+// archive ends with an entry named `TRAILER!!!`.
+use anyhow::{anyhow, Context, Result};
+use std::{
+    io::{self, Read},
+    path::PathBuf,
+};
+
+const NEWC_MAGIC: &[u8; 6] = b"070701";
+const NEWC_CRC_MAGIC: &[u8; 6] = b"070702";
+const HEADER_LEN: usize = 110;
+const TRAILER_NAME: &[u8] = b"TRAILER!!!";
+
+// The longest name or symlink target we're willing to read. This is the same as Linux's
+// `PATH_MAX`.
+const MAX_PATH_LEN: u64 = 4096;
+
+pub(crate) const S_IFMT: u32 = 0o170_000;
+const S_IFDIR: u32 = 0o040_000;
+const S_IFREG: u32 = 0o100_000;
+pub(crate) const S_IFLNK: u32 = 0o120_000;
+
+pub(crate) struct CpioArchive<R: Read> {
+    reader: R,
+    // The number of bytes of the current entry's content that have not been read yet.
+    remaining: u64,
+    // The number of padding bytes after the current entry's content.
+    padding: u64,
+    done: bool,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CpioEntry {
+    pub(crate) path: PathBuf,
+    pub(crate) mode: u32,
+    // This is only set for symlinks.
+    pub(crate) link_target: Option<PathBuf>,
+}
+
+impl CpioEntry {
+    pub(crate) fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+
+    // Note that in a newc archive, the content of a file with multiple hard links is stored with the
+    // last of those links, and the others are stored as empty files.
+    pub(crate) fn is_file(&self) -> bool {
+        self.mode & S_IFMT == S_IFREG
+    }
+
+    pub(crate) fn is_symlink(&self) -> bool {
+        self.mode & S_IFMT == S_IFLNK
+    }
+}
+
+impl<R: Read> CpioArchive<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            remaining: 0,
+            padding: 0,
+            done: false,
+        }
+    }
+
+    // Returns the next entry in the archive, skipping any unread content from the previous entry.
+    pub(crate) fn next_entry(&mut self) -> Result<Option<CpioEntry>> {
+        if self.done {
+            return Ok(None);
+        }
+        self.skip_rest_of_entry()?;
+
+        let mut header = [0u8; HEADER_LEN];
+        self.reader
+            .read_exact(&mut header)
+            .context("failed to read cpio entry header")?;
+        let magic = &header[0..6];
+        if magic != NEWC_MAGIC && magic != NEWC_CRC_MAGIC {
+            return Err(anyhow!(
+                "cpio entry header has an unsupported magic string, {}",
+                String::from_utf8_lossy(magic),
+            ));
+        }
+
+        let mode = header_field(&header, 1)?;
+        let size = u64::from(header_field(&header, 6)?);
+        let name_size = u64::from(header_field(&header, 11)?);
+        if name_size == 0 || name_size > MAX_PATH_LEN {
+            return Err(anyhow!(
+                "cpio entry has an invalid name size of {name_size}"
+            ));
+        }
+
+        let mut name = self
+            .read_bytes(name_size)
+            .context("failed to read cpio entry name")?;
+        // The name includes a trailing NUL.
+        name.pop();
+        skip(&mut self.reader, padding_for(HEADER_LEN as u64 + name_size))?;
+
+        if name == TRAILER_NAME {
+            self.done = true;
+            return Ok(None);
+        }
+        let path =
+            PathBuf::from(String::from_utf8(name).context("cpio entry name is not valid UTF-8")?);
+
+        self.remaining = size;
+        self.padding = padding_for(size);
+
+        let mut entry = CpioEntry {
+            path,
+            mode,
+            link_target: None,
+        };
+        if entry.is_symlink() {
+            if size > MAX_PATH_LEN {
+                return Err(anyhow!(
+                    "cpio symlink entry {} has a target that is too long",
+                    entry.path.display(),
+                ));
+            }
+            let target = self.read_bytes(size).with_context(|| {
+                format!(
+                    "failed to read symlink target for cpio entry {}",
+                    entry.path.display()
+                )
+            })?;
+            self.remaining = 0;
+            entry.link_target = Some(PathBuf::from(String::from_utf8(target).with_context(
+                || {
+                    format!(
+                        "symlink target for cpio entry {} is not valid UTF-8",
+                        entry.path.display()
+                    )
+                },
+            )?));
+        }
+
+        Ok(Some(entry))
+    }
+
+    // Returns a reader for the content of the entry most recently returned by `next_entry`.
+    pub(crate) fn content(&mut self) -> CpioContent<'_, R> {
+        CpioContent { archive: self }
+    }
+
+    fn skip_rest_of_entry(&mut self) -> Result<()> {
+        skip(&mut self.reader, self.remaining + self.padding)?;
+        self.remaining = 0;
+        self.padding = 0;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, len: u64) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; usize::try_from(len)?];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+}
+
+pub(crate) struct CpioContent<'a, R: Read> {
+    archive: &'a mut CpioArchive<R>,
+}
+
+impl<R: Read> Read for CpioContent<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = usize::try_from(self.archive.remaining)
+            .unwrap_or(usize::MAX)
+            .min(buf.len());
+        if max == 0 {
+            return Ok(0);
+        }
+        let n = self.archive.reader.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "cpio archive ended in the middle of an entry's content",
+            ));
+        }
+        self.archive.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+// The fields after the magic string are each 8 hex digits.
+fn header_field(header: &[u8; HEADER_LEN], idx: usize) -> Result<u32> {
+    let start = NEWC_MAGIC.len() + idx * 8;
+    let field = std::str::from_utf8(&header[start..start + 8])
+        .context("cpio entry header contains non-ASCII bytes")?;
+    u32::from_str_radix(field, 16)
+        .with_context(|| format!("cpio entry header contains an invalid hex field, {field}"))
+}
+
+fn padding_for(len: u64) -> u64 {
+    (4 - len % 4) % 4
+}
+
+fn skip(reader: &mut impl Read, len: u64) -> Result<()> {
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink())
+        .context("failed to skip past cpio data")?;
+    if skipped != len {
+        return Err(anyhow!("cpio archive ended unexpectedly"));
+    }
+    Ok(())
+}
@@ -0,0 +1,85 @@
+// A `.deb` package is an `ar` archive containing a `debian-binary` file, a `control.tar.*` tarball
+// with the package's metadata, and a `data.tar.*` tarball with the files that the package
+// installs. We only care about the data tarball.
+//
+// The `ar` format is simple enough that it's not worth pulling in another dependency to read it. See
+// https://en.wikipedia.org/wiki/Ar_(Unix)#File_format_details for details.
+use crate::installer::decompressing_reader;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+const AR_MAGIC: &[u8; 8] = b"!<arch>\n";
+const AR_HEADER_LEN: usize = 60;
+const AR_HEADER_END: &[u8; 2] = b"`\n";
+
+// Returns a reader for the uncompressed content of the data tarball in the `.deb` file.
+pub(crate) fn data_tarball_reader(path: &Path, mut file: File) -> Result<Box<dyn Read>> {
+    let mut magic = [0u8; AR_MAGIC.len()];
+    file.read_exact(&mut magic)
+        .with_context(|| format!("failed to read the header of {}", path.display()))?;
+    if &magic != AR_MAGIC {
+        return Err(anyhow!(
+            "{} is not a valid deb package, it does not start with the ar magic string",
+            path.display(),
+        ));
+    }
+
+    loop {
+        let mut header = [0u8; AR_HEADER_LEN];
+        match file.read_exact(&mut header) {
+            Ok(()) => (),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("failed to read an ar member header from {}", path.display())
+                })
+            }
+        }
+        if &header[58..60] != AR_HEADER_END {
+            return Err(anyhow!(
+                "{} contains an invalid ar member header",
+                path.display(),
+            ));
+        }
+
+        // GNU ar terminates names with a `/`, while BSD ar does not.
+        let name = String::from_utf8_lossy(&header[0..16]);
+        let name = name.trim_end().trim_end_matches('/');
+        let size = String::from_utf8_lossy(&header[48..58])
+            .trim()
+            .parse::<u64>()
+            .with_context(|| {
+                format!(
+                    "failed to parse the size of the {name} member in {}",
+                    path.display()
+                )
+            })?;
+        debug!("found ar member named {name} with size {size}");
+
+        if let Some(compression) = name.strip_prefix("data.tar") {
+            let ext = compression.strip_prefix('.').unwrap_or("tar");
+            debug!("decompressing the deb data tarball with the `{ext}` decoder");
+            return decompressing_reader(ext, file.take(size));
+        }
+
+        // Members are padded to an even number of bytes.
+        let skip = size + size % 2;
+        file.seek(SeekFrom::Current(i64::try_from(skip)?))
+            .with_context(|| {
+                format!(
+                    "failed to skip past the {name} member in {}",
+                    path.display()
+                )
+            })?;
+    }
+
+    Err(anyhow!(
+        "could not find a data tarball in the deb package at {}",
+        path.display(),
+    ))
+}
@@ -0,0 +1,99 @@
+// GitHub gives us a digest like `sha256:...` for each release asset. That's the only kind of digest
+// we can check, so digests that use any other algorithm are ignored.
+use sha2::{Digest, Sha256};
+use std::io;
+use thiserror::Error;
+
+const SHA256_PREFIX: &str = "sha256:";
+
+#[derive(Debug, Error)]
+#[error("{what} does not match its digest, {digest}, its SHA-256 hash is {actual}")]
+pub(crate) struct DigestMismatchError {
+    what: String,
+    digest: String,
+    actual: String,
+}
+
+// Hashes an asset's content as it's read or downloaded, so that it can be checked against the
+// asset's digest at the end.
+pub(crate) struct DigestChecker<'a> {
+    digest: &'a str,
+    hasher: Sha256,
+}
+
+impl<'a> DigestChecker<'a> {
+    // Returns `None` if there's no digest, or if it's not one we can check.
+    pub(crate) fn new(digest: Option<&'a str>) -> Option<Self> {
+        digest
+            .filter(|d| d.starts_with(SHA256_PREFIX))
+            .map(|digest| Self {
+                digest,
+                hasher: Sha256::new(),
+            })
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        self.hasher.update(bytes);
+    }
+
+    // The `what` describes the content that was hashed, for the error message.
+    pub(crate) fn check(self, what: &str) -> Result<(), DigestMismatchError> {
+        let actual = format!("{:x}", self.hasher.finalize());
+        if !actual.eq_ignore_ascii_case(&self.digest[SHA256_PREFIX.len()..]) {
+            return Err(DigestMismatchError {
+                what: what.to_string(),
+                digest: self.digest.to_string(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl io::Write for DigestChecker<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    // The SHA-256 hash of `some bytes`.
+    const SOME_BYTES_SHA256: &str =
+        "sha256:0d22cdcc10e6d049dbe1af5123d50873fdfc1a4f58306e58cb6241be9472014d";
+
+    #[rstest]
+    #[case::matches(SOME_BYTES_SHA256, Ok(()))]
+    #[case::matches_uppercase(
+        "sha256:0D22CDCC10E6D049DBE1AF5123D50873FDFC1A4F58306E58CB6241BE9472014D",
+        Ok(())
+    )]
+    #[case::does_not_match(
+        "sha256:0000",
+        Err("asset does not match its digest, sha256:0000, its SHA-256 hash is 0d22cdcc10e6d049dbe1af5123d50873fdfc1a4f58306e58cb6241be9472014d")
+    )]
+    fn check(#[case] digest: &str, #[case] expect: Result<(), &str>) {
+        let mut checker = DigestChecker::new(Some(digest)).expect("a SHA-256 digest is checked");
+        checker.update(b"some ");
+        checker.update(b"bytes");
+        assert_eq!(
+            checker.check("asset").map_err(|e| e.to_string()),
+            expect.map_err(str::to_string),
+        );
+    }
+
+    #[rstest]
+    #[case::no_digest(None)]
+    #[case::other_algorithm(Some("sha512:abc"))]
+    fn not_checked(#[case] digest: Option<&str>) {
+        assert!(DigestChecker::new(digest).is_none());
+    }
+}
@@ -22,26 +22,38 @@ pub(crate) enum ExtensionError {
 
 #[derive(Debug, EnumIter, PartialEq, Eq)]
 pub(crate) enum Extension {
+    Apk,
     AppImage,
     Bat,
+    Br,
     Bz,
     Bz2,
+    Deb,
     Exe,
     Gz,
     Jar,
+    Lz,
+    Lz4,
+    Lzma,
     Phar,
+    PkgTarZst,
     Py,
     Pyz,
+    Rpm,
     SevenZip,
     Sh,
     Tar,
     TarBz,
     TarBz2,
     TarGz,
+    TarLz,
+    TarLz4,
+    TarLzma,
     TarXz,
     TarZst,
     Tbz,
     Tgz,
+    Tlz,
     Txz,
     Tzst,
     Xz,
@@ -52,26 +64,38 @@ pub(crate) enum Extension {
 impl Extension {
     pub(crate) fn extension(&self) -> &'static str {
         match self {
+            Extension::Apk => ".apk",
             Extension::AppImage => ".AppImage",
             Extension::Bat => ".bat",
+            Extension::Br => ".br",
             Extension::Bz => ".bz",
             Extension::Bz2 => ".bz2",
+            Extension::Deb => ".deb",
             Extension::Exe => ".exe",
             Extension::Gz => ".gz",
             Extension::Jar => ".jar",
+            Extension::Lz => ".lz",
+            Extension::Lz4 => ".lz4",
+            Extension::Lzma => ".lzma",
             Extension::Phar => ".phar",
+            Extension::PkgTarZst => ".pkg.tar.zst",
             Extension::Py => ".py",
             Extension::Pyz => ".pyz",
+            Extension::Rpm => ".rpm",
             Extension::SevenZip => ".7z",
             Extension::Sh => ".sh",
             Extension::Tar => ".tar",
             Extension::TarBz => ".tar.bz",
             Extension::TarBz2 => ".tar.bz2",
             Extension::TarGz => ".tar.gz",
+            Extension::TarLz => ".tar.lz",
+            Extension::TarLz4 => ".tar.lz4",
+            Extension::TarLzma => ".tar.lzma",
             Extension::TarXz => ".tar.xz",
             Extension::TarZst => ".tar.zst",
             Extension::Tbz => ".tbz",
             Extension::Tgz => ".tgz",
+            Extension::Tlz => ".tlz",
             Extension::Txz => ".txz",
             Extension::Tzst => ".tzst",
             Extension::Xz => ".xz",
@@ -88,32 +112,86 @@ impl Extension {
         match self {
             Extension::AppImage
             | Extension::Bat
+            | Extension::Br
             | Extension::Bz
             | Extension::Bz2
             | Extension::Exe
             | Extension::Gz
             | Extension::Jar
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
             | Extension::Phar
             | Extension::Py
             | Extension::Pyz
             | Extension::Sh
             | Extension::Xz
             | Extension::Zst => false,
-            Extension::SevenZip
+            Extension::Apk
+            | Extension::Deb
+            | Extension::PkgTarZst
+            | Extension::Rpm
+            | Extension::SevenZip
             | Extension::Tar
             | Extension::TarBz
             | Extension::TarBz2
             | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
             | Extension::TarXz
             | Extension::TarZst
             | Extension::Tbz
             | Extension::Tgz
+            | Extension::Tlz
             | Extension::Txz
             | Extension::Tzst
             | Extension::Zip => true,
         }
     }
 
+    pub(crate) fn is_compressed(&self) -> bool {
+        match self {
+            Extension::Br
+            | Extension::Bz
+            | Extension::Bz2
+            | Extension::Gz
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
+            | Extension::Xz
+            | Extension::Zst => true,
+            Extension::Apk
+            | Extension::AppImage
+            | Extension::Bat
+            | Extension::Deb
+            | Extension::Exe
+            | Extension::Jar
+            | Extension::Phar
+            | Extension::PkgTarZst
+            | Extension::Py
+            | Extension::Pyz
+            | Extension::Rpm
+            | Extension::SevenZip
+            | Extension::Sh
+            | Extension::Tar
+            | Extension::TarBz
+            | Extension::TarBz2
+            | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
+            | Extension::TarXz
+            | Extension::TarZst
+            | Extension::Tbz
+            | Extension::Tgz
+            | Extension::Tlz
+            | Extension::Txz
+            | Extension::Tzst
+            | Extension::Zip => false,
+        }
+    }
+
     pub(crate) fn should_preserve_extension_on_install(&self) -> bool {
         match self {
             Extension::AppImage
@@ -124,18 +202,30 @@ impl Extension {
             | Extension::Py
             | Extension::Pyz
             | Extension::Sh => true,
-            Extension::Bz
+            Extension::Apk
+            | Extension::Br
+            | Extension::Bz
             | Extension::Gz
             | Extension::Bz2
+            | Extension::Deb
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
+            | Extension::PkgTarZst
+            | Extension::Rpm
             | Extension::SevenZip
             | Extension::Tar
             | Extension::TarBz
             | Extension::TarBz2
             | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
             | Extension::TarXz
             | Extension::TarZst
             | Extension::Tbz
             | Extension::Tgz
+            | Extension::Tlz
             | Extension::Txz
             | Extension::Tzst
             | Extension::Xz
@@ -144,14 +234,71 @@ impl Extension {
         }
     }
 
+    // The command that runs a file with this extension, for extensions that need an interpreter
+    // that we can write a launcher for. The path to the file is appended to this command.
+    pub(crate) fn launcher_command(&self, is_windows: bool) -> Option<&'static [&'static str]> {
+        match self {
+            Extension::Jar => Some(&["java", "-jar"]),
+            // On Windows, the Python installer from python.org doesn't create a `python3`
+            // executable.
+            Extension::Py | Extension::Pyz => Some(if is_windows {
+                &["python"]
+            } else {
+                &["python3"]
+            }),
+            Extension::Apk
+            | Extension::AppImage
+            | Extension::Bat
+            | Extension::Br
+            | Extension::Bz
+            | Extension::Bz2
+            | Extension::Deb
+            | Extension::Exe
+            | Extension::Gz
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
+            | Extension::Phar
+            | Extension::PkgTarZst
+            | Extension::Rpm
+            | Extension::SevenZip
+            | Extension::Sh
+            | Extension::Tar
+            | Extension::TarBz
+            | Extension::TarBz2
+            | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
+            | Extension::TarXz
+            | Extension::TarZst
+            | Extension::Tbz
+            | Extension::Tgz
+            | Extension::Tlz
+            | Extension::Txz
+            | Extension::Tzst
+            | Extension::Xz
+            | Extension::Zip
+            | Extension::Zst => None,
+        }
+    }
+
     pub(crate) fn matches_platform(&self, platform: &Platform) -> bool {
         match self {
-            Extension::AppImage => platform.target_os == OS::Linux,
+            Extension::Apk
+            | Extension::AppImage
+            | Extension::Deb
+            | Extension::PkgTarZst
+            | Extension::Rpm => platform.target_os == OS::Linux,
             Extension::Bat | Extension::Exe => platform.target_os == OS::Windows,
-            Extension::Bz
+            Extension::Br
+            | Extension::Bz
             | Extension::Bz2
             | Extension::Gz
             | Extension::Jar
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
             | Extension::Phar
             | Extension::Py
             | Extension::Pyz
@@ -161,10 +308,14 @@ impl Extension {
             | Extension::TarBz
             | Extension::TarBz2
             | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
             | Extension::TarXz
             | Extension::TarZst
             | Extension::Tbz
             | Extension::Tgz
+            | Extension::Tlz
             | Extension::Txz
             | Extension::Tzst
             | Extension::Xz
@@ -173,6 +324,91 @@ impl Extension {
         }
     }
 
+    // Returns true for the package formats used by Linux distros. These packages are only meant to
+    // be installed on Linux, even if the file name does not say so.
+    pub(crate) fn is_linux_package(&self) -> bool {
+        match self {
+            Extension::Apk | Extension::Deb | Extension::PkgTarZst | Extension::Rpm => true,
+            Extension::AppImage
+            | Extension::Bat
+            | Extension::Br
+            | Extension::Bz
+            | Extension::Bz2
+            | Extension::Exe
+            | Extension::Gz
+            | Extension::Jar
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
+            | Extension::Phar
+            | Extension::Py
+            | Extension::Pyz
+            | Extension::SevenZip
+            | Extension::Sh
+            | Extension::Tar
+            | Extension::TarBz
+            | Extension::TarBz2
+            | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
+            | Extension::TarXz
+            | Extension::TarZst
+            | Extension::Tbz
+            | Extension::Tgz
+            | Extension::Tlz
+            | Extension::Txz
+            | Extension::Tzst
+            | Extension::Xz
+            | Extension::Zip
+            | Extension::Zst => false,
+        }
+    }
+
+    // Returns true for tarballs that we can extract as they're downloaded. A `.deb` package is a
+    // tarball inside an `ar` archive, and we need to seek in the `ar` archive to find it.
+    pub(crate) fn can_stream(&self) -> bool {
+        match self {
+            Extension::Apk
+            | Extension::PkgTarZst
+            | Extension::Tar
+            | Extension::TarBz
+            | Extension::TarBz2
+            | Extension::TarGz
+            | Extension::TarLz
+            | Extension::TarLz4
+            | Extension::TarLzma
+            | Extension::TarXz
+            | Extension::TarZst
+            | Extension::Tbz
+            | Extension::Tgz
+            | Extension::Tlz
+            | Extension::Txz
+            | Extension::Tzst => true,
+            Extension::AppImage
+            | Extension::Bat
+            | Extension::Br
+            | Extension::Bz
+            | Extension::Bz2
+            | Extension::Deb
+            | Extension::Exe
+            | Extension::Gz
+            | Extension::Jar
+            | Extension::Lz
+            | Extension::Lz4
+            | Extension::Lzma
+            | Extension::Phar
+            | Extension::Py
+            | Extension::Pyz
+            | Extension::Rpm
+            | Extension::SevenZip
+            | Extension::Sh
+            | Extension::Xz
+            | Extension::Zip
+            | Extension::Zst => false,
+        }
+    }
+
     pub(crate) fn is_windows_only(&self) -> bool {
         matches!(self, Extension::Bat | Extension::Exe)
     }
@@ -264,23 +500,35 @@ mod test {
     use rstest::rstest;
 
     #[rstest]
+    #[case::Apk("foo.apk", Ok(Some(Extension::Apk)))]
     #[case::AppImage("foo.AppImage", Ok(Some(Extension::AppImage)))]
+    #[case::Br("foo.br", Ok(Some(Extension::Br)))]
     #[case::Bz("foo.bz", Ok(Some(Extension::Bz)))]
     #[case::Bz1("foo.bz2", Ok(Some(Extension::Bz2)))]
+    #[case::Deb("foo.deb", Ok(Some(Extension::Deb)))]
     #[case::Exe("foo.exe", Ok(Some(Extension::Exe)))]
     #[case::Gz("foo.gz", Ok(Some(Extension::Gz)))]
     #[case::Jar("foo.jar", Ok(Some(Extension::Jar)))]
+    #[case::Lz("foo.lz", Ok(Some(Extension::Lz)))]
+    #[case::Lz4("foo.lz4", Ok(Some(Extension::Lz4)))]
+    #[case::Lzma("foo.lzma", Ok(Some(Extension::Lzma)))]
     #[case::Phar("foo.phar", Ok(Some(Extension::Phar)))]
+    #[case::PkgTarZst("foo-1.2.3-1-x86_64.pkg.tar.zst", Ok(Some(Extension::PkgTarZst)))]
     #[case::Py("foo.py", Ok(Some(Extension::Py)))]
     #[case::Pyz("foo.pyz", Ok(Some(Extension::Pyz)))]
+    #[case::Rpm("foo.rpm", Ok(Some(Extension::Rpm)))]
     #[case::Sh("foo.sh", Ok(Some(Extension::Sh)))]
     #[case::Tar("foo.tar", Ok(Some(Extension::Tar)))]
     #[case::SevenZip("foo.7z", Ok(Some(Extension::SevenZip)))]
     #[case::TarBz("foo.tar.bz", Ok(Some(Extension::TarBz)))]
     #[case::TarBz2("foo.tar.bz2", Ok(Some(Extension::TarBz2)))]
     #[case::TarGz("foo.tar.gz", Ok(Some(Extension::TarGz)))]
+    #[case::TarLz("foo.tar.lz", Ok(Some(Extension::TarLz)))]
+    #[case::TarLz4("foo.tar.lz4", Ok(Some(Extension::TarLz4)))]
+    #[case::TarLzma("foo.tar.lzma", Ok(Some(Extension::TarLzma)))]
     #[case::TarXz("foo.tar.xz", Ok(Some(Extension::TarXz)))]
     #[case::TarZst("foo.tar.zst", Ok(Some(Extension::TarZst)))]
+    #[case::Tlz("foo.tlz", Ok(Some(Extension::Tlz)))]
     #[case::Xz("foo.xz", Ok(Some(Extension::Xz)))]
     #[case::Zip("foo.zip", Ok(Some(Extension::Zip)))]
     #[case::no_extension("foo", Ok(None))]
@@ -1,12 +1,12 @@
-use crate::{forgejo, github, gitlab, ubi::Asset};
+use crate::{forgejo, github, gitlab, rate_limit::RateLimit, retry::Retry, ubi::Asset};
 use anyhow::{anyhow, Context, Result};
-use log::debug;
+use log::{debug, info};
 use reqwest::{
     header::{HeaderValue, ACCEPT, AUTHORIZATION},
-    Client, RequestBuilder, Response,
+    Client, RequestBuilder, Response, StatusCode,
 };
 use serde::Deserialize;
-use std::env;
+use std::{env, time::Duration};
 use url::Url;
 
 // It'd be nice to use clap::ValueEnum here, but then we'd need to add clap as a dependency for the
@@ -33,6 +33,9 @@ pub(crate) struct Forge {
     token: Option<String>,
     #[allow(clippy::struct_field_names)] // We can't call this `type`.
     forge_type: ForgeType,
+    retry: Retry,
+    // If a request hits a rate limit that resets within this long, we wait for it to reset.
+    max_rate_limit_wait: Option<Duration>,
 }
 
 unsafe impl Send for Forge {}
@@ -41,14 +44,18 @@ unsafe impl Sync for Forge {}
 // Unified release structure that works for both GitHub and GitLab
 // using serde attributes to handle the differences
 #[derive(Debug, Deserialize)]
-struct Release {
+pub(crate) struct Release {
+    // All of the forges we support call this "tag_name".
+    #[serde(default)]
+    pub(crate) tag_name: Option<String>,
+
     // Accept both "published_at" (GitHub) and "released_at" (GitLab)
     #[serde(alias = "published_at", alias = "released_at")]
     date: chrono::DateTime<chrono::Utc>,
 
     // Handle both direct array (GitHub) and nested object (GitLab)
     #[serde(deserialize_with = "deserialize_assets")]
-    assets: Vec<Asset>,
+    pub(crate) assets: Vec<Asset>,
 }
 
 // Custom deserializer to handle both GitHub's direct array and GitLab's nested structure
@@ -70,21 +77,20 @@ where
 }
 
 impl Forge {
-    pub(crate) async fn fetch_assets(&self, client: &Client) -> Result<Vec<Asset>> {
+    pub(crate) async fn fetch_release(&self, client: &Client) -> Result<Release> {
         debug!("Fetching assets for project `{}`", self.project_name);
         let response = self.make_release_info_request(client).await?;
-        Ok(response
+        response
             .json::<Release>()
             .await
-            .context("failed to parse release JSON response")?
-            .assets)
+            .context("failed to parse release JSON response")
     }
 
-    pub(crate) async fn fetch_assets_with_min_age(
+    pub(crate) async fn fetch_release_with_min_age(
         &self,
         client: &Client,
         min_age_days: u32,
-    ) -> Result<Vec<Asset>> {
+    ) -> Result<Release> {
         debug!(
             "Fetching assets for project `{}` with minimum age of {min_age_days} days",
             self.project_name
@@ -97,7 +103,7 @@ impl Forge {
                     "Found release from {} (older than {min_age_days} days)",
                     release.date
                 );
-                return Ok(release.assets);
+                return Ok(release);
             }
         }
 
@@ -135,22 +141,70 @@ impl Forge {
     ) -> Result<Response> {
         debug!("{log_message} from `{url}`");
 
-        let mut req_builder = client.get(url.clone()).header(
-            ACCEPT,
-            HeaderValue::from_str("application/json")
-                .context("failed to create header value for Accept header")?,
-        );
-        req_builder = self.maybe_add_token_header(req_builder)?;
-        let req = req_builder
-            .build()
-            .with_context(|| format!("failed to build HTTP request for {url}"))?;
-        let resp = client
-            .execute(req)
-            .await
-            .with_context(|| format!("failed to execute HTTP request to {url}"))?;
+        // We only wait for a rate limit to reset once. If we hit it again after that, something
+        // else is using up the limit, and waiting again may not help.
+        let mut waited = false;
+        loop {
+            let mut req_builder = client.get(url.clone()).header(
+                ACCEPT,
+                HeaderValue::from_str("application/json")
+                    .context("failed to create header value for Accept header")?,
+            );
+            req_builder = self.maybe_add_token_header(req_builder)?;
+            let req = req_builder
+                .build()
+                .with_context(|| format!("failed to build HTTP request for {url}"))?;
+            let resp = self
+                .retry
+                .execute(client, req)
+                .await
+                .with_context(|| format!("failed to execute HTTP request to {url}"))?;
+
+            let status = resp.status();
+            if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+                return resp
+                    .error_for_status()
+                    .with_context(|| format!("HTTP request to {url} returned an error status"));
+            }
 
-        resp.error_for_status()
-            .with_context(|| format!("HTTP request to {url} returned an error status"))
+            let headers = resp.headers().clone();
+            let body = resp.text().await.unwrap_or_default();
+            let Some(limit) = RateLimit::from_response(status, &headers, &body) else {
+                return Err(anyhow!(
+                    "HTTP request to {url} returned an error status: {status}\n{body}"
+                ));
+            };
+
+            match (limit.wait(), self.max_rate_limit_wait) {
+                (Some(wait), Some(max)) if !waited && wait <= max => {
+                    info!(
+                        "The request to {url} hit the {} API's rate limit. Waiting {} seconds for it to reset.",
+                        self.forge_type.forge_name(),
+                        wait.as_secs(),
+                    );
+                    tokio::time::sleep(wait).await;
+                    waited = true;
+                }
+                _ => {
+                    let token_env_vars = if self.token.is_some() {
+                        &[]
+                    } else {
+                        self.forge_type.env_var_names()
+                    };
+                    return Err(limit
+                        .into_error(self.forge_type.forge_name(), url, token_env_vars)
+                        .into());
+                }
+            }
+        }
+    }
+
+    pub(crate) fn project_name(&self) -> &str {
+        &self.project_name
+    }
+
+    pub(crate) fn forge_type(&self) -> &ForgeType {
+        &self.forge_type
     }
 
     pub(crate) fn maybe_add_token_header(
@@ -233,6 +287,8 @@ impl ForgeType {
         tag: Option<String>,
         api_base: Option<String>,
         mut token: Option<String>,
+        retry: Retry,
+        max_rate_limit_wait: Option<Duration>,
     ) -> Result<Forge> {
         let api_base_url = if let Some(api_base) = api_base {
             Url::parse(&api_base)
@@ -260,6 +316,8 @@ impl ForgeType {
             api_base_url,
             token,
             forge_type: self,
+            retry,
+            max_rate_limit_wait,
         })
     }
 
@@ -379,13 +437,17 @@ mod tests {
             tag.map(String::from),
             Some(server.url()),
             token.map(String::from),
+            Retry::default(),
+            None,
         )?;
 
         let client = Client::new();
-        let got_assets = forge.fetch_assets(&client).await?;
+        let got_assets = forge.fetch_release(&client).await?.assets;
         let expect_assets = vec![Asset {
             name: "asset1".to_string(),
             url: asset_url,
+            size: None,
+            digest: None,
         }];
         assert_eq!(got_assets, expect_assets);
 
@@ -437,6 +499,8 @@ mod tests {
         let assets = vec![Asset {
             name: "asset1".to_string(),
             url: Url::parse("https://api.github.com/repos/houseabsolute/ubi/releases/assets/1")?,
+            size: None,
+            digest: None,
         }];
 
         let expect_path = if let Some(tag) = tag {
@@ -468,10 +532,12 @@ mod tests {
             tag.map(String::from),
             Some(server.url()),
             token.map(String::from),
+            Retry::default(),
+            None,
         )?;
 
         let client = Client::new();
-        let got_assets = forge.fetch_assets(&client).await?;
+        let got_assets = forge.fetch_release(&client).await?.assets;
         assert_eq!(got_assets, assets);
 
         m.assert_async().await;
@@ -525,6 +591,8 @@ mod tests {
         let assets = vec![Asset {
             name: "asset1".to_string(),
             url: Url::parse("https://gitlab.com/api/v4/projects/owner%2Frepo/releases/assets/1")?,
+            size: None,
+            digest: None,
         }];
 
         let expect_path = if let Some(tag) = tag {
@@ -558,10 +626,12 @@ mod tests {
             tag.map(String::from),
             Some(server.url()),
             token.map(String::from),
+            Retry::default(),
+            None,
         )?;
 
         let client = Client::new();
-        let got_assets = forge.fetch_assets(&client).await?;
+        let got_assets = forge.fetch_release(&client).await?.assets;
         assert_eq!(got_assets, assets);
 
         m.assert_async().await;
@@ -573,6 +643,91 @@ mod tests {
         Ok(())
     }
 
+    #[test(tokio::test)]
+    #[serial]
+    async fn github_rate_limit_error() -> Result<()> {
+        let vars = env::vars();
+        env::remove_var("GITHUB_TOKEN");
+
+        let mut server = Server::new_async().await;
+        let m = server
+            .mock("GET", "/repos/houseabsolute/ubi/releases/latest")
+            .with_status(403)
+            .with_header("x-ratelimit-remaining", "0")
+            .with_header("x-ratelimit-reset", "1743465600")
+            .with_body(r#"{"message":"API rate limit exceeded"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let forge = ForgeType::GitHub.new_forge(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Some(server.url()),
+            None,
+            Retry::default(),
+            None,
+        )?;
+        let err = forge.fetch_release(&Client::new()).await.unwrap_err();
+        let rate_limit_err = err
+            .downcast_ref::<crate::RateLimitError>()
+            .expect("error is a RateLimitError");
+        assert!(!rate_limit_err.is_secondary());
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "the request to {}/repos/houseabsolute/ubi/releases/latest hit the GitHub API's rate limit. The limit resets at 2025-04-01 00:00:00 UTC. Set the GITHUB_TOKEN env var to an API token to get a higher rate limit.",
+                server.url(),
+            ),
+        );
+
+        m.assert_async().await;
+
+        for (k, v) in vars {
+            env::set_var(k, v);
+        }
+
+        Ok(())
+    }
+
+    #[test(tokio::test)]
+    async fn github_wait_for_rate_limit() -> Result<()> {
+        let mut server = Server::new_async().await;
+        let limited = server
+            .mock("GET", "/repos/houseabsolute/ubi/releases/latest")
+            .with_status(403)
+            .with_header("retry-after", "1")
+            .with_body(r#"{"message":"You have exceeded a secondary rate limit."}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("GET", "/repos/houseabsolute/ubi/releases/latest")
+            .with_status(200)
+            .with_body(serde_json::to_string(&github::Release {
+                assets: vec![],
+                published_at: chrono::Utc::now(),
+            })?)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let forge = ForgeType::GitHub.new_forge(
+            "houseabsolute/ubi".to_string(),
+            None,
+            Some(server.url()),
+            Some("ghp_fakeToken".to_string()),
+            Retry::default(),
+            Some(Duration::from_secs(5)),
+        )?;
+        forge.fetch_release(&Client::new()).await?;
+
+        limited.assert_async().await;
+        ok.assert_async().await;
+
+        Ok(())
+    }
+
     #[test]
     fn gitlab_api_base_url() -> Result<()> {
         let url = ForgeType::GitLab.release_info_url(
@@ -1,59 +1,295 @@
 use crate::{
-    archive::{ArchiveEntry, SevenZipEntriesIterator, TarEntriesIterator, ZipEntriesIterator},
+    appimage,
+    arch::{arch_re, ALL_ARCHES_RE},
+    archive::{
+        normalize_member_path, sevenz_password, zip_entry, ArchiveEntry, ArchiveLink,
+        CpioEntriesIterator, SevenZipEntriesIterator, TarEntriesIterator, ZipEntriesIterator,
+    },
+    binary,
+    cpio::CpioArchive,
+    deb,
     extension::Extension,
+    launcher::{Launcher, LauncherConfig},
+    limits::{ExtractionBudget, ExtractionLimits},
+    lzip, magic,
+    os::{os_re, ALL_OSES_RE},
+    rpm,
+    safety::{check_extraction_path, check_member, MemberKind},
     ubi::Download,
 };
 use anyhow::{anyhow, Context, Result};
-use binstall_tar::Archive as TarArchive;
+use binstall_tar::{Archive as TarArchive, EntryType};
+use brotli_decompressor::Decompressor as BrotliDecoder;
 use bzip2::read::BzDecoder;
-use flate2::read::GzDecoder;
-use log::{debug, info};
+use flate2::read::{GzDecoder, MultiGzDecoder};
+use itertools::Itertools;
+use lazy_regex::Lazy;
+use log::{debug, info, warn};
+use lz4_flex::frame::FrameDecoder as Lz4Decoder;
+use platforms::{Platform, OS};
+use regex::Regex;
 use std::{
-    collections::HashMap,
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     ffi::OsString,
     fmt::Debug,
     fs::{self, create_dir_all, File},
-    io::{Read, Write},
+    io::{self, Read},
     path::{Path, PathBuf},
+    rc::Rc,
 };
 use strum::IntoEnumIterator;
 use tempfile::{tempdir, TempDir};
 use walkdir::WalkDir;
-use xz2::read::XzDecoder;
+use xz2::{read::XzDecoder, stream::Stream as XzStream};
 use zip::ZipArchive;
 use zstd::stream::read::Decoder as ZstdDecoder;
 
+#[cfg(target_family = "unix")]
+use crate::cpio::{S_IFLNK, S_IFMT};
 #[cfg(target_family = "unix")]
 use std::fs::{set_permissions, Permissions};
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::PermissionsExt;
 
-pub(crate) trait Installer: Debug {
-    fn install(&self, download: &Download) -> Result<()>;
+// This is the buffer size used in the `brotli_decompressor` docs.
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+// Installers must be `Send` and `Sync` so that a streamed download can be extracted on its own
+// thread.
+pub(crate) trait Installer: Debug + Send + Sync {
+    fn install(&self, download: &Download) -> Result<Installed>;
+    // Installs from a tarball that is read from `reader` as it's downloaded. The `name` is the name
+    // of the release asset, and `ext` must be an extension that `Extension::can_stream` accepts.
+    //
+    // The downloader reports a bad download, like one that doesn't match its digest, as an error
+    // from the last read. An installer that can't undo what it installed must read `reader` to the
+    // end before installing anything.
+    fn install_from_stream(
+        &self,
+        name: &str,
+        ext: &Extension,
+        reader: Box<dyn Read>,
+    ) -> Result<Installed>;
 }
 
+// What an installer installed.
 #[derive(Debug)]
+pub(crate) enum Installed {
+    Exe(InstalledExe),
+    // The directory that an archive's contents were extracted into.
+    Archive(PathBuf),
+}
+
+#[derive(Debug, Clone)]
 pub(crate) struct ExeInstaller {
     install_path: PathBuf,
     install_path_is_from_rename_exe_to: bool,
     exe_file_stem: String,
+    // This is false when the user told us the executable's name, in which case we shouldn't guess.
+    can_infer_exe: bool,
     is_windows: bool,
     extensions: Vec<&'static str>,
+    platform: Platform,
+    allow_platform_mismatch: bool,
+    // If this is set, artifacts that can't be run directly, like `.jar` files, are installed in a
+    // lib directory, and a launcher for them is installed in the install directory.
+    launcher_config: Option<LauncherConfig>,
+    // Used to decrypt encrypted zip and 7z archives.
+    archive_password: Option<String>,
+    // Tracks how much we've extracted, so we can stop if the download turns out to be a
+    // decompression bomb.
+    budget: ExtractionBudget,
+}
+
+// An executable that was just installed, along with backups of the files it replaced, if there
+// were any. When we install a launcher, `path` is the launcher, and the launched artifact is also
+// one of the installed files. The backups are kept until we know that we want to keep the new
+// executable. Either `commit` or `roll_back` must be called to clean up.
+#[derive(Debug)]
+pub(crate) struct InstalledExe {
+    pub(crate) path: PathBuf,
+    files: Vec<InstalledFile>,
+}
+
+#[derive(Debug)]
+struct InstalledFile {
+    path: PathBuf,
+    backup: Option<PathBuf>,
 }
 
 #[derive(Debug)]
 pub(crate) struct ArchiveInstaller {
     project_name: String,
     install_root: PathBuf,
+    archive_password: Option<String>,
+    extraction_limits: ExtractionLimits,
+}
+
+// The index of the archive member whose content should be installed, along with the path of the
+// member that matched the name we were looking for. These differ when the matching member is a link.
+#[derive(Debug)]
+struct ArchiveMatch {
+    index: usize,
+    path: PathBuf,
+    // This is true when nothing matched the name we were looking for, and we picked the archive's
+    // only executable instead. In that case, it's installed with its own name.
+    is_inferred: bool,
+}
+
+#[derive(Debug)]
+struct ArchiveMember {
+    path: PathBuf,
+    is_file: bool,
+    is_executable: Option<bool>,
+    link: Option<ArchiveLink>,
+    // This is `None` if the member isn't a binary executable that we recognize.
+    runs_on_platform: Option<bool>,
+}
+
+// When we can only read an archive once, like a compressed tarball, we copy the content of each
+// member that might be installed into a temp dir as we look through the archive. Once we've picked
+// the best match, we install it from here instead of reading the archive a second time.
+#[derive(Debug)]
+struct MemberSpool {
+    dir: TempDir,
+    files: HashMap<usize, PathBuf>,
+    // The normalized targets of links that might be installed. We need to spool these targets too.
+    link_targets: HashSet<PathBuf>,
+}
+
+impl MemberSpool {
+    fn new() -> Result<Self> {
+        Ok(MemberSpool {
+            dir: tempdir().context("failed to create temporary directory for archive members")?,
+            files: HashMap::new(),
+            link_targets: HashSet::new(),
+        })
+    }
+
+    fn add(&mut self, index: usize, mut content: impl Read) -> Result<()> {
+        let path = self.dir.path().join(index.to_string());
+        let mut writer = File::create(&path)
+            .with_context(|| format!("failed to create file at {}", path.display()))?;
+        io::copy(&mut content, &mut writer).with_context(|| {
+            format!(
+                "failed to write archive member content to {}",
+                path.display()
+            )
+        })?;
+        self.files.insert(index, path);
+        Ok(())
+    }
+
+    fn take(&mut self, index: usize) -> Option<PathBuf> {
+        self.files.remove(&index)
+    }
+}
+
+// How well an archive member fits the platform we're installing for. These are ordered from the
+// best fit to the worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum PlatformFit {
+    Matches,
+    Unknown,
+    Mismatch,
+}
+
+impl PlatformFit {
+    // A name that mentions our OS or architecture matches, while one that only mentions some other
+    // OS or architecture does not.
+    fn from_name(name: &str, ours: Option<&Lazy<Regex>>, all: &Regex) -> Self {
+        if ours.is_some_and(|re| re.is_match(name)) {
+            PlatformFit::Matches
+        } else if all.is_match(name) {
+            PlatformFit::Mismatch
+        } else {
+            PlatformFit::Unknown
+        }
+    }
+}
+
+// This is more than enough for any sane archive, and it keeps us from looping forever on a link
+// cycle.
+const MAX_LINK_DEPTH: usize = 16;
+
+// Follows links from the member at `idx` until it reaches a regular file, returning that file's
+// index.
+fn resolve_archive_member(members: &[ArchiveMember], mut idx: usize) -> Option<usize> {
+    for _ in 0..MAX_LINK_DEPTH {
+        let member = &members[idx];
+        let Some(link) = &member.link else {
+            return member.is_file.then_some(idx);
+        };
+
+        let Some(target) = link.target_path(&member.path) else {
+            debug!(
+                "the link at {} points outside of the archive",
+                member.path.display()
+            );
+            return None;
+        };
+        debug!(
+            "the link at {} points to {}",
+            member.path.display(),
+            target.display()
+        );
+        // If the same path appears more than once, the last one wins, just as it would if the
+        // archive were unpacked.
+        let Some(target_idx) = members
+            .iter()
+            .rposition(|m| normalize_member_path(&m.path).as_ref() == Some(&target))
+        else {
+            debug!(
+                "could not find the link target {} in the archive",
+                target.display()
+            );
+            return None;
+        };
+        idx = target_idx;
+    }
+
+    debug!("gave up resolving links after {MAX_LINK_DEPTH} levels");
+    None
+}
+
+// The maximum number of archives we will look through to find an executable, counting the downloaded
+// file itself. This allows for something like a zip file containing a tarball that contains a
+// gzipped executable.
+const MAX_ARCHIVE_DEPTH: usize = 3;
+
+// If the archive member at `path` is an archive or compressed file that we can look inside of, this
+// returns its extension.
+fn nested_archive_extension(path: &Path, depth: usize) -> Option<Extension> {
+    if depth + 1 >= MAX_ARCHIVE_DEPTH {
+        return None;
+    }
+    Extension::from_path(path)
+        .ok()
+        .flatten()
+        .filter(|ext| ext.is_archive() || ext.is_compressed())
+}
+
+fn is_in_bin_dir(path: &Path) -> bool {
+    path.parent()
+        .and_then(Path::file_name)
+        .is_some_and(|dir| dir == "bin" || dir == "sbin")
 }
 
 impl ExeInstaller {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         install_path: PathBuf,
         install_path_is_from_rename_exe_to: bool,
         exe: String,
-        is_windows: bool,
+        can_infer_exe: bool,
+        platform: Platform,
+        allow_platform_mismatch: bool,
+        launcher_config: Option<LauncherConfig>,
+        archive_password: Option<String>,
+        extraction_limits: ExtractionLimits,
     ) -> Self {
+        let is_windows = platform.target_os == OS::Windows;
         let extensions = if is_windows {
             Extension::iter()
                 .filter(super::extension::Extension::is_windows_only)
@@ -67,25 +303,47 @@ impl ExeInstaller {
             install_path,
             install_path_is_from_rename_exe_to,
             exe_file_stem: exe,
+            can_infer_exe,
             is_windows,
             extensions,
+            platform,
+            allow_platform_mismatch,
+            launcher_config,
+            archive_password,
+            budget: ExtractionBudget::new(extraction_limits),
         }
     }
 
-    fn extract_executable(&self, downloaded_file: &Path) -> Result<Option<PathBuf>> {
-        match Extension::from_path(downloaded_file)? {
+    // The `depth` is the number of archive files we're nested inside of. For a downloaded file it's
+    // 0, for an archive in that file it's 1, and so on.
+    fn extract_executable(&self, downloaded_file: &Path, depth: usize) -> Result<Option<PathBuf>> {
+        match magic::detect_extension(downloaded_file)? {
             Some(
-                Extension::Tar
+                ext @ (Extension::Tar
                 | Extension::TarBz
                 | Extension::TarBz2
                 | Extension::TarGz
+                | Extension::TarLz
+                | Extension::TarLz4
+                | Extension::TarLzma
                 | Extension::TarXz
                 | Extension::TarZst
                 | Extension::Tbz
                 | Extension::Tgz
+                | Extension::Tlz
                 | Extension::Txz
-                | Extension::Tzst,
-            ) => Ok(Some(self.extract_executable_from_tarball(downloaded_file)?)),
+                | Extension::Tzst
+                | Extension::Apk
+                | Extension::Deb
+                | Extension::PkgTarZst),
+            ) => Ok(Some(self.extract_executable_from_tarball(
+                downloaded_file,
+                &ext,
+                depth,
+            )?)),
+            Some(Extension::Rpm) => Ok(Some(
+                self.extract_executable_from_rpm(downloaded_file, depth)?,
+            )),
             Some(Extension::Bz | Extension::Bz2) => {
                 self.unbzip(downloaded_file)?;
                 Ok(None)
@@ -94,6 +352,22 @@ impl ExeInstaller {
                 self.ungzip(downloaded_file)?;
                 Ok(None)
             }
+            Some(Extension::Br) => {
+                self.unbrotli(downloaded_file)?;
+                Ok(None)
+            }
+            Some(Extension::Lz) => {
+                self.unlzip(downloaded_file)?;
+                Ok(None)
+            }
+            Some(Extension::Lz4) => {
+                self.unlz4(downloaded_file)?;
+                Ok(None)
+            }
+            Some(Extension::Lzma) => {
+                self.unlzma(downloaded_file)?;
+                Ok(None)
+            }
             Some(Extension::Xz) => {
                 self.unxz(downloaded_file)?;
                 Ok(None)
@@ -102,10 +376,12 @@ impl ExeInstaller {
                 self.unzstd(downloaded_file)?;
                 Ok(None)
             }
-            Some(Extension::SevenZip) => {
-                Ok(Some(self.extract_executable_from_7z(downloaded_file)?))
-            }
-            Some(Extension::Zip) => Ok(Some(self.extract_executable_from_zip(downloaded_file)?)),
+            Some(Extension::SevenZip) => Ok(Some(
+                self.extract_executable_from_7z(downloaded_file, depth)?,
+            )),
+            Some(Extension::Zip) => Ok(Some(
+                self.extract_executable_from_zip(downloaded_file, depth)?,
+            )),
             Some(
                 Extension::AppImage
                 | Extension::Bat
@@ -120,90 +396,108 @@ impl ExeInstaller {
         }
     }
 
-    fn extract_executable_from_tarball(&self, downloaded_file: &Path) -> Result<PathBuf> {
+    fn extract_executable_from_tarball(
+        &self,
+        downloaded_file: &Path,
+        ext: &Extension,
+        depth: usize,
+    ) -> Result<PathBuf> {
         debug!(
             "extracting executable from tarball at {}",
             downloaded_file.display(),
         );
 
-        // Iterating through the archive both here and in `best_match_from_archive` is really
-        // gross. But this is necessary because the underlying `Entry` structs returned by
-        // `arch.entries` are only valid for the duration of the loop iteration. That's because they
-        // rely on the position of the underlying file handle. It'd be nice to just be able to seek
-        // that handle back to the start of the file, but the readers provided by various decoders,
-        // like `BzDecoder`, do not implement the `Seek` trait.
-        //
-        // So the only viable solution is find the entry, then _re-open_ the file and go through the
-        // entries again until we find the one we want.
-
-        let mut arch = tar_reader_for(downloaded_file)?;
-        let entries = arch.entries().with_context(|| {
-            format!(
-                "failed to get entries from tarball at {}",
-                downloaded_file.display()
-            )
-        })?;
-        if let Some(idx) =
-            self.best_match_from_archive(TarEntriesIterator::new(entries), "tarball")?
-        {
-            let mut arch2 = tar_reader_for(downloaded_file)?;
+        self.extract_executable_from_tar_archive(
+            tar_reader_for(downloaded_file, ext)?,
+            Some(downloaded_file),
+            ext,
+            depth,
+        )
+    }
+
+    // This reads the tarball in `arch` once. If the member we pick wasn't spooled, we read the
+    // tarball a second time, which is only possible if it's in `downloaded_file`. When the tarball
+    // is streamed from the download, there is no file.
+    fn extract_executable_from_tar_archive(
+        &self,
+        mut arch: TarArchive<Box<dyn Read>>,
+        downloaded_file: Option<&Path>,
+        ext: &Extension,
+        depth: usize,
+    ) -> Result<PathBuf> {
+        let source = downloaded_file.map_or_else(
+            || String::from("streamed tarball"),
+            |f| format!("tarball at {}", f.display()),
+        );
+        let entries = arch
+            .entries()
+            .with_context(|| format!("failed to get entries from {source}"))?;
+        let mut spool = MemberSpool::new()?;
+        if let Some(m) = self.best_match_from_archive(
+            TarEntriesIterator::new(entries),
+            "tarball",
+            depth,
+            Some(&mut spool),
+        )? {
+            if let Some(spooled) = spool.take(m.index) {
+                debug!(
+                    "installing spooled tarball entry at index {} for {}",
+                    m.index,
+                    m.path.display(),
+                );
+                // The budget already counted these bytes when we spooled them.
+                return self.install_archive_member(&m, open_file(&spooled)?, depth);
+            }
+
+            // The entries returned by `arch.entries` are only valid for the duration of the loop
+            // iteration, and the readers provided by decoders like `BzDecoder` can't seek back to
+            // the start of the file. So if we didn't spool the member we picked, which can happen
+            // when it's the target of a link that came before the link, we have to re-open the file
+            // and go through the entries again until we find the one we want.
+            let Some(downloaded_file) = downloaded_file else {
+                return Err(anyhow!(
+                    concat!(
+                        "the best match in the {}, {}, is a link to an earlier entry that was not",
+                        " kept while reading the tarball, so it cannot be installed without",
+                        " downloading the tarball to a file first",
+                    ),
+                    source,
+                    m.path.display(),
+                ));
+            };
+            debug!(
+                "the tarball entry at index {} was not spooled, so reading the tarball again",
+                m.index,
+            );
+            let mut arch2 = tar_reader_for(downloaded_file, ext)?;
             for (i, entry) in arch2
                 .entries()
-                .with_context(|| {
-                    format!(
-                        "failed to get entries from tarball at {}",
-                        downloaded_file.display()
-                    )
-                })?
+                .with_context(|| format!("failed to get entries from {source}"))?
                 .enumerate()
             {
                 let mut entry = entry.with_context(|| {
-                    format!(
-                        "failed to read tarball entry at index {i} from {}",
-                        downloaded_file.display()
-                    )
+                    format!("failed to read tarball entry at index {i} from {source}")
                 })?;
-                if i != idx {
+                if i != m.index {
                     continue;
                 }
 
-                let entry_path = entry
-                    .path()
-                    .with_context(|| {
-                        format!(
-                            "failed to get path from tarball entry at index {i} in {}",
-                            downloaded_file.display()
-                        )
-                    })?
-                    .into_owned();
-                let install_path = self.maybe_munged_install_path(&entry_path)?;
                 debug!(
-                    "extracting tarball entry named {} to {}",
-                    entry_path.display(),
-                    install_path.display(),
+                    "extracting tarball entry at index {i} for {}",
+                    m.path.display(),
+                );
+                return self.install_archive_member(
+                    &m,
+                    self.budget.reader(&m.path, &mut entry),
+                    depth,
                 );
-                self.create_install_dir().with_context(|| {
-                    format!(
-                        "failed to create installation directory for {}",
-                        install_path.display()
-                    )
-                })?;
-                entry.unpack(&install_path).with_context(|| {
-                    format!(
-                        "failed to extract executable named {} into {}",
-                        entry_path.display(),
-                        install_path.display()
-                    )
-                })?;
-
-                return Ok(install_path);
             }
         }
 
         self.could_not_find_archive_matches_error()
     }
 
-    fn extract_executable_from_7z(&self, downloaded_file: &Path) -> Result<PathBuf> {
+    fn extract_executable_from_7z(&self, downloaded_file: &Path, depth: usize) -> Result<PathBuf> {
         debug!(
             "extracting executable from 7z file at {}",
             downloaded_file.display()
@@ -213,7 +507,7 @@ impl ExeInstaller {
             SevenZipEntriesIterator::new(
                 sevenz_rust2::ArchiveReader::new(
                     open_file(downloaded_file)?,
-                    sevenz_rust2::Password::empty(),
+                    sevenz_password(self.archive_password.as_deref()),
                 )
                 .with_context(|| {
                     format!(
@@ -223,12 +517,14 @@ impl ExeInstaller {
                 })?,
             ),
             "sevenzip",
+            depth,
+            None,
         )?;
 
-        if let Some(idx) = best_match {
+        if let Some(m) = best_match {
             let mut archive = sevenz_rust2::ArchiveReader::new(
                 open_file(downloaded_file)?,
-                sevenz_rust2::Password::empty(),
+                sevenz_password(self.archive_password.as_deref()),
             )
             .with_context(|| {
                 format!(
@@ -237,19 +533,14 @@ impl ExeInstaller {
                 )
             })?;
 
-            let entry = archive.archive().files[idx].clone();
-            let path = entry.path().with_context(|| {
-                format!(
-                    "failed to get path from 7z entry at index {idx} in {}",
-                    downloaded_file.display()
-                )
-            })?;
-            let install_path = self.maybe_munged_install_path(&path)?;
+            let entry = archive.archive().files[m.index].clone();
             debug!(
-                "extracting 7z entry named {} to {}",
-                path.display(),
-                install_path.display(),
+                "extracting 7z entry named {} for {}",
+                entry.name(),
+                m.path.display(),
             );
+            // `read_file` reads the whole entry into memory, so we check its size first.
+            self.budget.check_file(&m.path, entry.size())?;
             let buffer = archive.read_file(entry.name()).with_context(|| {
                 format!(
                     "failed to read 7z entry named {} from {}",
@@ -257,30 +548,18 @@ impl ExeInstaller {
                     downloaded_file.display()
                 )
             })?;
-            self.create_install_dir().with_context(|| {
-                format!(
-                    "failed to create installation directory for {}",
-                    install_path.display()
-                )
-            })?;
-
-            File::create(&install_path)
-                .with_context(|| format!("failed to create file at {}", install_path.display()))?
-                .write_all(&buffer)
-                .with_context(|| {
-                    format!(
-                        "failed to write extracted content to {}",
-                        install_path.display()
-                    )
-                })?;
 
-            return Ok(install_path);
+            return self.install_archive_member(
+                &m,
+                self.budget.reader(&m.path, buffer.as_slice()),
+                depth,
+            );
         }
 
         self.could_not_find_archive_matches_error()
     }
 
-    fn extract_executable_from_zip(&self, downloaded_file: &Path) -> Result<PathBuf> {
+    fn extract_executable_from_zip(&self, downloaded_file: &Path, depth: usize) -> Result<PathBuf> {
         debug!(
             "extracting executable from zip file at {}",
             downloaded_file.display()
@@ -292,143 +571,476 @@ impl ExeInstaller {
                 downloaded_file.display()
             )
         })?;
-        if let Some(idx) = self.best_match_from_archive(ZipEntriesIterator::new(&mut zip), "zip")? {
-            let mut zf = zip.by_index(idx).with_context(|| {
-                format!(
-                    "failed to get zip entry at index {idx} from {}",
-                    downloaded_file.display()
-                )
-            })?;
-            let zf_path = Path::new(zf.name());
-            let install_path = self.maybe_munged_install_path(zf_path)?;
+        if let Some(m) = self.best_match_from_archive(
+            ZipEntriesIterator::new(&mut zip, self.archive_password.as_deref()),
+            "zip",
+            depth,
+            None,
+        )? {
+            let mut zf = zip_entry(&mut zip, m.index, self.archive_password.as_deref())
+                .with_context(|| {
+                    format!(
+                        "failed to get zip entry at index {} from {}",
+                        m.index,
+                        downloaded_file.display()
+                    )
+                })?;
             debug!(
-                "extracting zip file entry named {} to {}",
+                "extracting zip file entry named {} for {}",
                 zf.name(),
-                install_path.display(),
+                m.path.display(),
             );
-            let mut buffer: Vec<u8> =
-                Vec::with_capacity(usize::try_from(zf.size()).with_context(|| {
+
+            return self.install_archive_member(&m, self.budget.reader(&m.path, &mut zf), depth);
+        }
+
+        self.could_not_find_archive_matches_error()
+    }
+
+    fn extract_executable_from_rpm(&self, downloaded_file: &Path, depth: usize) -> Result<PathBuf> {
+        debug!(
+            "extracting executable from rpm package at {}",
+            downloaded_file.display()
+        );
+
+        // Like a compressed tarball, the rpm payload cannot be seeked, so we have to re-open the
+        // file to get back to the entry we want.
+        let mut cpio = cpio_reader_for(downloaded_file)?;
+        if let Some(m) =
+            self.best_match_from_archive(CpioEntriesIterator::new(&mut cpio), "rpm", depth, None)?
+        {
+            let mut cpio = cpio_reader_for(downloaded_file)?;
+            let mut i = 0;
+            while cpio
+                .next_entry()
+                .with_context(|| {
                     format!(
-                        "failed to convert zip file size to usize for entry {} in {}",
-                        zf.name(),
+                        "failed to read rpm payload entry at index {i} from {}",
                         downloaded_file.display()
                     )
-                })?);
-            zf.read_to_end(&mut buffer).with_context(|| {
-                format!(
-                    "failed to read zip entry {} from {}",
-                    zf.name(),
-                    downloaded_file.display()
-                )
-            })?;
-            self.create_install_dir().with_context(|| {
+                })?
+                .is_some()
+            {
+                if i == m.index {
+                    debug!(
+                        "extracting rpm payload entry at index {i} for {}",
+                        m.path.display(),
+                    );
+                    return self.install_archive_member(
+                        &m,
+                        self.budget.reader(&m.path, cpio.content()),
+                        depth,
+                    );
+                }
+                i += 1;
+            }
+        }
+
+        self.could_not_find_archive_matches_error()
+    }
+
+    // Writes the content of the archive member that matched to the install path. If the member is
+    // itself an archive or compressed file, we write it to a temp dir instead and then look for the
+    // executable inside of it. The caller is responsible for counting the content against the
+    // extraction budget.
+    fn install_archive_member(
+        &self,
+        m: &ArchiveMatch,
+        mut reader: impl Read,
+        depth: usize,
+    ) -> Result<PathBuf> {
+        let member_path = m.path.as_path();
+        if let Some(ext) = nested_archive_extension(member_path, depth) {
+            let td =
+                tempdir().context("failed to create temporary directory for nested archive")?;
+            let nested_path = td.path().join(member_path.file_name().ok_or_else(|| {
+                anyhow!("archive member {} has no file name", member_path.display())
+            })?);
+            debug!(
+                "extracting nested {} file at {} to {}",
+                ext.extension(),
+                member_path.display(),
+                nested_path.display(),
+            );
+            let mut writer = File::create(&nested_path)
+                .with_context(|| format!("failed to create file at {}", nested_path.display()))?;
+            io::copy(&mut reader, &mut writer).with_context(|| {
                 format!(
-                    "failed to create installation directory for {}",
-                    install_path.display()
+                    "failed to write extracted content to {}",
+                    nested_path.display()
                 )
             })?;
 
-            File::create(&install_path)
-                .with_context(|| format!("failed to create file at {}", install_path.display()))?
-                .write_all(&buffer)
-                .with_context(|| {
-                    format!(
-                        "failed to write extracted content to {}",
-                        install_path.display()
-                    )
-                })?;
-
-            return Ok(install_path);
+            return Ok(self
+                .extract_executable(&nested_path, depth + 1)?
+                .unwrap_or_else(|| self.install_path.clone()));
         }
 
-        self.could_not_find_archive_matches_error()
+        let install_path = match member_path.file_name() {
+            Some(file_name) if m.is_inferred && !self.install_path_is_from_rename_exe_to => {
+                self.install_path.with_file_name(file_name)
+            }
+            _ => self.maybe_munged_install_path(member_path)?,
+        };
+        debug!(
+            "extracting archive member named {} to {}",
+            member_path.display(),
+            install_path.display(),
+        );
+        self.create_install_dir().with_context(|| {
+            format!(
+                "failed to create installation directory for {}",
+                install_path.display()
+            )
+        })?;
+        let mut writer = File::create(&install_path)
+            .with_context(|| format!("failed to create file at {}", install_path.display()))?;
+        io::copy(&mut reader, &mut writer).with_context(|| {
+            format!(
+                "failed to write extracted content to {}",
+                install_path.display()
+            )
+        })?;
+
+        Ok(install_path)
     }
 
     fn best_match_from_archive<'a>(
         &self,
         archive: impl Iterator<Item = Result<Box<dyn ArchiveEntry + 'a>>>,
         archive_type: &'static str,
-    ) -> Result<Option<usize>> {
-        let mut possible_matches: Vec<usize> = vec![];
+        depth: usize,
+        mut spool: Option<&mut MemberSpool>,
+    ) -> Result<Option<ArchiveMatch>> {
+        // We record every member in the archive so that we can resolve links to their targets.
+        let mut members: Vec<ArchiveMember> = vec![];
+        let mut exact_matches: Vec<usize> = vec![];
+        let mut partial_matches: Vec<usize> = vec![];
 
         for (i, entry) in archive.enumerate() {
-            let entry = entry
+            self.budget.add_entry()?;
+            let mut entry = entry
                 .with_context(|| format!("failed to read {archive_type} entry at index {i}"))?;
-            if !entry.is_file() {
-                continue;
-            }
-
             let path = entry.path().with_context(|| {
                 format!("failed to get path from {archive_type} entry at index {i}")
             })?;
+            let link = entry.link().with_context(|| {
+                format!("failed to get link target from {archive_type} entry at index {i}")
+            })?;
+            let is_executable = if entry.is_file() {
+                entry.is_executable().with_context(|| {
+                    format!("failed to check if {archive_type} entry at index {i} is executable")
+                })?
+            } else {
+                None
+            };
+            let header = entry.header().with_context(|| {
+                format!("failed to read the start of {archive_type} entry at index {i}")
+            })?;
+            let runs_on_platform = header
+                .as_deref()
+                .and_then(|header| binary::runs_on_platform(header, &self.platform));
+            members.push(ArchiveMember {
+                path: path.clone(),
+                is_file: entry.is_file(),
+                is_executable,
+                link: link.clone(),
+                runs_on_platform,
+            });
+
+            if let Some(spool) = spool.as_deref_mut() {
+                let has_matches = !exact_matches.is_empty() || !partial_matches.is_empty();
+                if self.is_spool_candidate(spool, &members[i], has_matches, depth) {
+                    if let Some(rest) = entry.rest_of_content() {
+                        debug!(
+                            "spooling {archive_type} entry with path `{}`",
+                            path.display()
+                        );
+                        let header = header.as_deref().unwrap_or_default();
+                        spool.add(i, self.budget.reader(&path, header.chain(rest)))?;
+                    }
+                }
+            }
+
+            if !entry.is_file() && link.is_none() {
+                continue;
+            }
 
             debug!("found {archive_type} entry with path `{}`", path.display());
             if let Some(file_name) = path.file_name() {
                 if let Some(file_name) = file_name.to_str() {
                     if self.archive_member_is_exact_match(file_name) {
-                        debug!("found {archive_type} file entry with exact match: `{file_name}`");
-                        return Ok(Some(i));
-                    } else if self.archive_member_is_partial_match(file_name) {
-                        // On Windows, we assume that the file is executable if it matches the
-                        // expected name, because Windows doesn't have executable bits. We treat
-                        // "None" as true because some archive types don't record whether a file is
-                        // executable.
-                        if self.is_windows
-                            || matches!(
-                                entry.is_executable().with_context(|| {
-                                    format!(
-                                        "failed to check if {archive_type} entry at index {i} is executable"
-                                    )
-                                })?,
-                                None | Some(true)
-                            )
-                        {
+                        if link.is_none() && exact_matches.is_empty() && is_in_bin_dir(&path) {
                             debug!(
-                                "found {archive_type} file entry with partial match: `{file_name}`"
+                                "found {archive_type} file entry with exact match: `{file_name}`"
                             );
-                            possible_matches.push(i);
+                            return Ok(Some(ArchiveMatch {
+                                index: i,
+                                path,
+                                is_inferred: false,
+                            }));
                         }
+                        debug!("found {archive_type} entry with exact match: `{file_name}`");
+                        exact_matches.push(i);
+                    } else if self.archive_member_is_partial_match(file_name) {
+                        debug!("found {archive_type} entry with partial match: `{file_name}`");
+                        partial_matches.push(i);
                     }
                 }
             }
         }
 
-        Ok(possible_matches.into_iter().next())
-    }
+        // Packages often contain other files with the same name as the executable, like shell
+        // completion files, so we prefer an exact match that is in a `bin` directory.
+        for i in exact_matches
+            .iter()
+            .filter(|&&i| is_in_bin_dir(&members[i].path))
+            .chain(
+                exact_matches
+                    .iter()
+                    .filter(|&&i| !is_in_bin_dir(&members[i].path)),
+            )
+            .copied()
+        {
+            if let Some(target) = resolve_archive_member(&members, i) {
+                return Ok(Some(ArchiveMatch {
+                    index: target,
+                    path: members[i].path.clone(),
+                    is_inferred: false,
+                }));
+            }
+        }
 
-    fn archive_member_is_exact_match(&self, file_name: &str) -> bool {
-        if self.extensions.is_empty() {
-            return file_name == self.exe_file_stem;
+        // Partial matches are ranked by how well they fit the platform, using the binary's header
+        // if it has one and the member's name otherwise. Within each rank, partial matches which we
+        // know are executable are preferred over those where the archive doesn't tell us.
+        let mut candidates: Vec<(PlatformFit, bool, ArchiveMatch)> = vec![];
+        for i in partial_matches {
+            let Some(target) = resolve_archive_member(&members, i) else {
+                continue;
+            };
+            let m = ArchiveMatch {
+                index: target,
+                path: members[i].path.clone(),
+                is_inferred: false,
+            };
+
+            // On Windows, we assume that the file is executable if it matches the expected name,
+            // because Windows doesn't have executable bits. We treat "None" as a possible match
+            // because some archive types don't record whether a file is executable. Nested
+            // archives aren't expected to be executable.
+            let is_known_executable = match members[target].is_executable {
+                _ if self.is_windows => true,
+                _ if nested_archive_extension(&m.path, depth).is_some() => false,
+                Some(true) => true,
+                None => false,
+                Some(false) => {
+                    debug!(
+                        "skipping non-executable {archive_type} entry with partial match: `{}`",
+                        m.path.display(),
+                    );
+                    continue;
+                }
+            };
+            let fit = self.platform_fit(&members[target], &m.path);
+            debug!(
+                "{archive_type} entry with partial match `{}` has a platform fit of {fit:?}",
+                m.path.display(),
+            );
+            candidates.push((fit, !is_known_executable, m));
         }
 
-        self.extensions
+        // This is a stable sort, so candidates that rank the same stay in archive order.
+        candidates.sort_by_key(|(fit, is_possible, _)| (*fit, *is_possible));
+        if let Some((_, _, m)) = candidates.into_iter().next() {
+            return Ok(Some(m));
+        }
+
+        // Some projects ship an archive that contains another archive with an unrelated name, like
+        // a zip file containing `dist.tar.gz`. If there's exactly one such member, we look in it.
+        let mut nested = members
             .iter()
-            .map(|&ext| format!("{}{}", self.exe_file_stem.to_lowercase(), ext))
-            .any(|n| n == file_name)
+            .enumerate()
+            .filter(|(_, m)| m.is_file && nested_archive_extension(&m.path, depth).is_some());
+        if let (Some((i, m)), None) = (nested.next(), nested.next()) {
+            debug!(
+                "found a single nested archive in the {archive_type} file: `{}`",
+                m.path.display()
+            );
+            return Ok(Some(ArchiveMatch {
+                index: i,
+                path: m.path.clone(),
+                is_inferred: false,
+            }));
+        }
+
+        if self.can_infer_exe {
+            return self.infer_executable_from_archive(&members, archive_type, depth);
+        }
+
+        Ok(None)
     }
 
-    fn archive_member_is_partial_match(&self, file_name: &str) -> bool {
-        if !file_name.starts_with(&self.exe_file_stem) {
+    // Returns true if the member's content might be installed, so we need to spool it. When a link
+    // might be installed, this records its target so that we spool the target when we get to it.
+    // The executables that we might infer are only spooled until we find a member whose name
+    // matches, since we don't infer anything once we have a match.
+    fn is_spool_candidate(
+        &self,
+        spool: &mut MemberSpool,
+        member: &ArchiveMember,
+        has_matches: bool,
+        depth: usize,
+    ) -> bool {
+        let name_matches = member
+            .path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| {
+                self.archive_member_is_exact_match(n) || self.archive_member_is_partial_match(n)
+            });
+        let is_link_target = normalize_member_path(&member.path)
+            .is_some_and(|path| spool.link_targets.contains(&path));
+
+        if let Some(link) = &member.link {
+            if name_matches || is_link_target {
+                if let Some(target) = link.target_path(&member.path) {
+                    spool.link_targets.insert(target);
+                }
+            }
             return false;
         }
-        if self.extensions.is_empty() {
-            return true;
-        }
-        self.extensions
+
+        member.is_file
+            && (name_matches
+                || is_link_target
+                || nested_archive_extension(&member.path, depth).is_some()
+                || (self.can_infer_exe
+                    && !has_matches
+                    && self.archive_member_is_executable(member, depth)))
+    }
+
+    // When nothing in the archive matches the executable's name, but the archive contains exactly
+    // one executable file, that's almost certainly the one we want. This happens when a project's
+    // executable is not named after the project, like `rg` from `BurntSushi/ripgrep`.
+    fn infer_executable_from_archive(
+        &self,
+        members: &[ArchiveMember],
+        archive_type: &'static str,
+        depth: usize,
+    ) -> Result<Option<ArchiveMatch>> {
+        let executables = members
             .iter()
-            .any(|&ext| file_name.to_lowercase().ends_with(ext))
+            .enumerate()
+            .filter(|(_, m)| self.archive_member_is_executable(m, depth))
+            .collect::<Vec<_>>();
+        match executables.as_slice() {
+            [] => Ok(None),
+            [(i, m)] => {
+                info!(
+                    "could not find any files matching [{}] in the {archive_type} file, so installing its only executable, `{}`",
+                    self.expect_names(),
+                    m.path.display(),
+                );
+                Ok(Some(ArchiveMatch {
+                    index: *i,
+                    path: m.path.clone(),
+                    is_inferred: true,
+                }))
+            }
+            _ => Err(anyhow!(
+                "could not find any files matching [{}] in the {archive_type} file, and it contains more than one executable, so ubi cannot tell which one to install: {}. Set the executable name to one of these to install it.",
+                self.expect_names(),
+                executables
+                    .iter()
+                    .map(|(_, m)| m.path.display().to_string())
+                    .join(", "),
+            )),
+        }
     }
 
-    fn could_not_find_archive_matches_error(&self) -> Result<PathBuf> {
-        let expect_names = if self.extensions.is_empty() {
-            format!("{}*", self.exe_file_stem)
+    // A file counts as executable if its mode says so or if it's a binary executable. On Windows,
+    // the file's extension has to be one that Windows can run.
+    fn archive_member_is_executable(&self, member: &ArchiveMember, depth: usize) -> bool {
+        if !member.is_file
+            || member.link.is_some()
+            || nested_archive_extension(&member.path, depth).is_some()
+        {
+            return false;
+        }
+        if self.is_windows {
+            let file_name = member
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            return self.extensions.iter().any(|&ext| file_name.ends_with(ext));
+        }
+        member.is_executable == Some(true) || member.runs_on_platform.is_some()
+    }
+
+    fn platform_fit(&self, member: &ArchiveMember, path: &Path) -> PlatformFit {
+        match member.runs_on_platform {
+            Some(true) => return PlatformFit::Matches,
+            Some(false) => return PlatformFit::Mismatch,
+            None => (),
+        }
+
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return PlatformFit::Unknown;
+        };
+        let os = PlatformFit::from_name(name, os_re(self.platform.target_os), &ALL_OSES_RE);
+        let arch = PlatformFit::from_name(
+            name,
+            arch_re(self.platform.target_arch, self.platform.target_endian),
+            &ALL_ARCHES_RE,
+        );
+        if os == PlatformFit::Mismatch || arch == PlatformFit::Mismatch {
+            PlatformFit::Mismatch
+        } else if os == PlatformFit::Matches || arch == PlatformFit::Matches {
+            PlatformFit::Matches
+        } else {
+            PlatformFit::Unknown
+        }
+    }
+
+    fn archive_member_is_exact_match(&self, file_name: &str) -> bool {
+        if self.extensions.is_empty() {
+            return file_name == self.exe_file_stem;
+        }
+
+        self.extensions
+            .iter()
+            .map(|&ext| format!("{}{}", self.exe_file_stem.to_lowercase(), ext))
+            .any(|n| n == file_name)
+    }
+
+    fn archive_member_is_partial_match(&self, file_name: &str) -> bool {
+        if !file_name.starts_with(&self.exe_file_stem) {
+            return false;
+        }
+        if self.extensions.is_empty() {
+            return true;
+        }
+        self.extensions
+            .iter()
+            .any(|&ext| file_name.to_lowercase().ends_with(ext))
+    }
+
+    fn expect_names(&self) -> String {
+        if self.extensions.is_empty() {
+            format!("{}*", self.exe_file_stem)
         } else {
             self.extensions
                 .iter()
                 .map(|ext| format!("{}*{}", self.exe_file_stem, ext))
                 .collect::<Vec<_>>()
                 .join(" ")
-        };
+        }
+    }
+
+    fn could_not_find_archive_matches_error(&self) -> Result<PathBuf> {
+        let expect_names = self.expect_names();
 
         debug!("could not find any entries matching [{expect_names}]");
         Err(anyhow!(
@@ -439,28 +1051,52 @@ impl ExeInstaller {
     fn unbzip(&self, downloaded_file: &Path) -> Result<()> {
         debug!("uncompressing executable from bzip file");
         let reader = BzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
+        self.write_to_install_path(downloaded_file, reader)
     }
 
     fn ungzip(&self, downloaded_file: &Path) -> Result<()> {
         debug!("uncompressing executable from gzip file");
         let reader = GzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unbrotli(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from brotli file");
+        let reader = BrotliDecoder::new(open_file(downloaded_file)?, BROTLI_BUFFER_SIZE);
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unlzip(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from lzip file");
+        let reader = lzip::decoder(open_file(downloaded_file)?)?;
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unlz4(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from lz4 file");
+        let reader = Lz4Decoder::new(open_file(downloaded_file)?);
+        self.write_to_install_path(downloaded_file, reader)
+    }
+
+    fn unlzma(&self, downloaded_file: &Path) -> Result<()> {
+        debug!("uncompressing executable from lzma file");
+        let reader = lzma_decoder(open_file(downloaded_file)?)?;
+        self.write_to_install_path(downloaded_file, reader)
     }
 
     fn unxz(&self, downloaded_file: &Path) -> Result<()> {
         debug!("uncompressing executable from xz file");
         let reader = XzDecoder::new(open_file(downloaded_file)?);
-        self.write_to_install_path(reader)
+        self.write_to_install_path(downloaded_file, reader)
     }
 
     fn unzstd(&self, downloaded_file: &Path) -> Result<()> {
         debug!("uncompressing executable from zstd file");
         let reader = ZstdDecoder::new(open_file(downloaded_file)?)?;
-        self.write_to_install_path(reader)
+        self.write_to_install_path(downloaded_file, reader)
     }
 
-    fn write_to_install_path(&self, mut reader: impl Read) -> Result<()> {
+    fn write_to_install_path(&self, downloaded_file: &Path, reader: impl Read) -> Result<()> {
         self.create_install_dir().with_context(|| {
             format!(
                 "failed to create installation directory for {}",
@@ -469,9 +1105,11 @@ impl ExeInstaller {
         })?;
         let mut writer = File::create(&self.install_path)
             .with_context(|| format!("Cannot write to {}", self.install_path.display()))?;
-        std::io::copy(&mut reader, &mut writer).with_context(|| {
-            format!("failed to copy content to {}", self.install_path.display())
-        })?;
+        std::io::copy(
+            &mut self.budget.reader(downloaded_file, reader),
+            &mut writer,
+        )
+        .with_context(|| format!("failed to copy content to {}", self.install_path.display()))?;
         Ok(())
     }
 
@@ -529,6 +1167,37 @@ impl ExeInstaller {
         Ok(install_path)
     }
 
+    // Returns a copy of this installer that installs into `dir` instead of the real install
+    // directory.
+    fn staged_in(&self, dir: &Path) -> Result<Self> {
+        let file_name = self.install_path.file_name().ok_or_else(|| {
+            anyhow!(
+                "install path {} has no file name",
+                self.install_path.display()
+            )
+        })?;
+        Ok(ExeInstaller {
+            install_path: dir.join(file_name),
+            // Each install starts with nothing extracted.
+            budget: ExtractionBudget::new(self.budget.limits),
+            ..self.clone()
+        })
+    }
+
+    // If the executable can't run on the platform we're installing for, that's an error, unless the
+    // user asked us to allow this.
+    fn check_platform(&self, exe: &Path) -> Result<()> {
+        let Some(mismatch) = binary::platform_mismatch(exe, &self.platform)? else {
+            return Ok(());
+        };
+
+        if self.allow_platform_mismatch {
+            warn!("{mismatch}");
+            return Ok(());
+        }
+        Err(anyhow!(mismatch))
+    }
+
     #[cfg(target_family = "windows")]
     fn chmod_executable(_exe: &Path) -> Result<()> {
         Ok(())
@@ -544,26 +1213,294 @@ impl ExeInstaller {
 }
 
 impl Installer for ExeInstaller {
-    fn install(&self, download: &Download) -> Result<()> {
-        let exe = self.extract_executable(&download.archive_path)?;
-        let real_exe = exe.as_deref().unwrap_or(&self.install_path);
-        Self::chmod_executable(real_exe).with_context(|| {
+    fn install(&self, download: &Download) -> Result<Installed> {
+        self.install_staged(|staged| staged.extract_executable(&download.archive_path, 0))
+    }
+
+    fn install_from_stream(
+        &self,
+        name: &str,
+        ext: &Extension,
+        reader: Box<dyn Read>,
+    ) -> Result<Installed> {
+        self.install_staged(|staged| {
+            debug!("extracting executable from tarball {name} as it is downloaded");
+            staged
+                .extract_executable_from_tar_archive(tar_reader_from(reader, ext)?, None, ext, 0)
+                .map(Some)
+        })
+    }
+}
+
+impl ExeInstaller {
+    // We extract the executable into a staging directory and check it there, so that a failed
+    // install doesn't touch an executable that's already installed. The `extract` function is
+    // called with an installer for the staging directory, and returns the path of the staged
+    // executable, if it's not at that installer's install path.
+    fn install_staged(
+        &self,
+        extract: impl FnOnce(&ExeInstaller) -> Result<Option<PathBuf>>,
+    ) -> Result<Installed> {
+        let staging_dir =
+            tempdir().context("failed to create temporary directory for staging the executable")?;
+        let staged = self.staged_in(staging_dir.path())?;
+        let staged_exe = extract(&staged)?.unwrap_or_else(|| staged.install_path.clone());
+        self.check_platform(&staged_exe)?;
+        Self::chmod_executable(&staged_exe).with_context(|| {
             format!(
                 "failed to set executable permissions on {}",
+                staged_exe.display()
+            )
+        })?;
+
+        let file_name = staged_exe.file_name().ok_or_else(|| {
+            anyhow!(
+                "staged executable {} has no file name",
+                staged_exe.display()
+            )
+        })?;
+        let real_exe = self.install_path.with_file_name(file_name);
+        self.create_install_dir().with_context(|| {
+            format!(
+                "failed to create installation directory for {}",
                 real_exe.display()
             )
         })?;
-        info!("Installed executable into {}", real_exe.display());
 
+        let launcher = match &self.launcher_config {
+            Some(config) => self
+                .launcher_for(config, &staged_exe)?
+                .map(|launcher| (launcher, &config.lib_dir)),
+            None => None,
+        };
+        let installed = if let Some((launcher, lib_dir)) = launcher {
+            self.install_with_launcher(
+                &launcher,
+                lib_dir,
+                staging_dir.path(),
+                &staged_exe,
+                &staged.budget,
+            )?
+        } else {
+            let mut installed = InstalledExe::new(real_exe.clone());
+            installed.add(&staged_exe, real_exe)?;
+            installed
+        };
+        info!("Installed executable into {}", installed.path.display());
+
+        Ok(Installed::Exe(installed))
+    }
+}
+
+impl ExeInstaller {
+    fn launcher_for(&self, config: &LauncherConfig, staged_exe: &Path) -> Result<Option<Launcher>> {
+        if config.for_appimages && appimage::is_appimage(staged_exe)? {
+            return Ok(Some(Launcher::AppRun));
+        }
+        if config.for_interpreted_files {
+            return Launcher::for_artifact(staged_exe, self.is_windows);
+        }
+        Ok(None)
+    }
+
+    // Installs the artifact at `staged_artifact` into `lib_dir`, and writes a launcher for it into
+    // the install directory. For an AppImage, we install the directory extracted from it instead of
+    // the artifact itself. What we extract from an AppImage counts against `budget`.
+    fn install_with_launcher(
+        &self,
+        launcher: &Launcher,
+        lib_dir: &Path,
+        staging_dir: &Path,
+        staged_artifact: &Path,
+        budget: &ExtractionBudget,
+    ) -> Result<InstalledExe> {
+        let (Some(artifact_name), Some(artifact_stem)) =
+            (staged_artifact.file_name(), staged_artifact.file_stem())
+        else {
+            return Err(anyhow!(
+                "staged executable {} has no file name",
+                staged_artifact.display()
+            ));
+        };
+        debug!("creating directory at {}", lib_dir.display());
+        create_dir_all(lib_dir)
+            .with_context(|| format!("could not create a directory at {}", lib_dir.display()))?;
+        let target_name = match launcher {
+            Launcher::AppRun => artifact_stem,
+            Launcher::Interpreter { .. } => artifact_name,
+        };
+        // The launcher might be run from anywhere, so it needs the target's absolute path.
+        let target = std::path::absolute(lib_dir.join(target_name)).with_context(|| {
+            format!(
+                "failed to make the path to {} in {} absolute",
+                target_name.to_string_lossy(),
+                lib_dir.display()
+            )
+        })?;
+
+        let launcher_name = launcher.file_name(&artifact_stem.to_string_lossy());
+        let staged_launcher = staging_dir.join(&launcher_name);
+        debug!(
+            "writing launcher for {} to {}",
+            target.display(),
+            staged_launcher.display()
+        );
+        fs::write(&staged_launcher, launcher.script(&target)).with_context(|| {
+            format!("failed to write launcher to {}", staged_launcher.display())
+        })?;
+        Self::chmod_executable(&staged_launcher).with_context(|| {
+            format!(
+                "failed to set executable permissions on {}",
+                staged_launcher.display()
+            )
+        })?;
+
+        let mut installed = InstalledExe::new(self.install_path.with_file_name(&launcher_name));
+        let res = match launcher {
+            Launcher::AppRun => installed.add_with(target, |dir| {
+                appimage::extract(staged_artifact, dir, budget)
+            }),
+            Launcher::Interpreter { .. } => installed.add(staged_artifact, target),
+        }
+        .and_then(|()| installed.add(&staged_launcher, installed.path.clone()));
+        if let Err(e) = res {
+            installed.roll_back()?;
+            return Err(e);
+        }
+        info!(
+            "Installed {} into {}",
+            target_name.to_string_lossy(),
+            lib_dir.display()
+        );
+
+        Ok(installed)
+    }
+}
+
+impl InstalledExe {
+    fn new(path: PathBuf) -> Self {
+        InstalledExe {
+            path,
+            files: vec![],
+        }
+    }
+
+    // Moves any existing file at `path` out of the way, then copies `new_file` to `path`. If this
+    // fails, the file that was at `path` is restored.
+    fn add(&mut self, new_file: &Path, path: PathBuf) -> Result<()> {
+        self.add_with(path, |path| {
+            fs::copy(new_file, path).map(|_| ()).with_context(|| {
+                format!(
+                    "error copying file from {} to {}",
+                    new_file.display(),
+                    path.display()
+                )
+            })
+        })
+    }
+
+    // Moves any existing file or directory at `path` out of the way, then calls `create` to create
+    // the new one. The backup is in the same directory so that moving it is just a rename, which
+    // works even if the old executable is running. If this fails, whatever was at `path` is
+    // restored.
+    fn add_with(&mut self, path: PathBuf, create: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+        let backup = if fs::symlink_metadata(&path).is_ok() {
+            let mut backup_name = OsString::from(".");
+            backup_name.push(path.file_name().unwrap_or_default());
+            backup_name.push(".ubi-backup");
+            let backup = path.with_file_name(backup_name);
+            // This can be left behind if a previous install was interrupted.
+            remove_path(&backup)?;
+            debug!(
+                "moving existing file at {} to {}",
+                path.display(),
+                backup.display()
+            );
+            fs::rename(&path, &backup).with_context(|| {
+                format!(
+                    "failed to move existing file at {} to {}",
+                    path.display(),
+                    backup.display()
+                )
+            })?;
+            Some(backup)
+        } else {
+            None
+        };
+
+        let file = InstalledFile { path, backup };
+        if let Err(e) = create(&file.path) {
+            file.roll_back()?;
+            return Err(e);
+        }
+        self.files.push(file);
+
+        Ok(())
+    }
+
+    // Removes the backups of the replaced files, if there are any.
+    pub(crate) fn commit(self) -> Result<()> {
+        for file in &self.files {
+            if let Some(backup) = &file.backup {
+                debug!("removing backup at {}", backup.display());
+                remove_path(backup)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Removes the new files and puts the replaced files back, if there were any.
+    pub(crate) fn roll_back(self) -> Result<()> {
+        for file in self.files.into_iter().rev() {
+            file.roll_back()?;
+        }
+        Ok(())
+    }
+}
+
+impl InstalledFile {
+    fn roll_back(self) -> Result<()> {
+        remove_path(&self.path)?;
+        if let Some(backup) = &self.backup {
+            debug!(
+                "restoring previous file at {} from {}",
+                self.path.display(),
+                backup.display()
+            );
+            fs::rename(backup, &self.path).with_context(|| {
+                format!(
+                    "failed to restore previous file at {} from {}",
+                    self.path.display(),
+                    backup.display()
+                )
+            })?;
+        }
         Ok(())
     }
 }
 
+// Removes the file or directory at `path`, if there is one.
+fn remove_path(path: &Path) -> Result<()> {
+    let res = match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(_) => return Ok(()),
+    };
+    res.with_context(|| format!("failed to remove {}", path.display()))
+}
+
 impl ArchiveInstaller {
-    pub(crate) fn new(project_name: String, install_path: PathBuf) -> Self {
+    pub(crate) fn new(
+        project_name: String,
+        install_path: PathBuf,
+        archive_password: Option<String>,
+        extraction_limits: ExtractionLimits,
+    ) -> Self {
         ArchiveInstaller {
             project_name,
             install_root: install_path,
+            archive_password,
+            extraction_limits,
         }
     }
 
@@ -574,31 +1511,55 @@ impl ArchiveInstaller {
                 downloaded_file.display()
             )
         })?;
+        // If a limit is exceeded, we return before copying anything out of the temp dir, so nothing
+        // is left behind.
+        let budget = ExtractionBudget::new(self.extraction_limits);
 
-        match Extension::from_path(downloaded_file)? {
+        match magic::detect_extension(downloaded_file)? {
             Some(
-                Extension::Tar
+                ext @ (Extension::Tar
                 | Extension::TarBz
                 | Extension::TarBz2
                 | Extension::TarGz
+                | Extension::TarLz
+                | Extension::TarLz4
+                | Extension::TarLzma
                 | Extension::TarXz
                 | Extension::TarZst
                 | Extension::Tbz
                 | Extension::Tgz
+                | Extension::Tlz
                 | Extension::Txz
-                | Extension::Tzst,
-            ) => Self::extract_entire_tarball(downloaded_file, td.path()).with_context(|| {
-                format!("failed to extract tarball at {}", downloaded_file.display())
-            })?,
+                | Extension::Tzst
+                | Extension::Apk
+                | Extension::Deb
+                | Extension::PkgTarZst),
+            ) => tar_reader_for(downloaded_file, &ext)
+                .and_then(|arch| Self::extract_entire_tarball(arch, &ext, td.path(), &budget))
+                .with_context(|| {
+                    format!("failed to extract tarball at {}", downloaded_file.display())
+                })?,
+            Some(Extension::Rpm) => {
+                Self::extract_entire_rpm(downloaded_file, td.path(), &budget).with_context(
+                    || {
+                        format!(
+                            "failed to extract rpm package at {}",
+                            downloaded_file.display()
+                        )
+                    },
+                )?;
+            }
             Some(Extension::SevenZip) => {
-                Self::extract_entire_7z(downloaded_file, td.path()).with_context(|| {
-                    format!(
-                        "failed to extract 7z archive at {}",
-                        downloaded_file.display()
-                    )
-                })?;
+                self.extract_entire_7z(downloaded_file, td.path(), &budget)
+                    .with_context(|| {
+                        format!(
+                            "failed to extract 7z archive at {}",
+                            downloaded_file.display()
+                        )
+                    })?;
             }
-            Some(Extension::Zip) => Self::extract_entire_zip(downloaded_file, td.path())
+            Some(Extension::Zip) => self
+                .extract_entire_zip(downloaded_file, td.path(), &budget)
                 .with_context(|| {
                     format!(
                         "failed to extract zip archive at {}",
@@ -621,80 +1582,398 @@ impl ArchiveInstaller {
         Ok(())
     }
 
-    fn extract_entire_tarball(downloaded_file: &Path, into: &Path) -> Result<()> {
-        debug!(
-            "extracting entire tarball at {} to {}",
-            downloaded_file.display(),
-            into.display()
-        );
-
-        let mut arch = tar_reader_for(downloaded_file)?;
-        arch.unpack(into)
-            .with_context(|| format!("failed to unpack tarball to {}", into.display()))?;
+    // The caller adds the tarball's location to any error this returns.
+    fn extract_entire_tarball(
+        mut arch: TarArchive<Box<dyn Read>>,
+        ext: &Extension,
+        into: &Path,
+        budget: &ExtractionBudget,
+    ) -> Result<()> {
+        debug!("extracting entire tarball to {}", into.display());
+
+        // Like `tar::Archive::unpack`, we unpack directories last, so that a directory's permissions
+        // can't keep us from unpacking the files inside of it.
+        let mut directories = vec![];
+        for entry in arch
+            .entries()
+            .context("failed to get entries from tarball")?
+        {
+            budget.add_entry()?;
+            let mut entry = entry.context("failed to read tarball entry")?;
+            let path = entry
+                .path()
+                .context("failed to get path from tar entry")?
+                .to_path_buf();
+            if ext.is_linux_package() && is_package_metadata(&path) {
+                debug!("skipping package metadata file {}", path.display());
+                continue;
+            }
+            let entry_type = entry.header().entry_type();
+            let link_name = entry
+                .link_name()
+                .context("failed to get link name from tar entry")?
+                .map(|l| l.to_path_buf());
+            let mode = entry
+                .header()
+                .mode()
+                .context("failed to get mode from tar entry header")?;
+            check_member(
+                &path,
+                tar_member_kind(entry_type, link_name.as_deref()),
+                Some(mode),
+            )?;
+            if entry_type.is_dir() {
+                directories.push(entry);
+                continue;
+            }
+            // The tar reader never returns more content for an entry than its header says it has.
+            budget.add_file(&path, entry.size())?;
+            entry.unpack_in(into).with_context(|| {
+                format!("failed to unpack {} to {}", path.display(), into.display())
+            })?;
+        }
+        for mut dir in directories {
+            dir.unpack_in(into).with_context(|| {
+                format!(
+                    "failed to unpack directory {} to {}",
+                    dir.path().unwrap_or_default().display(),
+                    into.display()
+                )
+            })?;
+        }
 
         Ok(())
     }
 
-    fn extract_entire_7z(downloaded_file: &Path, into: &Path) -> Result<()> {
+    fn extract_entire_7z(
+        &self,
+        downloaded_file: &Path,
+        into: &Path,
+        budget: &ExtractionBudget,
+    ) -> Result<()> {
         debug!(
             "extracting entire 7z file at {} to {}",
             downloaded_file.display(),
             into.display()
         );
 
-        sevenz_rust2::decompress_file(downloaded_file, into)
-            .with_context(|| format!("failed to decompress 7z file to {}", into.display()))?;
+        // Errors returned from the extract function are wrapped in a `sevenz_rust2::Error`, so we
+        // stash a safety or limit error here to return it as-is.
+        let mut rejected: Option<anyhow::Error> = None;
+        let res = sevenz_rust2::decompress_with_extract_fn_and_password(
+            open_file(downloaded_file)?,
+            into,
+            sevenz_password(self.archive_password.as_deref()),
+            |entry, reader, dest| {
+                if let Err(e) = Self::check_7z_entry(entry, budget) {
+                    rejected = Some(e);
+                    return Ok(false);
+                }
+                sevenz_rust2::default_entry_extract_fn(entry, reader, dest)
+            },
+        );
+        if let Some(e) = rejected {
+            return Err(e);
+        }
+        res.with_context(|| format!("failed to decompress 7z file to {}", into.display()))?;
+        Self::apply_7z_modes(downloaded_file, into, self.archive_password.as_deref())?;
         Ok(())
     }
 
-    fn extract_entire_zip(downloaded_file: &Path, into: &Path) -> Result<()> {
-        debug!(
-            "extracting entire zip file at {} to {}",
-            downloaded_file.display(),
-            into.display(),
-        );
+    fn check_7z_entry(entry: &sevenz_rust2::ArchiveEntry, budget: &ExtractionBudget) -> Result<()> {
+        let path = Path::new(entry.name());
+        let mode = crate::archive::sevenz_unix_mode(entry);
+        // `sevenz_rust2` writes symlinks out as regular files containing the link's target, so we
+        // only need to look for special files here.
+        let kind = if entry.is_directory() {
+            MemberKind::Dir
+        } else {
+            mode.and_then(MemberKind::special_from_unix_mode)
+                .unwrap_or(MemberKind::File)
+        };
+        check_member(path, kind, mode)?;
 
-        let mut zip = ZipArchive::new(open_file(downloaded_file)?).with_context(|| {
+        budget.add_entry()?;
+        if !entry.is_directory() {
+            // The 7z reader never returns more content for an entry than its size.
+            budget.add_file(path, entry.size())?;
+        }
+        Ok(())
+    }
+
+    // Unlike the tar and zip crates, `sevenz_rust2` does not set the mode of extracted files, even
+    // when the archive records them.
+    #[cfg(target_family = "windows")]
+    fn apply_7z_modes(
+        _downloaded_file: &Path,
+        _into: &Path,
+        _password: Option<&str>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    fn apply_7z_modes(downloaded_file: &Path, into: &Path, password: Option<&str>) -> Result<()> {
+        let archive = sevenz_rust2::ArchiveReader::new(
+            open_file(downloaded_file)?,
+            sevenz_password(password),
+        )
+        .with_context(|| {
             format!(
-                "failed to create zip archive reader for {}",
+                "failed to create 7z archive reader for {}",
                 downloaded_file.display()
             )
         })?;
-        zip.extract(into).with_context(|| {
-            format!(
-                "failed to extract zip archive from {} to {}",
-                downloaded_file.display(),
-                into.display()
-            )
-        })?;
+
+        for entry in &archive.archive().files {
+            if entry.is_directory() {
+                continue;
+            }
+            let Some(mode) = crate::archive::sevenz_unix_mode(entry) else {
+                continue;
+            };
+            // The mode of a symlink is meaningless, and setting it would change the mode of the
+            // file the link points to.
+            if mode & S_IFMT == S_IFLNK {
+                continue;
+            }
+            // These checks already passed during extraction, but we use the path they return so
+            // that we never touch anything outside of `into`.
+            let member_path = check_member(Path::new(entry.name()), MemberKind::File, Some(mode))?;
+            let path = into.join(&member_path);
+            if path
+                .symlink_metadata()
+                .map_or(true, |meta| meta.file_type().is_symlink())
+            {
+                debug!(
+                    "not setting the mode of {} because it is missing or a symlink",
+                    path.display()
+                );
+                continue;
+            }
+            debug!("setting mode of {} to {:o}", path.display(), mode & 0o777);
+            set_permissions(&path, Permissions::from_mode(mode & 0o777))
+                .with_context(|| format!("failed to set permissions on {}", path.display()))?;
+        }
+
         Ok(())
     }
 
-    fn copy_extracted_contents(&self, td: &TempDir) -> Result<()> {
-        let copy_from = match self.extracted_contents_top_level_dir(td.path())? {
-            Some(dir) => dir,
-            None => td.path().to_path_buf(),
-        };
-
+    fn extract_entire_rpm(
+        downloaded_file: &Path,
+        into: &Path,
+        budget: &ExtractionBudget,
+    ) -> Result<()> {
         debug!(
-            "copying extracted contents from {} to {}",
-            copy_from.display(),
-            self.install_root.display(),
+            "extracting entire rpm package at {} to {}",
+            downloaded_file.display(),
+            into.display(),
         );
 
-        for entry in WalkDir::new(&copy_from).into_iter().filter_map(Result::ok) {
-            let full_path = entry.path();
-            let target_path =
-                self.install_root
-                    .join(full_path.strip_prefix(&copy_from).with_context(|| {
-                        format!(
-                            "failed to strip prefix {} from path {}",
-                            copy_from.display(),
-                            full_path.display()
-                        )
-                    })?);
-
-            if full_path.is_dir() {
+        let mut cpio = cpio_reader_for(downloaded_file)?;
+        while let Some(entry) = cpio.next_entry()? {
+            budget.add_entry()?;
+            let kind = if entry.is_dir() {
+                MemberKind::Dir
+            } else if let Some(target) = &entry.link_target {
+                MemberKind::Symlink(target)
+            } else {
+                MemberKind::special_from_unix_mode(entry.mode).unwrap_or(MemberKind::File)
+            };
+            let member_path = check_member(&entry.path, kind, Some(entry.mode))?;
+            if member_path.as_os_str().is_empty() {
+                continue;
+            }
+            let path = check_extraction_path(into, &member_path)?;
+
+            if entry.is_dir() {
+                create_dir_all(&path)
+                    .with_context(|| format!("failed to create directory {}", path.display()))?;
+                continue;
+            }
+
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent)
+                    .with_context(|| format!("failed to create directory {}", parent.display()))?;
+            }
+
+            if let Some(target) = &entry.link_target {
+                Self::create_extracted_symlink(target, &path)?;
+            } else if entry.is_file() {
+                let mut writer = File::create(&path)
+                    .with_context(|| format!("failed to create file at {}", path.display()))?;
+                io::copy(
+                    &mut budget.reader(&member_path, cpio.content()),
+                    &mut writer,
+                )
+                .with_context(|| {
+                    format!("failed to write extracted content to {}", path.display())
+                })?;
+                Self::set_extracted_mode(&path, entry.mode)?;
+            } else {
+                debug!(
+                    "skipping rpm payload entry {} because it is not a file, directory, or symlink",
+                    entry.path.display(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "windows")]
+    fn create_extracted_symlink(_target: &Path, path: &Path) -> Result<()> {
+        debug!("skipping symlink {} on Windows", path.display());
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    fn create_extracted_symlink(target: &Path, path: &Path) -> Result<()> {
+        std::os::unix::fs::symlink(target, path).with_context(|| {
+            format!(
+                "failed to create symlink at {} pointing to {}",
+                path.display(),
+                target.display()
+            )
+        })
+    }
+
+    #[cfg(target_family = "windows")]
+    fn set_extracted_mode(_path: &Path, _mode: u32) -> Result<()> {
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    fn set_extracted_mode(path: &Path, mode: u32) -> Result<()> {
+        set_permissions(path, Permissions::from_mode(mode & 0o777))
+            .with_context(|| format!("failed to set permissions on {}", path.display()))
+    }
+
+    fn extract_entire_zip(
+        &self,
+        downloaded_file: &Path,
+        into: &Path,
+        budget: &ExtractionBudget,
+    ) -> Result<()> {
+        debug!(
+            "extracting entire zip file at {} to {}",
+            downloaded_file.display(),
+            into.display(),
+        );
+
+        let mut zip = ZipArchive::new(open_file(downloaded_file)?).with_context(|| {
+            format!(
+                "failed to create zip archive reader for {}",
+                downloaded_file.display()
+            )
+        })?;
+        // We extract one entry at a time instead of using the `zip` crate's `extract` method, which
+        // can't decrypt entries or limit how much it extracts.
+        Self::extract_zip_entries(&mut zip, self.archive_password.as_deref(), into, budget)
+            .with_context(|| {
+                format!(
+                    "failed to extract zip archive from {} to {}",
+                    downloaded_file.display(),
+                    into.display()
+                )
+            })?;
+        Ok(())
+    }
+
+    fn extract_zip_entries(
+        zip: &mut ZipArchive<File>,
+        password: Option<&str>,
+        into: &Path,
+        budget: &ExtractionBudget,
+    ) -> Result<()> {
+        for i in 0..zip.len() {
+            budget.add_entry()?;
+            let entry = zip_entry(zip, i, password)
+                .with_context(|| format!("failed to get zip entry at index {i}"))?;
+            let name = PathBuf::from(entry.name());
+            let is_dir = entry.is_dir();
+            let is_symlink = entry.is_symlink();
+            let unix_mode = entry.unix_mode();
+            // We can't check a symlink's target until we read it below.
+            let kind = if is_dir {
+                MemberKind::Dir
+            } else {
+                unix_mode
+                    .and_then(MemberKind::special_from_unix_mode)
+                    .unwrap_or(MemberKind::File)
+            };
+            let member_path = check_member(&name, kind, unix_mode)?;
+            if member_path.as_os_str().is_empty() {
+                continue;
+            }
+            let path = check_extraction_path(into, &member_path)?;
+            // The sizes in a zip file's headers can't be trusted, so we count what we read.
+            let mut reader = budget.reader(&member_path, entry);
+
+            if is_dir {
+                create_dir_all(&path)
+                    .with_context(|| format!("failed to create directory {}", path.display()))?;
+                continue;
+            }
+
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent)
+                    .with_context(|| format!("failed to create directory {}", parent.display()))?;
+            }
+
+            // A symlink in a zip file is stored as a file containing the link's target.
+            if is_symlink {
+                let mut target = String::new();
+                reader.read_to_string(&mut target).with_context(|| {
+                    format!(
+                        "failed to read symlink target for zip entry {}",
+                        member_path.display(),
+                    )
+                })?;
+                let target = PathBuf::from(target);
+                check_member(&name, MemberKind::Symlink(&target), unix_mode)?;
+                Self::create_extracted_symlink(&target, &path)?;
+                continue;
+            }
+
+            let mut writer = File::create(&path)
+                .with_context(|| format!("failed to create file at {}", path.display()))?;
+            io::copy(&mut reader, &mut writer).with_context(|| {
+                format!("failed to write extracted content to {}", path.display())
+            })?;
+            if let Some(mode) = unix_mode {
+                Self::set_extracted_mode(&path, mode)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn copy_extracted_contents(&self, td: &TempDir) -> Result<()> {
+        let copy_from = match self.extracted_contents_top_level_dir(td.path())? {
+            Some(dir) => dir,
+            None => td.path().to_path_buf(),
+        };
+
+        debug!(
+            "copying extracted contents from {} to {}",
+            copy_from.display(),
+            self.install_root.display(),
+        );
+
+        for entry in WalkDir::new(&copy_from).into_iter().filter_map(Result::ok) {
+            let full_path = entry.path();
+            let target_path =
+                self.install_root
+                    .join(full_path.strip_prefix(&copy_from).with_context(|| {
+                        format!(
+                            "failed to strip prefix {} from path {}",
+                            copy_from.display(),
+                            full_path.display()
+                        )
+                    })?);
+
+            if full_path.is_dir() {
                 debug!("creating directory {}", target_path.display(),);
                 create_dir_all(&target_path).with_context(|| {
                     format!("failed to create directory at {}", target_path.display())
@@ -787,42 +2066,151 @@ impl ArchiveInstaller {
 }
 
 impl Installer for ArchiveInstaller {
-    fn install(&self, download: &Download) -> Result<()> {
+    fn install(&self, download: &Download) -> Result<Installed> {
         self.extract_entire_archive(&download.archive_path)?;
         info!(
             "Installed contents of archive file into {}",
             self.install_root.display()
         );
 
-        Ok(())
+        Ok(Installed::Archive(self.install_root.clone()))
+    }
+
+    fn install_from_stream(
+        &self,
+        name: &str,
+        ext: &Extension,
+        reader: Box<dyn Read>,
+    ) -> Result<Installed> {
+        let td = tempdir().with_context(|| {
+            format!("failed to create temporary directory for extracting {name}")
+        })?;
+        let budget = ExtractionBudget::new(self.extraction_limits);
+        debug!("extracting entire tarball {name} as it is downloaded");
+        // The tar reader stops at the end of the archive, which may be before the end of the
+        // download. We keep a handle on the download so we can read the rest of it before we copy
+        // anything into place.
+        let mut reader = SharedReader(Rc::new(RefCell::new(reader)));
+        tar_reader_from(Box::new(reader.clone()), ext)
+            .and_then(|arch| Self::extract_entire_tarball(arch, ext, td.path(), &budget))
+            .with_context(|| format!("failed to extract tarball {name} as it was downloaded"))?;
+        io::copy(&mut reader, &mut io::sink())
+            .with_context(|| format!("failed to finish downloading {name}"))?;
+        self.copy_extracted_contents(&td)?;
+        info!(
+            "Installed contents of archive file into {}",
+            self.install_root.display()
+        );
+
+        Ok(Installed::Archive(self.install_root.clone()))
     }
 }
 
-fn tar_reader_for(downloaded_file: &Path) -> Result<TarArchive<Box<dyn Read>>> {
+// A reader that can be read from through more than one handle.
+#[derive(Clone)]
+struct SharedReader(Rc<RefCell<Box<dyn Read>>>);
+
+impl Read for SharedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+// Returns the kind of a tar entry for the safety checks in `check_member`.
+fn tar_member_kind(entry_type: EntryType, link_name: Option<&Path>) -> MemberKind<'_> {
+    let link_name = link_name.unwrap_or(Path::new(""));
+    if entry_type.is_dir() {
+        MemberKind::Dir
+    } else if entry_type.is_symlink() {
+        MemberKind::Symlink(link_name)
+    } else if entry_type.is_hard_link() {
+        MemberKind::HardLink(link_name)
+    } else if entry_type.is_character_special() {
+        MemberKind::Special("character device")
+    } else if entry_type.is_block_special() {
+        MemberKind::Special("block device")
+    } else if entry_type.is_fifo() {
+        MemberKind::Special("FIFO")
+    } else {
+        MemberKind::File
+    }
+}
+
+// Alpine and Arch packages store their metadata in dot files at the root of the package, like
+// `.PKGINFO` and `.MTREE`. These are not part of what the package installs.
+fn is_package_metadata(path: &Path) -> bool {
+    normalize_member_path(path).is_some_and(|p| {
+        let mut components = p.components();
+        matches!(
+            (components.next(), components.next()),
+            (Some(c), None) if c.as_os_str().to_string_lossy().starts_with('.')
+        )
+    })
+}
+
+fn tar_reader_for(downloaded_file: &Path, ext: &Extension) -> Result<TarArchive<Box<dyn Read>>> {
     let file = open_file(downloaded_file)?;
+    if *ext == Extension::Deb {
+        return Ok(TarArchive::new(deb::data_tarball_reader(
+            downloaded_file,
+            file,
+        )?));
+    }
+    tar_reader_from(Box::new(file), ext)
+}
 
-    let ext = downloaded_file.extension();
+// Returns a tar reader for the tarball in `reader`. This does not work for `.deb` packages, since we
+// need to seek in those to find the tarball.
+fn tar_reader_from(reader: Box<dyn Read>, ext: &Extension) -> Result<TarArchive<Box<dyn Read>>> {
+    // The last part of the extension tells us how the tarball is compressed, so `.tar.gz` is `gz`,
+    // `.tgz` is `tgz`, and a plain `.tar` is `tar`.
+    match ext.extension().rsplit('.').next() {
+        // An apk package is several gzip streams concatenated together, each of which contains part
+        // of a tarball.
+        Some("apk") => Ok(TarArchive::new(Box::new(MultiGzDecoder::new(reader)))),
+        Some(e) => Ok(TarArchive::new(decompressing_reader(e, reader)?)),
+        None => Ok(TarArchive::new(reader)),
+    }
+}
+
+fn cpio_reader_for(downloaded_file: &Path) -> Result<CpioArchive<Box<dyn Read>>> {
+    let file = open_file(downloaded_file)?;
+    Ok(CpioArchive::new(rpm::payload_reader(
+        downloaded_file,
+        file,
+    )?))
+}
+
+// Returns a reader that decompresses the tarball content in `reader` based on the tarball's
+// extension, without the leading dot.
+pub(crate) fn decompressing_reader(
+    ext: &str,
+    reader: impl Read + 'static,
+) -> Result<Box<dyn Read>> {
     match ext {
-        Some(ext) => match ext.to_str() {
-            Some("tar") => Ok(TarArchive::new(Box::new(file))),
-            Some("bz" | "tbz" | "bz2" | "tbz2") => {
-                Ok(TarArchive::new(Box::new(BzDecoder::new(file))))
-            }
-            Some("gz" | "tgz") => Ok(TarArchive::new(Box::new(GzDecoder::new(file)))),
-            Some("xz" | "txz") => Ok(TarArchive::new(Box::new(XzDecoder::new(file)))),
-            Some("zst" | "tzst") => Ok(TarArchive::new(Box::new(ZstdDecoder::new(file)?))),
-            Some(e) => Err(anyhow!(
-                "don't know how to uncompress a tarball with extension = {e}",
-            )),
-            None => Err(anyhow!(
-                "tarball {} has a non-UTF-8 extension",
-                downloaded_file.display(),
-            )),
-        },
-        None => Ok(TarArchive::new(Box::new(file))),
+        "tar" => Ok(Box::new(reader)),
+        "bz" | "tbz" | "bz2" | "tbz2" => Ok(Box::new(BzDecoder::new(reader))),
+        "br" => Ok(Box::new(BrotliDecoder::new(reader, BROTLI_BUFFER_SIZE))),
+        "gz" | "tgz" => Ok(Box::new(GzDecoder::new(reader))),
+        "lz" => lzip::decoder(reader),
+        "lz4" => Ok(Box::new(Lz4Decoder::new(reader))),
+        // GNU tar treats `.tlz` as a tarball compressed with lzma, not lzip.
+        "lzma" | "tlz" => Ok(Box::new(lzma_decoder(reader)?)),
+        "xz" | "txz" => Ok(Box::new(XzDecoder::new(reader))),
+        "zst" | "tzst" => Ok(Box::new(ZstdDecoder::new(reader)?)),
+        e => Err(anyhow!(
+            "don't know how to uncompress a tarball with extension = {e}",
+        )),
     }
 }
 
+// This decodes the legacy `.lzma` format, which predates xz.
+fn lzma_decoder<R: Read>(reader: R) -> Result<XzDecoder<R>> {
+    let stream =
+        XzStream::new_lzma_decoder(u64::MAX).context("failed to create an lzma decoder")?;
+    Ok(XzDecoder::new_stream(reader, stream))
+}
+
 fn open_file(path: &Path) -> Result<File> {
     File::open(path).with_context(|| format!("Failed to open file at {}", path.display()))
 }
@@ -837,23 +2225,38 @@ mod tests {
 
     #[rstest]
     #[case("test-data/project.7z", None)]
+    #[case("test-data/project.apk", None)]
     #[case("test-data/project.AppImage", Some("AppImage"))]
     #[case("test-data/project.bat", Some("bat"))]
+    #[case("test-data/project.br", None)]
     #[case("test-data/project.bz", None)]
     #[case("test-data/project.bz2", None)]
+    // This also contains a non-executable file named `project` outside of `usr/bin`.
+    #[case("test-data/project.deb", None)]
     #[case("test-data/project.exe", Some("exe"))]
     #[case("test-data/project.gz", None)]
     #[case("test-data/project.jar", Some("jar"))]
+    #[case("test-data/project.lz", None)]
+    #[case("test-data/project.lz4", None)]
+    #[case("test-data/project.lzma", None)]
     #[case("test-data/project.phar", Some("phar"))]
+    #[case("test-data/project.pkg.tar.zst", None)]
     #[case("test-data/project.py", Some("py"))]
     #[case("test-data/project.pyz", Some("pyz"))]
+    // Like the deb package, this contains a non-executable file named `project` outside of
+    // `usr/bin`.
+    #[case("test-data/project.rpm", None)]
     #[case("test-data/project.sh", Some("sh"))]
     #[case("test-data/project.tar", None)]
     #[case("test-data/project.tar.bz", None)]
     #[case("test-data/project.tar.bz2", None)]
     #[case("test-data/project.tar.gz", None)]
+    #[case("test-data/project.tar.lz", None)]
+    #[case("test-data/project.tar.lz4", None)]
+    #[case("test-data/project.tar.lzma", None)]
     #[case("test-data/project.tar.xz", None)]
     #[case("test-data/project.tar.zst", None)]
+    #[case("test-data/project.tlz", None)]
     #[case("test-data/project.xz", None)]
     #[case("test-data/project.zip", None)]
     #[case("test-data/project.zst", None)]
@@ -863,6 +2266,19 @@ mod tests {
     // These are archive files that just contain a partial match for the expected executable.
     #[case("test-data/project-with-partial-match.tar.gz", None)]
     #[case("test-data/project-with-partial-match.zip", None)]
+    // This contains a non-executable partial match before the executable partial match.
+    #[case("test-data/project-with-non-executable-partial-match.zip", None)]
+    // These contain a link named `project` that points to the real executable.
+    #[case("test-data/project-with-hardlink.tar.gz", None)]
+    #[case("test-data/project-with-symlink.tar.gz", None)]
+    #[case("test-data/project-with-symlink.zip", None)]
+    // These contain an archive or compressed file with the executable inside of it.
+    #[case("test-data/project-with-nested-gzip.tar.gz", None)]
+    #[case("test-data/project-with-nested-tarball.zip", None)]
+    // The content of these does not match their names.
+    #[case("test-data/project-gzipped-exe", None)]
+    #[case("test-data/project-gzipped-tarball", None)]
+    #[case("test-data/project-tarball-named.zip", None)]
     fn exe_installer(
         #[case] archive_path: &str,
         #[case] installed_extension: Option<&str>,
@@ -944,11 +2360,21 @@ mod tests {
     ) -> Result<()> {
         let exe_file_stem = "project";
 
+        let platform_name = if is_windows {
+            "x86_64-pc-windows-msvc"
+        } else {
+            "x86_64-unknown-linux-gnu"
+        };
         let installer = ExeInstaller::new(
             install_path.to_path_buf(),
             install_path_is_from_rename_exe_to,
             exe_file_stem.to_string(),
-            is_windows,
+            true,
+            Platform::find(platform_name).unwrap().clone(),
+            false,
+            None,
+            None,
+            ExtractionLimits::default(),
         );
 
         installer.install(&Download {
@@ -992,14 +2418,433 @@ mod tests {
         Ok(())
     }
 
+    #[rstest]
+    #[case::one_executable(
+        "test-data/project-with-one-unrelated-executable.tar.gz",
+        true,
+        Ok("tool")
+    )]
+    #[case::exe_was_set(
+        "test-data/project-with-one-unrelated-executable.tar.gz",
+        false,
+        Err("could not find any files matching [project*] in the downloaded archive file")
+    )]
+    #[case::several_executables(
+        "test-data/project-with-several-unrelated-executables.tar.gz",
+        true,
+        Err("contains more than one executable, so ubi cannot tell which one to install: project-1.2.3/other-tool, project-1.2.3/tool.")
+    )]
+    fn exe_installer_infers_exe(
+        #[case] archive_path: &str,
+        #[case] can_infer_exe: bool,
+        #[case] expect: Result<&str, &str>,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let installer = ExeInstaller::new(
+            td.path().join("project"),
+            false,
+            "project".to_string(),
+            can_infer_exe,
+            Platform::find("x86_64-unknown-linux-gnu").unwrap().clone(),
+            false,
+            None,
+            None,
+            ExtractionLimits::default(),
+        );
+        let res = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        });
+
+        match expect {
+            Ok(name) => {
+                res?;
+                assert!(fs::exists(td.path().join(name))?, "installed {name}");
+                assert!(!fs::exists(td.path().join("project"))?);
+            }
+            Err(msg) => {
+                let err = res.unwrap_err().to_string();
+                assert!(err.contains(msg), "error `{err}` contains `{msg}`");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::exact_match("test-data/project.tar.gz", Extension::TarGz)]
+    #[case::partial_match("test-data/project-with-partial-match.tar.gz", Extension::TarGz)]
+    #[case::symlink("test-data/project-with-symlink.tar.gz", Extension::TarGz)]
+    #[case::hard_link("test-data/project-with-hardlink.tar.gz", Extension::TarGz)]
+    #[case::nested("test-data/project-with-nested-gzip.tar.gz", Extension::TarGz)]
+    #[case::inferred(
+        "test-data/project-with-one-unrelated-executable.tar.gz",
+        Extension::TarGz
+    )]
+    #[case::deb("test-data/project.deb", Extension::Deb)]
+    fn exe_installer_spools_tarball_match(
+        #[case] archive_path: &str,
+        #[case] ext: Extension,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let installer = ExeInstaller::new(
+            td.path().join("project"),
+            false,
+            "project".to_string(),
+            true,
+            Platform::find("x86_64-unknown-linux-gnu").unwrap().clone(),
+            false,
+            None,
+            None,
+            ExtractionLimits::default(),
+        );
+
+        let mut spool = MemberSpool::new()?;
+        let mut arch = tar_reader_for(Path::new(archive_path), &ext)?;
+        let m = installer
+            .best_match_from_archive(
+                TarEntriesIterator::new(arch.entries()?),
+                "tarball",
+                0,
+                Some(&mut spool),
+            )?
+            .expect("found a match");
+        let spooled = spool.take(m.index).expect("the match was spooled");
+
+        let mut arch = tar_reader_for(Path::new(archive_path), &ext)?;
+        let mut entry = arch.entries()?.nth(m.index).expect("entry exists")?;
+        let mut expect = vec![];
+        entry.read_to_end(&mut expect)?;
+        assert_eq!(fs::read(spooled)?, expect);
+
+        Ok(())
+    }
+
+    #[rstest]
+    // The members of this tarball have names that don't say what platform they're for, so the
+    // choice is made by looking at each member's binary header.
+    #[case::binary_header_linux_x86_64(
+        "test-data/project-with-binaries-for-several-platforms.tar.gz",
+        "x86_64-unknown-linux-gnu",
+        "project-c"
+    )]
+    #[case::binary_header_linux_aarch64(
+        "test-data/project-with-binaries-for-several-platforms.tar.gz",
+        "aarch64-unknown-linux-gnu",
+        "project-b"
+    )]
+    #[case::binary_header_macos_aarch64(
+        "test-data/project-with-binaries-for-several-platforms.tar.gz",
+        "aarch64-apple-darwin",
+        "project-a"
+    )]
+    // The members of this tarball are not binaries, so the choice is made by looking at each
+    // member's name.
+    #[case::name_linux_x86_64(
+        "test-data/project-with-names-for-several-platforms.tar.gz",
+        "x86_64-unknown-linux-gnu",
+        "project-linux-amd64"
+    )]
+    #[case::name_linux_aarch64(
+        "test-data/project-with-names-for-several-platforms.tar.gz",
+        "aarch64-unknown-linux-gnu",
+        "project-linux-arm64"
+    )]
+    #[case::name_macos_aarch64(
+        "test-data/project-with-names-for-several-platforms.tar.gz",
+        "aarch64-apple-darwin",
+        "project-darwin-arm64"
+    )]
+    fn exe_installer_ranks_partial_matches_by_platform(
+        #[case] archive_path: &str,
+        #[case] platform_name: &str,
+        #[case] expect_member: &str,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            false,
+            "project".to_string(),
+            true,
+            Platform::find(platform_name).unwrap().clone(),
+            false,
+            None,
+            None,
+            ExtractionLimits::default(),
+        );
+        installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        })?;
+
+        // Each member's content ends with its name.
+        let content = fs::read(&install_path)?;
+        assert!(
+            content.ends_with(expect_member.as_bytes()),
+            "installed {expect_member}",
+        );
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::matching_platform("x86_64-unknown-linux-gnu", false, true)]
+    #[case::mismatched_platform("aarch64-unknown-linux-gnu", false, false)]
+    #[case::mismatched_platform_allowed("aarch64-unknown-linux-gnu", true, true)]
+    fn exe_installer_checks_platform(
+        #[case] platform_name: &str,
+        #[case] allow_platform_mismatch: bool,
+        #[case] expect_installed: bool,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            false,
+            "project".to_string(),
+            true,
+            Platform::find(platform_name).unwrap().clone(),
+            allow_platform_mismatch,
+            None,
+            None,
+            ExtractionLimits::default(),
+        );
+
+        // This is just the ELF header for a Linux x86-64 executable.
+        let res = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/project-linux-x86-64"),
+        });
+        assert_eq!(res.is_ok(), expect_installed, "{res:?}");
+        assert_eq!(fs::exists(&install_path)?, expect_installed);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::jar_unix(
+        "test-data/project.jar",
+        "x86_64-unknown-linux-gnu",
+        "project",
+        "project.jar",
+        "java -jar"
+    )]
+    #[case::pyz_unix(
+        "test-data/project.pyz",
+        "x86_64-unknown-linux-gnu",
+        "project",
+        "project.pyz",
+        "python3"
+    )]
+    #[case::py_unix(
+        "test-data/project.py",
+        "x86_64-unknown-linux-gnu",
+        "project",
+        "project.py",
+        "python3"
+    )]
+    #[case::jar_windows(
+        "test-data/project.jar",
+        "x86_64-pc-windows-msvc",
+        "project.cmd",
+        "project.jar",
+        "java -jar"
+    )]
+    #[case::py_windows(
+        "test-data/project.py",
+        "x86_64-pc-windows-msvc",
+        "project.cmd",
+        "project.py",
+        "python"
+    )]
+    fn exe_installer_installs_launcher(
+        #[case] archive_path: &str,
+        #[case] platform_name: &str,
+        #[case] expect_launcher: &str,
+        #[case] expect_artifact: &str,
+        #[case] expect_command: &str,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let lib_dir = td.path().join("lib");
+        let installer = ExeInstaller::new(
+            td.path().join("bin").join("project"),
+            false,
+            "project".to_string(),
+            true,
+            Platform::find(platform_name).unwrap().clone(),
+            false,
+            Some(LauncherConfig {
+                lib_dir: lib_dir.clone(),
+                for_interpreted_files: true,
+                for_appimages: false,
+            }),
+            None,
+            ExtractionLimits::default(),
+        );
+        let Installed::Exe(installed) = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        })?
+        else {
+            panic!("installer did not return the installed exe");
+        };
+        installed.commit()?;
+
+        let artifact = lib_dir.join(expect_artifact);
+        assert_eq!(fs::read(&artifact)?, fs::read(archive_path)?);
+
+        let launcher = td.path().join("bin").join(expect_launcher);
+        let script = fs::read_to_string(&launcher)?;
+        let expect_invocation = format!("{expect_command} ");
+        assert!(
+            script.contains(&expect_invocation),
+            "launcher runs `{expect_command}`: {script}",
+        );
+        assert!(
+            script.contains(&*artifact.to_string_lossy()),
+            "launcher runs {}: {script}",
+            artifact.display(),
+        );
+        #[cfg(target_family = "unix")]
+        assert!(launcher.metadata()?.permissions().mode() & 0o111 != 0);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::extracted(true)]
+    #[case::not_extracted(false)]
+    fn exe_installer_extracts_appimage(#[case] extract: bool) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let bin_dir = td.path().join("bin");
+        let lib_dir = td.path().join("lib");
+        let installer = ExeInstaller::new(
+            bin_dir.join("project"),
+            false,
+            "project".to_string(),
+            true,
+            Platform::find("x86_64-unknown-linux-gnu").unwrap().clone(),
+            false,
+            Some(LauncherConfig {
+                lib_dir: lib_dir.clone(),
+                for_interpreted_files: false,
+                for_appimages: extract,
+            }),
+            None,
+            ExtractionLimits::default(),
+        );
+        // We install twice to make sure that an existing extracted directory is replaced.
+        for _ in 0..2 {
+            let Installed::Exe(installed) = installer.install(&Download {
+                _temp_dir: tempdir()?,
+                archive_path: PathBuf::from("test-data/project-squashfs-gzip.AppImage"),
+            })?
+            else {
+                panic!("installer did not return the installed exe");
+            };
+            installed.commit()?;
+        }
+
+        if !extract {
+            assert!(fs::exists(bin_dir.join("project.AppImage"))?);
+            assert!(!fs::exists(&lib_dir)?);
+            return Ok(());
+        }
+
+        let app_dir = lib_dir.join("project");
+        assert!(fs::exists(app_dir.join("AppRun"))?);
+        assert!(fs::exists(app_dir.join("usr/bin/project"))?);
+        assert!(!fs::exists(lib_dir.join(".project.ubi-backup"))?);
+        let script = fs::read_to_string(bin_dir.join("project"))?;
+        assert!(
+            script.contains(&*app_dir.join("AppRun").to_string_lossy()),
+            "launcher runs AppRun: {script}",
+        );
+
+        #[cfg(target_family = "unix")]
+        {
+            let output = std::process::Command::new(bin_dir.join("project")).output()?;
+            assert!(output.status.success(), "{output:?}");
+            assert_eq!(String::from_utf8_lossy(&output.stdout), "project 1.2.3\n");
+        }
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::commit(true)]
+    #[case::roll_back(false)]
+    fn exe_installer_replaces_existing_exe(#[case] commit: bool) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+        fs::write(&install_path, "old")?;
+        let backup_path = td.path().join(".project.ubi-backup");
+
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            false,
+            "project".to_string(),
+            true,
+            Platform::find("x86_64-unknown-linux-gnu").unwrap().clone(),
+            false,
+            None,
+            None,
+            ExtractionLimits::default(),
+        );
+        let Installed::Exe(installed) = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from("test-data/project-linux-x86-64"),
+        })?
+        else {
+            panic!("installer did not return the installed exe");
+        };
+        assert_eq!(installed.path, install_path);
+        assert_ne!(fs::read(&install_path)?, b"old");
+        assert_eq!(fs::read(&backup_path)?, b"old");
+
+        if commit {
+            installed.commit()?;
+            assert_ne!(fs::read(&install_path)?, b"old");
+        } else {
+            installed.roll_back()?;
+            assert_eq!(fs::read(&install_path)?, b"old");
+        }
+        assert!(!fs::exists(&backup_path)?, "backup was removed");
+
+        Ok(())
+    }
+
     #[rstest]
     #[case("test-data/project.7z")]
+    #[case("test-data/project.apk")]
+    #[case("test-data/project.deb")]
+    #[case("test-data/project.pkg.tar.zst")]
+    #[case("test-data/project.rpm")]
     #[case("test-data/project.tar")]
     #[case("test-data/project.tar.bz")]
     #[case("test-data/project.tar.bz2")]
     #[case("test-data/project.tar.gz")]
+    #[case("test-data/project.tar.lz")]
+    #[case("test-data/project.tar.lz4")]
+    #[case("test-data/project.tar.lzma")]
     #[case("test-data/project.tar.xz")]
     #[case("test-data/project.tar.zst")]
+    #[case("test-data/project.tlz")]
     #[case("test-data/project.zip")]
     fn archive_installer(#[case] archive_path: &str) -> Result<()> {
         crate::test_log::init_logging();
@@ -1011,7 +2856,12 @@ mod tests {
         path_with_subdir.extend(&["subdir", "project"]);
 
         for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(String::from("project"), install_root.clone());
+            let installer = ArchiveInstaller::new(
+                String::from("project"),
+                install_root.clone(),
+                None,
+                ExtractionLimits::default(),
+            );
             installer.install(&Download {
                 // It doesn't matter what we use here. We're not actually going to
                 // put anything in this temp dir.
@@ -1034,6 +2884,350 @@ mod tests {
         Ok(())
     }
 
+    #[rstest]
+    #[case::zipcrypto("test-data/project-zipcrypto-encrypted.zip", Some("hunter2"), None)]
+    #[case::aes_zip("test-data/project-aes-encrypted.zip", Some("hunter2"), None)]
+    #[case::sevenz("test-data/project-encrypted.7z", Some("hunter2"), None)]
+    #[case::unencrypted_zip("test-data/project.zip", Some("hunter2"), None)]
+    #[case::unencrypted_7z("test-data/project.7z", Some("hunter2"), None)]
+    #[case::zip_without_password(
+        "test-data/project-aes-encrypted.zip",
+        None,
+        Some("Password required to decrypt file")
+    )]
+    #[case::zip_with_wrong_password(
+        "test-data/project-aes-encrypted.zip",
+        Some("wrong"),
+        Some("provided password is incorrect")
+    )]
+    #[case::sevenz_without_password(
+        "test-data/project-encrypted.7z",
+        None,
+        Some("PasswordRequired")
+    )]
+    fn exe_installer_with_archive_password(
+        #[case] archive_path: &str,
+        #[case] password: Option<&str>,
+        #[case] expect_err: Option<&str>,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join("project");
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            false,
+            "project".to_string(),
+            true,
+            Platform::find("x86_64-unknown-linux-gnu").unwrap().clone(),
+            false,
+            None,
+            password.map(String::from),
+            ExtractionLimits::default(),
+        );
+        let res = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        });
+
+        match expect_err {
+            None => {
+                let Installed::Exe(installed) = res? else {
+                    panic!("expected an installed executable");
+                };
+                installed.commit()?;
+                assert_eq!(fs::read(&install_path)?, b"exe");
+            }
+            Some(msg) => {
+                let err = format!("{:#}", res.unwrap_err());
+                assert!(err.contains(msg), "error `{err}` contains `{msg}`");
+                assert!(!install_path.exists());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::zipcrypto("test-data/project-zipcrypto-encrypted.zip", Some("hunter2"), None)]
+    #[case::aes_zip("test-data/project-aes-encrypted.zip", Some("hunter2"), None)]
+    #[case::sevenz("test-data/project-encrypted.7z", Some("hunter2"), None)]
+    #[case::unencrypted_zip("test-data/project.zip", Some("hunter2"), None)]
+    #[case::zip_without_password(
+        "test-data/project-zipcrypto-encrypted.zip",
+        None,
+        Some("Password required to decrypt file")
+    )]
+    #[case::sevenz_with_wrong_password(
+        "test-data/project-encrypted.7z",
+        Some("wrong"),
+        Some("MaybeBadPassword")
+    )]
+    fn archive_installer_with_archive_password(
+        #[case] archive_path: &str,
+        #[case] password: Option<&str>,
+        #[case] expect_err: Option<&str>,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+        let installer = ArchiveInstaller::new(
+            String::from("project"),
+            install_root.clone(),
+            password.map(String::from),
+            ExtractionLimits::default(),
+        );
+        let res = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        });
+
+        let exe = install_root.join("bin").join("project");
+        match expect_err {
+            None => {
+                res?;
+                assert_eq!(fs::read(&exe)?, b"exe");
+            }
+            Some(msg) => {
+                let err = format!("{:#}", res.unwrap_err());
+                assert!(err.contains(msg), "error `{err}` contains `{msg}`");
+                assert!(!exe.exists());
+            }
+        }
+
+        Ok(())
+    }
+
+    const FILE_TOO_LARGE: &str = "is larger than the limit of 2 bytes for a single extracted file";
+    const TOTAL_TOO_LARGE: &str = "goes over the limit of 1048576 bytes for all extracted files";
+    const TOO_MANY_ENTRIES: &str = "the archive contains more than the limit of 2 entries";
+
+    fn max_file_bytes() -> ExtractionLimits {
+        ExtractionLimits {
+            file_bytes: Some(2),
+            ..Default::default()
+        }
+    }
+
+    fn max_total_bytes() -> ExtractionLimits {
+        ExtractionLimits {
+            total_bytes: Some(1024 * 1024),
+            ..Default::default()
+        }
+    }
+
+    fn max_entries() -> ExtractionLimits {
+        ExtractionLimits {
+            entries: Some(2),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case::gz("test-data/project.gz", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::gz_bomb("test-data/project-bomb.gz", max_total_bytes(), TOTAL_TOO_LARGE)]
+    #[case::tarball("test-data/project.tar.gz", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::tarball_entries("test-data/project.tar.gz", max_entries(), TOO_MANY_ENTRIES)]
+    #[case::zip("test-data/project.zip", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::zip_entries("test-data/project.zip", max_entries(), TOO_MANY_ENTRIES)]
+    #[case::sevenz("test-data/project.7z", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::rpm("test-data/project.rpm", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::nested(
+        "test-data/project-with-nested-gzip.tar.gz",
+        max_file_bytes(),
+        FILE_TOO_LARGE
+    )]
+    fn exe_installer_extraction_limits(
+        #[case] archive_path: &str,
+        #[case] limits: ExtractionLimits,
+        #[case] expect_err: &str,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let install_path = td.path().join("bin").join("project");
+        let installer = ExeInstaller::new(
+            install_path.clone(),
+            false,
+            "project".to_string(),
+            true,
+            Platform::find("x86_64-unknown-linux-gnu").unwrap().clone(),
+            false,
+            None,
+            None,
+            limits,
+        );
+        let res = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        });
+
+        let err = format!("{:#}", res.unwrap_err());
+        assert!(
+            err.contains(expect_err),
+            "error `{err}` contains `{expect_err}`"
+        );
+        assert!(!install_path.exists(), "nothing was installed");
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::tarball("test-data/project.tar.gz", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::tarball_entries("test-data/project.tar.gz", max_entries(), TOO_MANY_ENTRIES)]
+    #[case::deb("test-data/project.deb", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::rpm("test-data/project.rpm", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::sevenz("test-data/project.7z", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::sevenz_entries("test-data/project.7z", max_entries(), TOO_MANY_ENTRIES)]
+    #[case::zip("test-data/project.zip", max_file_bytes(), FILE_TOO_LARGE)]
+    #[case::zip_entries("test-data/project.zip", max_entries(), TOO_MANY_ENTRIES)]
+    fn archive_installer_extraction_limits(
+        #[case] archive_path: &str,
+        #[case] limits: ExtractionLimits,
+        #[case] expect_err: &str,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("project");
+        let installer =
+            ArchiveInstaller::new(String::from("project"), install_root.clone(), None, limits);
+        let res = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        });
+
+        let err = format!("{:#}", res.unwrap_err());
+        assert!(
+            err.contains(expect_err),
+            "error `{err}` contains `{expect_err}`"
+        );
+        assert!(!install_root.exists(), "nothing was installed");
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::tarball_absolute_path(
+        "test-data/project-with-absolute-path.tar.gz",
+        "the archive member /tmp/evil has an absolute path"
+    )]
+    #[case::tarball_parent_dir(
+        "test-data/project-with-parent-dir.tar.gz",
+        "the archive member project/../../evil has a `..` component in its path"
+    )]
+    #[case::tarball_escaping_symlink(
+        "test-data/project-with-escaping-symlink.tar.gz",
+        "the archive member project/bin/evil is a symlink to ../../../etc/passwd, which is outside of the archive root"
+    )]
+    #[case::tarball_escaping_hardlink(
+        "test-data/project-with-escaping-hardlink.tar.gz",
+        "the archive member project/bin/evil is a hard link to ../etc/passwd, which is outside of the archive root"
+    )]
+    #[case::tarball_device_file(
+        "test-data/project-with-device-file.tar.gz",
+        "the archive member project/bin/evil is a character device, which ubi will not extract"
+    )]
+    #[case::tarball_setuid_file(
+        "test-data/project-with-setuid-file.tar.gz",
+        "the archive member project/bin/evil has the setuid bit set"
+    )]
+    #[case::zip_absolute_path(
+        "test-data/project-with-absolute-path.zip",
+        "the archive member /tmp/evil has an absolute path"
+    )]
+    #[case::zip_parent_dir(
+        "test-data/project-with-parent-dir.zip",
+        "the archive member project/../../evil has a `..` component in its path"
+    )]
+    #[case::zip_escaping_symlink(
+        "test-data/project-with-escaping-symlink.zip",
+        "the archive member project/bin/evil is a symlink to ../../../etc/passwd, which is outside of the archive root"
+    )]
+    #[case::zip_device_file(
+        "test-data/project-with-device-file.zip",
+        "the archive member project/bin/evil is a character device, which ubi will not extract"
+    )]
+    #[case::zip_setuid_file(
+        "test-data/project-with-setuid-file.zip",
+        "the archive member project/bin/evil has the setuid bit set"
+    )]
+    #[case::sevenz_parent_dir(
+        "test-data/project-with-parent-dir.7z",
+        "the archive member project/../../evil has a `..` component in its path"
+    )]
+    #[case::sevenz_setuid_file(
+        "test-data/project-with-setuid-file.7z",
+        "the archive member project/bin/evil has the setuid bit set"
+    )]
+    fn archive_installer_rejects_unsafe_members(
+        #[case] archive_path: &str,
+        #[case] expect_err: &str,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let install_root = td.path().join("subdir").join("project");
+        let installer = ArchiveInstaller::new(
+            String::from("project"),
+            install_root.clone(),
+            None,
+            ExtractionLimits::default(),
+        );
+        let res = installer.install(&Download {
+            _temp_dir: tempdir()?,
+            archive_path: PathBuf::from(archive_path),
+        });
+
+        let err = format!("{:#}", res.unwrap_err());
+        assert!(
+            err.contains(expect_err),
+            "error `{err}` contains `{expect_err}`"
+        );
+        assert!(!install_root.exists(), "nothing was installed");
+        assert!(!td.path().join("evil").exists(), "nothing escaped the root");
+
+        Ok(())
+    }
+
+    // Each member in these archives is safe on its own, but together they are `x -> .`, `x/y -> ..`,
+    // and `x/y/evil`, which would write `evil` to the parent of the directory we extract into.
+    #[cfg(target_family = "unix")]
+    #[rstest]
+    #[case::zip("test-data/project-with-chained-symlinks.zip")]
+    #[case::rpm("test-data/project-with-chained-symlinks.rpm")]
+    fn extract_entire_archive_rejects_chained_symlinks(#[case] archive_path: &str) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let into = td.path().join("into");
+        create_dir_all(&into)?;
+        let installer = ArchiveInstaller::new(
+            String::from("project"),
+            td.path().join("project"),
+            None,
+            ExtractionLimits::default(),
+        );
+        let budget = ExtractionBudget::new(ExtractionLimits::default());
+        let archive_path = Path::new(archive_path);
+        let res = if archive_path.extension().is_some_and(|e| e == "zip") {
+            installer.extract_entire_zip(archive_path, &into, &budget)
+        } else {
+            ArchiveInstaller::extract_entire_rpm(archive_path, &into, &budget)
+        };
+
+        let err = format!("{:#}", res.unwrap_err());
+        let expect_err = "the archive member x/y/evil is inside a symlink that leads outside of the archive root";
+        assert!(
+            err.contains(expect_err),
+            "error `{err}` contains `{expect_err}`"
+        );
+        assert!(!td.path().join("evil").exists(), "nothing escaped the root");
+
+        Ok(())
+    }
+
     // This tests a bug in the initial implementation where a tarball that just contained files
     // caused us to try to move its contents up to a directory that didn't exist.
     #[test_log::test]
@@ -1045,7 +3239,12 @@ mod tests {
         path_with_subdir.extend(&["subdir", "project"]);
 
         for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(String::from("project"), install_root.clone());
+            let installer = ArchiveInstaller::new(
+                String::from("project"),
+                install_root.clone(),
+                None,
+                ExtractionLimits::default(),
+            );
             installer.install(&Download {
                 // It doesn't matter what we use here. We're not actually going to
                 // put anything in this temp dir.
@@ -1073,7 +3272,12 @@ mod tests {
         path_with_subdir.extend(&["subdir", "project"]);
 
         for install_root in [path_without_subdir, path_with_subdir] {
-            let installer = ArchiveInstaller::new(String::from("project"), install_root.clone());
+            let installer = ArchiveInstaller::new(
+                String::from("project"),
+                install_root.clone(),
+                None,
+                ExtractionLimits::default(),
+            );
             installer.install(&Download {
                 // It doesn't matter what we use here. We're not actually going to
                 // put anything in this temp dir.
@@ -1117,7 +3321,12 @@ mod tests {
             let share_dir = install_root.join("share");
             create_dir_all(&share_dir)?;
 
-            let installer = ArchiveInstaller::new(String::from("project"), install_root.clone());
+            let installer = ArchiveInstaller::new(
+                String::from("project"),
+                install_root.clone(),
+                None,
+                ExtractionLimits::default(),
+            );
             installer.install(&Download {
                 // It doesn't matter what we use here. We're not actually going to
                 // put anything in this temp dir.
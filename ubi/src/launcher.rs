@@ -0,0 +1,173 @@
+// Some release artifacts can't be run directly from the install directory. Files like `.jar` and
+// `.pyz` files need an interpreter, and an extracted AppImage has to be run through its `AppRun`
+// file. When the user asks for launchers, we install these into a lib directory and write a small
+// script to the install directory that runs them.
+use crate::{appimage::APP_RUN, extension::Extension};
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+// Which launchers to install, and where to put the things they launch.
+#[derive(Debug, Clone)]
+pub(crate) struct LauncherConfig {
+    pub(crate) lib_dir: PathBuf,
+    pub(crate) for_interpreted_files: bool,
+    pub(crate) for_appimages: bool,
+}
+
+#[derive(Debug)]
+pub(crate) enum Launcher {
+    // Runs a file with an interpreter.
+    Interpreter {
+        command: &'static [&'static str],
+        is_windows: bool,
+    },
+    // Runs the `AppRun` file in a directory extracted from an AppImage. AppImages only run on Linux,
+    // so this is always a shell script.
+    AppRun,
+}
+
+impl Launcher {
+    // Returns a launcher for the artifact at `path`, or `None` if its extension isn't one that we
+    // write launchers for.
+    pub(crate) fn for_artifact(path: &Path, is_windows: bool) -> Result<Option<Self>> {
+        Ok(Extension::from_path(path)?
+            .and_then(|ext| ext.launcher_command(is_windows))
+            .map(|command| Launcher::Interpreter {
+                command,
+                is_windows,
+            }))
+    }
+
+    // The file name for the launcher of an artifact named `artifact_stem`, without its extension.
+    pub(crate) fn file_name(&self, artifact_stem: &str) -> String {
+        match self {
+            Launcher::Interpreter {
+                is_windows: true, ..
+            } => format!("{artifact_stem}.cmd"),
+            Launcher::Interpreter {
+                is_windows: false, ..
+            }
+            | Launcher::AppRun => artifact_stem.to_string(),
+        }
+    }
+
+    // Returns the launcher script that runs `target`, passing along all of the launcher's
+    // arguments. For an interpreter, `target` is the file to run. For an AppImage, it's the
+    // directory the AppImage was extracted into.
+    pub(crate) fn script(&self, target: &Path) -> String {
+        match self {
+            Launcher::Interpreter {
+                command,
+                is_windows: true,
+            } => {
+                format!(
+                    "@echo off\r\n{} {} %*\r\n",
+                    command.join(" "),
+                    cmd_quote(&target.to_string_lossy()),
+                )
+            }
+            Launcher::Interpreter {
+                command,
+                is_windows: false,
+            } => format!(
+                "#!/bin/sh\nexec {} {} \"$@\"\n",
+                command.join(" "),
+                sh_quote(&target.to_string_lossy()),
+            ),
+            // The AppImage runtime sets these env vars before running `AppRun`, and some `AppRun`
+            // files depend on them.
+            Launcher::AppRun => {
+                let app_dir = sh_quote(&target.to_string_lossy());
+                format!(
+                    "#!/bin/sh\nexport APPDIR={app_dir}\nexport ARGV0=\"$0\"\nexec {} \"$@\"\n",
+                    sh_quote(&target.join(APP_RUN).to_string_lossy()),
+                )
+            }
+        }
+    }
+}
+
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+// Inside double quotes, the only character a batch file treats specially is `%`, which starts a
+// variable reference even there, so we double it. Batch files have no way to escape a double quote,
+// but Windows paths can't contain them, so this is safe.
+fn cmd_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('%', "%%"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::jar_unix(
+        "tool.jar",
+        false,
+        "/opt/lib/tool.jar",
+        Some(("tool", "#!/bin/sh\nexec java -jar '/opt/lib/tool.jar' \"$@\"\n"))
+    )]
+    #[case::pyz_unix(
+        "tool.pyz",
+        false,
+        "/opt/lib/tool.pyz",
+        Some(("tool", "#!/bin/sh\nexec python3 '/opt/lib/tool.pyz' \"$@\"\n"))
+    )]
+    #[case::py_unix_quoted(
+        "tool.py",
+        false,
+        "/opt/it's/tool.py",
+        Some(("tool", "#!/bin/sh\nexec python3 '/opt/it'\\''s/tool.py' \"$@\"\n"))
+    )]
+    #[case::jar_windows(
+        "tool.jar",
+        true,
+        r"C:\lib\tool.jar",
+        Some(("tool.cmd", "@echo off\r\njava -jar \"C:\\lib\\tool.jar\" %*\r\n"))
+    )]
+    #[case::py_windows(
+        "tool.py",
+        true,
+        r"C:\lib\tool.py",
+        Some(("tool.cmd", "@echo off\r\npython \"C:\\lib\\tool.py\" %*\r\n"))
+    )]
+    #[case::windows_percent(
+        "tool.jar",
+        true,
+        r"C:\100%\%PATH%\tool.jar",
+        Some(("tool.cmd", "@echo off\r\njava -jar \"C:\\100%%\\%%PATH%%\\tool.jar\" %*\r\n"))
+    )]
+    #[case::exe("tool.exe", true, r"C:\lib\tool.exe", None)]
+    #[case::no_extension("tool", false, "/opt/lib/tool", None)]
+    fn interpreter_launcher(
+        #[case] artifact_name: &str,
+        #[case] is_windows: bool,
+        #[case] artifact_path: &str,
+        #[case] expect: Option<(&str, &str)>,
+    ) -> Result<()> {
+        let launcher = Launcher::for_artifact(Path::new(artifact_name), is_windows)?;
+        match (launcher, expect) {
+            (Some(launcher), Some((file_name, script))) => {
+                assert_eq!(launcher.file_name("tool"), file_name);
+                assert_eq!(launcher.script(Path::new(artifact_path)), script);
+            }
+            (None, None) => (),
+            (launcher, expect) => panic!("got {launcher:?}, expected {expect:?}"),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_run_launcher() {
+        let launcher = Launcher::AppRun;
+        assert_eq!(launcher.file_name("tool"), "tool");
+        assert_eq!(
+            launcher.script(Path::new("/opt/lib/tool")),
+            "#!/bin/sh\nexport APPDIR='/opt/lib/tool'\nexport ARGV0=\"$0\"\nexec '/opt/lib/tool/AppRun' \"$@\"\n",
+        );
+    }
+}
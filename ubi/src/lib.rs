@@ -61,24 +61,36 @@
 //! doesn't match one of the following:
 //!
 //! - `.7z`
+//! - `.apk` (Linux only)
 //! - `.AppImage` (Linux only)
 //! - `.bat` (Windows only)
+//! - `.br`
 //! - `.bz`
 //! - `.bz2`
+//! - `.deb` (Linux only)
 //! - `.exe` (Windows only)
 //! - `.gz`
 //! - `.jar`
+//! - `.lz`
+//! - `.lz4`
+//! - `.lzma`
 //! - `.phar`
+//! - `.pkg.tar.zst` (Linux only)
 //! - `.py`
 //! - `.pyz`
+//! - `.rpm` (Linux only)
 //! - `.sh`
 //! - `.tar`
 //! - `.tar.bz`
 //! - `.tar.bz2`
 //! - `.tar.gz`
+//! - `.tar.lz`
+//! - `.tar.lz4`
+//! - `.tar.lzma`
 //! - `.tar.xz`
 //! - `.tbz`
 //! - `.tgz`
+//! - `.tlz`
 //! - `.txz`
 //! - `.xz`
 //! - `.zip`
@@ -118,6 +130,11 @@
 //!   32-bit assets.
 //! - If you've provided a string to [`UbiBuilder::matching`], this is used as a filter at this
 //!   point.
+//! - If some of the remaining assets are Linux distro packages (`.apk`, `.deb`, `.pkg.tar.zst`, or
+//!   `.rpm`) and some are not, it filters out the packages.
+//! - If you are on a musl platform and all of the remaining assets are Linux distro packages
+//!   (`.apk`, `.deb`, `.pkg.tar.zst`, or `.rpm`), then any Alpine `.apk` packages are preferred,
+//!   since Alpine uses musl.
 //! - If your platform is macOS on ARM64 and there are assets for both x86-64 and ARM64, it filters
 //!   out the non-ARM64 assets.
 //!
@@ -126,14 +143,27 @@
 //!
 //! ## How `ubi` Finds the Right Executable in an Archive File
 //!
+//! After downloading the selected release artifact, `ubi` looks at the first few bytes of the file
+//! to see what it contains. It recognizes gzip, bzip2, xz, zstd, zip, 7z, and tar data, as well as
+//! ELF, Mach-O, and PE executables. If the content doesn't match the file's name, for example a
+//! gzipped tarball named `precious-linux-amd64` or a tarball named `precious.zip`, then `ubi`
+//! handles the file based on its content instead of its name.
+//!
 //! If the selected release artifact is an archive file (a tarball or zip file), then `ubi` will
-//! look inside the archive to find the right executable.
+//! look inside the archive to find the right executable. A `.deb` package is treated as an archive
+//! containing the files in the package's data tarball. `ubi` reads these packages itself, so it
+//! does not need `dpkg` to be installed. Similarly, an `.rpm` package is treated as an archive
+//! containing the files in the package's payload, so `rpm` is not needed either. Alpine `.apk` and
+//! Arch Linux `.pkg.tar.zst` packages are tarballs, but the package metadata files at their root,
+//! like `.PKGINFO`, are ignored.
 //!
 //! It first tries to find a file matching the exact name of the project (plus an extension on
 //! Windows). So for example, if you're installing
 //! [`houseabsolute/precious`](https://github.com/houseabsolute/precious), it will look in the
 //! archive for a file named `precious` on Unix-like systems and `precious.bat` or `precious.exe` on
-//! Windows. Note that if it finds an exact match, it does not check the file's mode.
+//! Windows. Note that if it finds an exact match, it does not check the file's mode. If there is
+//! more than one exact match, a file in a `bin` or `sbin` directory is preferred, so that something
+//! like `usr/share/bash-completion/completions/precious` is not picked over `usr/bin/precious`.
 //!
 //! If it can't find an exact match it will look for a file that _starts with_ the project
 //! name. This is mostly to account for projects that include things like platforms or release names
@@ -141,9 +171,124 @@
 //! [`houseabsolute/precious`](https://github.com/houseabsolute/precious) as an example again, it
 //! will match a file named `precious-linux-amd64` or `precious-v1.2.3`. In this case, it will
 //! _rename_ the extracted file to `precious`. On Unix-like systems, these partial matches will only
-//! be considered if the file's mode includes an executable bit. On Windows, it looks for a partial
-//! match that is a `.bat` or `.exe` file, and the extracted file will be renamed to `precious.bat`
-//! or `precious.exe`.
+//! be considered if the file's mode includes an executable bit. Zip files don't reliably record a
+//! Unix mode, so for these, a partial match that is marked as executable is preferred, but one
+//! without an executable bit will still be used if there's nothing better. On Windows, it looks for
+//! a partial match that is a `.bat` or `.exe` file, and the extracted file will be renamed to
+//! `precious.bat` or `precious.exe`.
+//!
+//! If there is more than one partial match, for example `precious-linux-amd64` and
+//! `precious-linux-arm64`, `ubi` reads the start of each one. If it's an ELF, Mach-O, or PE binary,
+//! its header says which platform it was built for, and binaries for your platform are preferred
+//! over everything else, while binaries for other platforms are only used as a last resort. When a
+//! partial match isn't a binary, `ubi` uses the same OS and CPU architecture regexes that it uses
+//! for release artifact names on the member's name instead.
+//!
+//! If the matching file is a symlink or hard link, for example `bin/precious ->
+//! ../libexec/precious-1.2.3`, then `ubi` follows the link to its target in the archive and
+//! installs the target's content using the name of the link. Links that point outside of the
+//! archive are ignored.
+//!
+//! If nothing in the archive matches and you did not set the executable name with
+//! [`UbiBuilder::exe`], but the archive contains exactly one executable file, then `ubi` installs
+//! that file using its own name. For example, the archives for
+//! [`BurntSushi/ripgrep`](https://github.com/BurntSushi/ripgrep) contain a single executable named
+//! `rg`, so it's installed as `rg`. A file counts as executable if its mode includes an executable
+//! bit or if it's an ELF, Mach-O, or PE binary. On Windows, it must have an extension like `.exe`
+//! or `.bat` instead. If the archive contains more than one executable, `ubi` returns an error that
+//! lists them.
+//!
+//! If the matching file is itself an archive or compressed file, like a zip file that contains
+//! `precious-linux-amd64.tar.gz`, then `ubi` looks inside that file for the executable, using the
+//! same rules. If nothing in an archive matches the executable's name, but the archive contains
+//! exactly one archive or compressed file, `ubi` will look inside that. Archives will only be
+//! looked into up to three levels deep, counting the downloaded file.
+//!
+//! To install from an encrypted zip or 7z archive, call [`UbiBuilder::archive_password`] or set the
+//! `UBI_ARCHIVE_PASSWORD` env var. The password is used for both single executables and
+//! [`UbiBuilder::extract_all`].
+//!
+//! By default, `ubi` extracts as much as a release asset contains. When installing untrusted
+//! releases, for example on a shared CI runner, you can guard against decompression bombs with
+//! [`UbiBuilder::max_extracted_bytes`], [`UbiBuilder::max_extracted_file_bytes`], and
+//! [`UbiBuilder::max_archive_entries`]. If a limit is exceeded, [`Ubi::install_binary`] returns an
+//! error, and nothing is installed.
+//!
+//! With [`UbiBuilder::extract_all`], every member of the archive is checked before it's extracted.
+//! Members with absolute paths or `..` components, links that point outside of the archive, device
+//! files, and setuid or setgid files are all rejected with an error that names the member.
+//!
+//! By default, the release asset is downloaded to a temp file before it's installed. If you call
+//! [`UbiBuilder::stream_download`], tarballs are extracted as they're downloaded instead, which
+//! saves disk space and time for large downloads. Streamed downloads are still checked against
+//! the asset's SHA-256 digest, but they are not retried or resumed.
+//!
+//! When a download that's saved to a temp file fails partway through, `ubi` keeps what it
+//! downloaded. The next attempt to download the same URL resumes it with a `Range` request, as long
+//! as the server sent an `ETag` or `Last-Modified` header the first time. These partial downloads
+//! are kept in a `ubi-partial-downloads` directory in the user's cache dir, which you can change
+//! with [`UbiBuilder::partial_download_dir`].
+//!
+//! Requests to the forge's API and downloads of release assets are retried when they fail with a
+//! transient error, like a connection reset, a timeout, or a 502, 503, or 504 response. By
+//! default, `ubi` retries up to 3 times with an exponential backoff, and honors a `Retry-After`
+//! header from the server. A download that fails partway through picks up where it left off. You
+//! can change this with [`UbiBuilder::max_retries`] and [`UbiBuilder::retry_delay`].
+//!
+//! When a request to the forge's API hits a rate limit, [`Ubi::install_binary`] returns a
+//! [`RateLimitError`]. Its message says when the limit resets and, if you didn't provide a token,
+//! which env var to set to one to get a higher limit. If you call
+//! [`UbiBuilder::wait_for_rate_limit`], `ubi` waits for the limit to reset instead, as long as it
+//! resets soon enough.
+//!
+//! To show the progress of the download, pass a hook to [`UbiBuilder::download_progress_hook`].
+//! It's called with a [`DownloadProgress`] as each chunk of the release asset arrives.
+//!
+//! If you call [`UbiBuilder::use_download_cache`], downloaded release assets are kept in a local
+//! cache, and installing the same asset from the same release again doesn't download it again. The
+//! cache is keyed by forge, project, release tag, and asset name, plus the asset's digest when the
+//! forge provides one. A SHA-256 digest is also checked when an asset is cached and each time it's
+//! installed from the cache. Use [`DownloadCache`] to see how much space the cache uses and to
+//! remove assets that haven't been used in a while.
+//!
+//! ## Launchers
+//!
+//! Some release artifacts, like `.jar`, `.py`, and `.pyz` files, need an interpreter to run. By
+//! default, these are installed like any other executable, keeping their extension. If you call
+//! [`UbiBuilder::install_launchers`], `ubi` instead installs them in a lib directory and installs a
+//! launcher in the install directory that runs them with `java -jar` or `python3`. On Windows, the
+//! launcher is a `.cmd` file and Python files are run with `python`.
+//!
+//! Running an AppImage requires FUSE, which isn't available in most containers. If you call
+//! [`UbiBuilder::extract_appimages`], `ubi` extracts the SquashFS filesystem embedded in an AppImage
+//! into a directory in the lib directory instead, and installs a launcher that runs its `AppRun`
+//! file.
+//!
+//! ## Checking the Installed Executable
+//!
+//! Once the executable is installed, if it's an ELF, Mach-O, or PE binary, `ubi` reads its header
+//! to check that it was built for the OS, CPU architecture, bitness, and endianness of the platform
+//! it was installed for. A Mach-O universal binary passes if any of its architectures match. Some
+//! architecture mismatches are allowed when the OS can run the binary anyway, like x86 binaries on
+//! x86-64 Linux or x86-64 binaries on ARM64 macOS. If the binary can't run on the platform, it is
+//! deleted and [`Ubi::install_binary`] returns an error. You can call
+//! [`UbiBuilder::allow_platform_mismatch`] to log a warning and keep the binary instead.
+//!
+//! If you call [`UbiBuilder::smoke_test`], `ubi` also runs the installed executable, by default
+//! with `--version`, and checks that it exits successfully before a timeout. This is only done when
+//! installing for the platform `ubi` is running on. You can also have it check that the output
+//! contains the version from the release's tag. If the smoke test fails, the new executable is
+//! removed, any executable it replaced is restored, and [`Ubi::install_binary`] returns an error.
+//! To make this possible, `ubi` always extracts the executable into a temporary directory and checks
+//! it there before copying it into the install directory.
+//!
+//! ## Post-Install Hooks
+//!
+//! You can call [`UbiBuilder::post_install_hook`] to run code after a successful install, for
+//! example to generate shell completions or to sign the executable. Each hook is passed a
+//! [`PostInstall`] with the installed path, the release tag, and the project name. If a hook fails,
+//! [`Ubi::install_binary`] returns a [`PostInstallHookError`], so you can tell this apart from a
+//! failed install.
 //!
 //! ## Features
 //!
@@ -151,24 +296,50 @@
 //!
 #![doc = document_features::document_features!()]
 
+mod appimage;
 mod arch;
 mod archive;
+mod binary;
 mod builder;
+mod cache;
+mod cpio;
+mod deb;
+mod digest;
 mod extension;
 mod forge;
 mod forgejo;
 mod github;
 mod gitlab;
 mod installer;
+mod launcher;
+mod limits;
+mod lzip;
+mod magic;
 mod os;
+mod partial_download;
 mod picker;
+mod post_install;
+mod progress;
+mod rate_limit;
+mod retry;
+mod rpm;
+mod safety;
+mod smoke_test;
 #[cfg(test)]
 mod test;
 #[cfg(test)]
 mod test_log;
 mod ubi;
 
-pub use crate::{builder::UbiBuilder, forge::ForgeType, ubi::Ubi};
+pub use crate::{
+    builder::UbiBuilder,
+    cache::{CacheStats, DownloadCache},
+    forge::ForgeType,
+    post_install::{PostInstall, PostInstallHookError},
+    progress::DownloadProgress,
+    rate_limit::RateLimitError,
+    ubi::Ubi,
+};
 
 // The version of the `ubi` crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,226 @@
+// Limits on how much `ubi` will extract from a downloaded file. These protect against decompression
+// bombs, which are small files that expand into an enormous amount of data, and against archives
+// with an enormous number of entries.
+use std::{
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ExtractionLimits {
+    // The most bytes we will extract in total, across all files and nested archives.
+    pub(crate) total_bytes: Option<u64>,
+    // The most bytes we will extract for any one file.
+    pub(crate) file_bytes: Option<u64>,
+    // The most archive entries we will look at, across all nested archives.
+    pub(crate) entries: Option<u64>,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum ExtractionLimitError {
+    #[error(
+        "{} is larger than the limit of {max} bytes for a single extracted file",
+        path.display()
+    )]
+    FileTooLarge { path: PathBuf, max: u64 },
+    #[error(
+        "extracting {} goes over the limit of {max} bytes for all extracted files",
+        path.display()
+    )]
+    TotalTooLarge { path: PathBuf, max: u64 },
+    #[error("the archive contains more than the limit of {max} entries")]
+    TooManyEntries { max: u64 },
+}
+
+// Tracks how much has been extracted so far. Each install needs its own budget. The counts are
+// atomic so that an installer can be shared with the thread that extracts a streamed download.
+#[derive(Debug, Default)]
+pub(crate) struct ExtractionBudget {
+    pub(crate) limits: ExtractionLimits,
+    extracted_bytes: AtomicU64,
+    extracted_entries: AtomicU64,
+}
+
+// A clone starts with nothing extracted, since it's used for a different install.
+impl Clone for ExtractionBudget {
+    fn clone(&self) -> Self {
+        Self::new(self.limits)
+    }
+}
+
+impl ExtractionBudget {
+    pub(crate) fn new(limits: ExtractionLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    // Counts one more archive entry.
+    pub(crate) fn add_entry(&self) -> Result<(), ExtractionLimitError> {
+        let entries = self.extracted_entries.load(Ordering::Relaxed) + 1;
+        if let Some(max) = self.limits.entries.filter(|max| entries > *max) {
+            return Err(ExtractionLimitError::TooManyEntries { max });
+        }
+        self.extracted_entries.store(entries, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // Checks that a file of `size` bytes fits in the budget, without counting it. This lets us
+    // reject a file before reading it into memory.
+    pub(crate) fn check_file(&self, path: &Path, size: u64) -> Result<(), ExtractionLimitError> {
+        self.total_after(path, size, size).map(|_| ())
+    }
+
+    // Counts a file of `size` bytes. This should only be used when the archive format guarantees
+    // that an entry's content is no larger than the size in its header. Otherwise, use `reader`.
+    pub(crate) fn add_file(&self, path: &Path, size: u64) -> Result<(), ExtractionLimitError> {
+        self.extracted_bytes
+            .store(self.total_after(path, size, size)?, Ordering::Relaxed);
+        Ok(())
+    }
+
+    // Returns a reader that counts the bytes read from `reader` as the content of the file at
+    // `path`. Once a limit is exceeded, reading returns an error.
+    pub(crate) fn reader<R: Read>(&self, path: &Path, reader: R) -> LimitedReader<'_, R> {
+        LimitedReader {
+            budget: self,
+            path: path.to_path_buf(),
+            reader,
+            file_bytes: 0,
+        }
+    }
+
+    // Returns the total number of bytes extracted after adding `added` bytes to the file at `path`,
+    // which brings that file's size to `file_bytes`.
+    fn total_after(
+        &self,
+        path: &Path,
+        file_bytes: u64,
+        added: u64,
+    ) -> Result<u64, ExtractionLimitError> {
+        if let Some(max) = self.limits.file_bytes.filter(|max| file_bytes > *max) {
+            return Err(ExtractionLimitError::FileTooLarge {
+                path: path.to_path_buf(),
+                max,
+            });
+        }
+        let total = self
+            .extracted_bytes
+            .load(Ordering::Relaxed)
+            .saturating_add(added);
+        if let Some(max) = self.limits.total_bytes.filter(|max| total > *max) {
+            return Err(ExtractionLimitError::TotalTooLarge {
+                path: path.to_path_buf(),
+                max,
+            });
+        }
+        Ok(total)
+    }
+}
+
+pub(crate) struct LimitedReader<'a, R> {
+    budget: &'a ExtractionBudget,
+    path: PathBuf,
+    reader: R,
+    file_bytes: u64,
+}
+
+impl<R: Read> Read for LimitedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.file_bytes += n as u64;
+        let total = self
+            .budget
+            .total_after(&self.path, self.file_bytes, n as u64)
+            .map_err(io::Error::other)?;
+        self.budget.extracted_bytes.store(total, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::no_limits(ExtractionLimits::default(), None)]
+    #[case::under_limits(
+        ExtractionLimits {
+            total_bytes: Some(10),
+            file_bytes: Some(6),
+            entries: None,
+        },
+        None
+    )]
+    #[case::file_too_large(
+        ExtractionLimits {
+            file_bytes: Some(5),
+            ..Default::default()
+        },
+        Some("b is larger than the limit of 5 bytes for a single extracted file")
+    )]
+    #[case::total_too_large(
+        ExtractionLimits {
+            total_bytes: Some(9),
+            ..Default::default()
+        },
+        Some("extracting b goes over the limit of 9 bytes for all extracted files")
+    )]
+    fn reader(#[case] limits: ExtractionLimits, #[case] expect_err: Option<&str>) {
+        let budget = ExtractionBudget::new(limits);
+        let mut out = vec![];
+        budget
+            .reader(Path::new("a"), &b"1234"[..])
+            .read_to_end(&mut out)
+            .unwrap();
+
+        let res = budget
+            .reader(Path::new("b"), &b"123456"[..])
+            .read_to_end(&mut out);
+        match expect_err {
+            None => {
+                res.unwrap();
+                assert_eq!(out, b"1234123456");
+            }
+            Some(msg) => assert_eq!(res.unwrap_err().to_string(), msg),
+        }
+    }
+
+    #[test]
+    fn add_and_check_file() {
+        let budget = ExtractionBudget::new(ExtractionLimits {
+            total_bytes: Some(10),
+            ..Default::default()
+        });
+
+        budget.check_file(Path::new("a"), 10).unwrap();
+        budget.check_file(Path::new("a"), 10).unwrap();
+        assert!(budget.check_file(Path::new("a"), 11).is_err());
+
+        budget.add_file(Path::new("a"), 6).unwrap();
+        assert_eq!(
+            budget.add_file(Path::new("b"), 6).unwrap_err().to_string(),
+            "extracting b goes over the limit of 10 bytes for all extracted files",
+        );
+        budget.add_file(Path::new("b"), 4).unwrap();
+    }
+
+    #[test]
+    fn add_entry() {
+        let budget = ExtractionBudget::new(ExtractionLimits {
+            entries: Some(2),
+            ..Default::default()
+        });
+
+        budget.add_entry().unwrap();
+        budget.add_entry().unwrap();
+        assert_eq!(
+            budget.add_entry().unwrap_err().to_string(),
+            "the archive contains more than the limit of 2 entries",
+        );
+    }
+}
@@ -0,0 +1,141 @@
+// An lzip file contains one or more members, each of which is a raw LZMA stream with a 6 byte header
+// and a 20 byte trailer. liblzma can decode the LZMA stream, but the version bundled with `xz2`
+// doesn't know about the lzip container. So we turn the lzip header into the header used by the
+// legacy `.lzma` format and let liblzma take it from there, then check the trailer ourselves.
+//
+// Only the first member is decoded. Tools like `lzip` only create multiple members when asked to
+// split their output into volumes. See https://www.nongnu.org/lzip/manual/lzip_manual.html#File-format
+// for details.
+use anyhow::{anyhow, Context, Result};
+use flate2::Crc;
+use std::io::{self, BufRead, BufReader, Read};
+use xz2::stream::{Action, Status, Stream};
+
+const LZIP_MAGIC: &[u8; 4] = b"LZIP";
+const LZIP_HEADER_LEN: usize = 6;
+const LZIP_TRAILER_LEN: usize = 20;
+
+// The lzip format always uses these LZMA parameters: lc=3, lp=0, pb=2.
+const LZMA_PROPERTIES: u8 = 0x5d;
+// An lzip stream always ends with an end of stream marker, so we tell liblzma that we don't know
+// the uncompressed size.
+const LZMA_UNKNOWN_SIZE: u64 = u64::MAX;
+
+struct LzipDecoder<R: BufRead> {
+    reader: R,
+    stream: Stream,
+    crc: Crc,
+    done: bool,
+}
+
+// Returns a reader that decompresses the lzip data in `reader`.
+pub(crate) fn decoder(mut reader: impl Read + 'static) -> Result<Box<dyn Read>> {
+    let mut header = [0u8; LZIP_HEADER_LEN];
+    reader
+        .read_exact(&mut header)
+        .context("failed to read lzip header")?;
+    if &header[0..4] != LZIP_MAGIC {
+        return Err(anyhow!(
+            "this is not a valid lzip file, it does not start with the lzip magic string"
+        ));
+    }
+    if header[4] != 1 {
+        return Err(anyhow!("unsupported lzip version {}", header[4]));
+    }
+
+    let mut lzma_header = vec![LZMA_PROPERTIES];
+    lzma_header.extend(dictionary_size(header[5]).to_le_bytes());
+    lzma_header.extend(LZMA_UNKNOWN_SIZE.to_le_bytes());
+
+    Ok(Box::new(LzipDecoder {
+        reader: BufReader::new(io::Cursor::new(lzma_header).chain(reader)),
+        stream: Stream::new_lzma_decoder(u64::MAX).context("failed to create an lzma decoder")?,
+        crc: Crc::new(),
+        done: false,
+    }))
+}
+
+// The low 5 bits of the dictionary size byte are the base 2 log of a size. The high 3 bits are the
+// number of sixteenths of that size to subtract from it.
+fn dictionary_size(byte: u8) -> u32 {
+    let base = 1u32 << (byte & 0x1f);
+    base - (base / 16) * u32::from(byte >> 5)
+}
+
+impl<R: BufRead> Read for LzipDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            let input = self.reader.fill_buf()?;
+            let eof = input.is_empty();
+            let total_in = self.stream.total_in();
+            let total_out = self.stream.total_out();
+            let status = self
+                .stream
+                .process(input, buf, Action::Run)
+                .map_err(io::Error::other)?;
+            let consumed = usize::try_from(self.stream.total_in() - total_in)
+                .expect("consumed more bytes than were in the input buffer");
+            let read = usize::try_from(self.stream.total_out() - total_out)
+                .expect("wrote more bytes than were in the output buffer");
+            self.reader.consume(consumed);
+            self.crc.update(&buf[..read]);
+
+            if status == Status::StreamEnd {
+                self.done = true;
+                self.check_trailer()?;
+                return Ok(read);
+            }
+            if read > 0 {
+                return Ok(read);
+            }
+            if eof {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "lzip stream ended before the end of stream marker",
+                ));
+            }
+        }
+    }
+}
+
+impl<R: BufRead> LzipDecoder<R> {
+    fn check_trailer(&mut self) -> io::Result<()> {
+        let mut trailer = [0u8; LZIP_TRAILER_LEN];
+        self.reader.read_exact(&mut trailer)?;
+
+        let crc = u32::from_le_bytes(trailer[0..4].try_into().unwrap());
+        if crc != self.crc.sum() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "lzip data does not match the CRC in the lzip trailer",
+            ));
+        }
+        let size = u64::from_le_bytes(trailer[4..12].try_into().unwrap());
+        if size != self.stream.total_out() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "lzip data does not match the size in the lzip trailer",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::min(0x0c, 4096)]
+    #[case::one_mib(0x14, 1 << 20)]
+    #[case::eight_mib(0x17, 8 << 20)]
+    #[case::fifteen_sixteenths(0x37, (8 << 20) - (8 << 20) / 16)]
+    fn dictionary_size(#[case] byte: u8, #[case] expect: u32) {
+        assert_eq!(super::dictionary_size(byte), expect);
+    }
+}
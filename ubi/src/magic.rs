@@ -0,0 +1,188 @@
+// Release assets are not always named for what they contain. Some projects release a gzipped
+// executable or tarball with no extension at all, and some name a tarball `.zip`. This module looks
+// at the first few bytes of a file to figure out what it actually is.
+use crate::{extension::Extension, installer::decompressing_reader};
+use anyhow::{Context, Result};
+use log::debug;
+use std::{fs::File, io::Read, path::Path};
+
+// A tar header is 512 bytes, and this is enough to see all of the magic bytes we look for.
+const SNIFF_LEN: u64 = 512;
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_MAGIC: &[u8] = b"ustar";
+
+#[derive(Debug, PartialEq, Eq)]
+enum FileType {
+    Bzip2,
+    Gzip,
+    SevenZip,
+    Tar,
+    Xz,
+    Zip,
+    Zstd,
+    // This covers ELF, Mach-O, and PE executables.
+    Executable,
+}
+
+// Returns the extension that best describes the file at `path`. When the file's content is
+// recognized and does not agree with its name, the content wins. Otherwise, this is the same as
+// `Extension::from_path`.
+pub(crate) fn detect_extension(path: &Path) -> Result<Option<Extension>> {
+    let from_name = Extension::from_path(path);
+    let Some(file_type) = sniff_file_type(path)? else {
+        return from_name;
+    };
+
+    if let Ok(ext) = &from_name {
+        if name_matches_file_type(ext.as_ref(), &file_type) {
+            return from_name;
+        }
+    }
+
+    let from_content = extension_for_file_type(path, &file_type)?;
+    debug!(
+        "the content of {} looks like {file_type:?} data, which does not match its name, so it will be treated as {}",
+        path.display(),
+        from_content.as_ref().map_or("an executable", Extension::extension),
+    );
+    Ok(from_content)
+}
+
+fn sniff_file_type(path: &Path) -> Result<Option<FileType>> {
+    let mut header = vec![];
+    File::open(path)
+        .and_then(|f| f.take(SNIFF_LEN).read_to_end(&mut header))
+        .with_context(|| format!("failed to read the start of {}", path.display()))?;
+    Ok(file_type_from_header(&header))
+}
+
+fn file_type_from_header(header: &[u8]) -> Option<FileType> {
+    let file_type = if header.starts_with(&[0x1f, 0x8b]) {
+        FileType::Gzip
+    } else if header.starts_with(b"BZh") {
+        FileType::Bzip2
+    } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        FileType::Xz
+    } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        FileType::Zstd
+    } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        FileType::Zip
+    } else if header.starts_with(&[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c]) {
+        FileType::SevenZip
+    } else if is_tar_header(header) {
+        FileType::Tar
+    } else if header.starts_with(b"\x7fELF")
+        // These are the 32-bit and 64-bit Mach-O magic numbers in both byte orders, followed by the
+        // magic number for a universal binary.
+        || header.starts_with(&[0xfe, 0xed, 0xfa, 0xce])
+        || header.starts_with(&[0xfe, 0xed, 0xfa, 0xcf])
+        || header.starts_with(&[0xce, 0xfa, 0xed, 0xfe])
+        || header.starts_with(&[0xcf, 0xfa, 0xed, 0xfe])
+        || header.starts_with(&[0xca, 0xfe, 0xba, 0xbe])
+        || header.starts_with(b"MZ")
+    {
+        FileType::Executable
+    } else {
+        return None;
+    };
+    Some(file_type)
+}
+
+fn is_tar_header(header: &[u8]) -> bool {
+    header
+        .get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + TAR_MAGIC.len())
+        .is_some_and(|magic| magic == TAR_MAGIC)
+}
+
+fn name_matches_file_type(ext: Option<&Extension>, file_type: &FileType) -> bool {
+    let Some(ext) = ext else {
+        return *file_type == FileType::Executable;
+    };
+    match file_type {
+        FileType::Bzip2 => matches!(
+            ext,
+            Extension::Bz | Extension::Bz2 | Extension::TarBz | Extension::TarBz2 | Extension::Tbz
+        ),
+        FileType::Gzip => matches!(
+            ext,
+            Extension::Apk | Extension::Gz | Extension::TarGz | Extension::Tgz
+        ),
+        FileType::SevenZip => matches!(ext, Extension::SevenZip),
+        FileType::Tar => matches!(ext, Extension::Tar),
+        FileType::Xz => matches!(ext, Extension::TarXz | Extension::Txz | Extension::Xz),
+        // Jar and pyz files are zip files.
+        FileType::Zip => matches!(ext, Extension::Jar | Extension::Pyz | Extension::Zip),
+        FileType::Zstd => matches!(
+            ext,
+            Extension::PkgTarZst | Extension::TarZst | Extension::Tzst | Extension::Zst
+        ),
+        FileType::Executable => !(ext.is_archive() || ext.is_compressed()),
+    }
+}
+
+// For compressed files, we look at the start of the decompressed data to see if it's a tarball.
+fn extension_for_file_type(path: &Path, file_type: &FileType) -> Result<Option<Extension>> {
+    let (compression, tarball, compressed) = match file_type {
+        FileType::Bzip2 => ("bz2", Extension::TarBz2, Extension::Bz2),
+        FileType::Gzip => ("gz", Extension::TarGz, Extension::Gz),
+        FileType::Xz => ("xz", Extension::TarXz, Extension::Xz),
+        FileType::Zstd => ("zst", Extension::TarZst, Extension::Zst),
+        FileType::SevenZip => return Ok(Some(Extension::SevenZip)),
+        FileType::Tar => return Ok(Some(Extension::Tar)),
+        FileType::Zip => return Ok(Some(Extension::Zip)),
+        FileType::Executable => return Ok(None),
+    };
+
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut header = vec![];
+    // If we can't decompress the start of the file, we'll find out why when we try to install it.
+    let is_tarball = decompressing_reader(compression, file)
+        .and_then(|r| Ok(r.take(SNIFF_LEN).read_to_end(&mut header)?))
+        .map_or_else(
+            |e| {
+                debug!("failed to decompress the start of {}: {e}", path.display());
+                false
+            },
+            |_| is_tar_header(&header),
+        );
+
+    Ok(Some(if is_tarball { tarball } else { compressed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::tarball("test-data/project.tar", Some(Extension::Tar))]
+    #[case::gzipped_tarball("test-data/project.tar.gz", Some(Extension::TarGz))]
+    #[case::gzipped_tarball_with_no_extension(
+        "test-data/project-gzipped-tarball",
+        Some(Extension::TarGz)
+    )]
+    #[case::gzipped_exe_with_no_extension("test-data/project-gzipped-exe", Some(Extension::Gz))]
+    #[case::tarball_named_zip("test-data/project-tarball-named.zip", Some(Extension::TarGz))]
+    #[case::jar_is_zip("test-data/project.jar", Some(Extension::Jar))]
+    #[case::pyz_is_zip("test-data/project.pyz", Some(Extension::Pyz))]
+    #[case::unrecognized_content("test-data/project.sh", Some(Extension::Sh))]
+    #[case::unrecognized_content_no_extension("test-data/project", None)]
+    fn detect_extension(#[case] path: &str, #[case] expect: Option<Extension>) -> Result<()> {
+        crate::test_log::init_logging();
+
+        assert_eq!(super::detect_extension(Path::new(path))?, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::elf(b"\x7fELF\x02\x01\x01", Some(FileType::Executable))]
+    #[case::macho_64(&[0xcf, 0xfa, 0xed, 0xfe, 0x07], Some(FileType::Executable))]
+    #[case::pe(b"MZ\x90\x00", Some(FileType::Executable))]
+    #[case::seven_zip(&[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c, 0x00], Some(FileType::SevenZip))]
+    #[case::zip(b"PK\x03\x04\x14\x00", Some(FileType::Zip))]
+    #[case::script(b"#!/bin/sh\n", None)]
+    #[case::empty(b"", None)]
+    fn file_type_from_header(#[case] header: &[u8], #[case] expect: Option<FileType>) {
+        assert_eq!(super::file_type_from_header(header), expect);
+    }
+}
@@ -1,5 +1,6 @@
 use itertools::Itertools;
 use lazy_regex::{regex, Lazy};
+use platforms::OS;
 use regex::Regex;
 use std::sync::LazyLock;
 
@@ -40,6 +41,36 @@ pub(crate) fn windows_re() -> &'static Lazy<Regex> {
     regex!(r"(?i:(?:\b|_)win(?:32|64|dows)?(?:\b|_))")
 }
 
+// Returns the regex that matches names for the given OS, or `None` if this is an OS we don't know how
+// to match.
+pub(crate) fn os_re(os: OS) -> Option<&'static Lazy<Regex>> {
+    #[allow(clippy::wildcard_enum_match_arm)]
+    Some(match os {
+        // The strings the regexes match are those supported by Rust
+        // (based on the platforms crate) and Go (based on
+        // https://gist.github.com/asukakenji/f15ba7e588ac42795f421b48b8aede63).
+        //
+        // There are some OS variants in the platforms package that don't
+        // correspond to any target supported by rustup. Those are
+        // commented out here.
+        //
+        //OS::Dragonfly => regex!(r"(?i:(?:\b|_)dragonfly(?:\b|_))"),
+        OS::Android => android_re(),
+        OS::FreeBSD => freebsd_re(),
+        OS::Fuchsia => fuchsia(),
+        //OS::Haiku => regex!(r"(?i:(?:\b|_)haiku(?:\b|_))"),
+        OS::IllumOS => illumos_re(),
+        OS::Linux => linux_re(),
+        OS::MacOS => macos_re(),
+        OS::NetBSD => netbsd_re(),
+        //OS::OpenBSD => regex!(r"(?i:(?:\b|_)openbsd(?:\b|_))"),
+        OS::Solaris => solaris_re(),
+        //OS::VxWorks => regex!(r"(?i:(?:\b|_)vxworks(?:\b|_))"),
+        OS::Windows => windows_re(),
+        _ => return None,
+    })
+}
+
 pub(crate) static ALL_OSES_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(
         &[
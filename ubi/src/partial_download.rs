@@ -0,0 +1,370 @@
+// When a download is interrupted, we keep what we got so that the next attempt can pick up where it
+// left off. Each asset URL gets its own directory under the partial downloads dir. That directory
+// holds the partial file and the validator (an `ETag` or `Last-Modified` value) from the response
+// that started it. We only resume when we have a validator, since we send it in an `If-Range`
+// header. If the asset changed since we started, the server sends the whole thing again.
+//
+// Each URL also gets a lock file next to its directory, which we hold while downloading, so two
+// `ubi` processes never write to the same partial download. The lock files are never removed, since
+// removing one while another process waits on it would let a third process lock a new one.
+use crate::cache;
+use anyhow::{anyhow, Context, Result};
+use fs4::FileExt;
+use log::{debug, info};
+use reqwest::header::{HeaderMap, CONTENT_RANGE, ETAG, LAST_MODIFIED};
+use std::{
+    env,
+    fs::{self, DirBuilder, File, OpenOptions},
+    hash::{DefaultHasher, Hash, Hasher},
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+const VALIDATOR_FILE: &str = "validator";
+
+// Returns the directory that partial downloads are kept in when the user doesn't pick one. This is
+// in the user's cache dir rather than the temp dir, which other users can write to. We only fall
+// back to the temp dir if we can't find a cache dir.
+pub(crate) fn default_dir() -> PathBuf {
+    cache::user_cache_dir()
+        .unwrap_or_else(env::temp_dir)
+        .join("ubi-partial-downloads")
+}
+
+#[derive(Debug)]
+pub(crate) struct PartialDownload {
+    dir: PathBuf,
+    file_path: PathBuf,
+    // This is locked for as long as the download is in progress.
+    _lock: File,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Resume {
+    pub(crate) offset: u64,
+    pub(crate) validator: String,
+}
+
+impl PartialDownload {
+    // This creates `root` if needed, and waits for any other `ubi` process that's downloading the
+    // same URL to finish.
+    pub(crate) fn new(root: &Path, url: &Url, name: &str) -> Result<Self> {
+        prepare_dir(root)?;
+
+        // The hash isn't guaranteed to be the same across Rust releases. If it changes, we just
+        // won't find a partial download from an older `ubi`.
+        let mut hasher = DefaultHasher::new();
+        url.as_str().hash(&mut hasher);
+        let key = format!("{:016x}", hasher.finish());
+        let lock = lock(&root.join(format!("{key}.lock")))?;
+        let dir = root.join(key);
+        let file_path = dir.join(name);
+        Ok(PartialDownload {
+            dir,
+            file_path,
+            _lock: lock,
+        })
+    }
+
+    // Returns where to resume this download from, if there's a partial download with a validator.
+    pub(crate) fn resume(&self) -> Option<Resume> {
+        let offset = fs::metadata(&self.file_path).ok()?.len();
+        if offset == 0 {
+            return None;
+        }
+        let validator = fs::read_to_string(self.dir.join(VALIDATOR_FILE)).ok()?;
+        Some(Resume { offset, validator })
+    }
+
+    // Starts the download over, returning the file to write the whole asset to.
+    pub(crate) fn start(&self, validator: Option<&str>) -> Result<File> {
+        fs::create_dir_all(&self.dir).with_context(|| {
+            format!(
+                "failed to create directory for partial download at {}",
+                self.dir.display()
+            )
+        })?;
+
+        let validator_path = self.dir.join(VALIDATOR_FILE);
+        // We remove the old validator first, so that a new partial file is never paired with a
+        // validator for an older version of the asset.
+        if validator_path.exists() {
+            fs::remove_file(&validator_path).with_context(|| {
+                format!(
+                    "failed to remove partial download validator at {}",
+                    validator_path.display()
+                )
+            })?;
+        }
+        let file = File::create(&self.file_path).with_context(|| {
+            format!(
+                "failed to create file at {} for downloaded asset",
+                self.file_path.display()
+            )
+        })?;
+        if let Some(validator) = validator {
+            fs::write(&validator_path, validator).with_context(|| {
+                format!(
+                    "failed to write partial download validator to {}",
+                    validator_path.display()
+                )
+            })?;
+        } else {
+            debug!("the response has no ETag or Last-Modified header, so this download cannot be resumed");
+        }
+
+        Ok(file)
+    }
+
+    // Returns the partial file, opened for appending the rest of the asset.
+    pub(crate) fn append(&self) -> Result<File> {
+        OpenOptions::new()
+            .append(true)
+            .open(&self.file_path)
+            .with_context(|| {
+                format!(
+                    "failed to open partial download at {} to resume it",
+                    self.file_path.display()
+                )
+            })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.file_path
+    }
+
+    // Moves the completed download to `into` and removes the partial download's directory.
+    pub(crate) fn finish(self, into: &Path) -> Result<()> {
+        fs::rename(&self.file_path, into).with_context(|| {
+            format!(
+                "failed to move completed download from {} to {}",
+                self.file_path.display(),
+                into.display(),
+            )
+        })?;
+        self.discard()
+    }
+
+    pub(crate) fn discard(&self) -> Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        fs::remove_dir_all(&self.dir).with_context(|| {
+            format!(
+                "failed to remove partial download directory at {}",
+                self.dir.display()
+            )
+        })
+    }
+}
+
+// Creates the partial downloads dir so that only the current user can use it, and refuses to use a
+// dir that belongs to someone else, since they could read or replace what we download.
+pub(crate) fn prepare_dir(root: &Path) -> Result<()> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(target_family = "unix")]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(root).with_context(|| {
+        format!(
+            "failed to create partial downloads directory at {}",
+            root.display()
+        )
+    })?;
+
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let meta = fs::metadata(root).with_context(|| {
+            format!(
+                "failed to get metadata for partial downloads directory at {}",
+                root.display()
+            )
+        })?;
+        if meta.uid() != rustix::process::geteuid().as_raw() {
+            return Err(anyhow!(
+                "the partial downloads directory at {} belongs to another user, so ubi will not use it",
+                root.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn lock(path: &Path) -> Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .with_context(|| format!("failed to open partial download lock at {}", path.display()))?;
+    // We call these through the trait, since newer versions of Rust have `File` methods with the
+    // same names.
+    if FileExt::try_lock(&file).is_err() {
+        info!("waiting for another ubi process that is downloading the same asset");
+        FileExt::lock(&file)
+            .with_context(|| format!("failed to lock partial download at {}", path.display()))?;
+    }
+    Ok(file)
+}
+
+// Returns the value to send in an `If-Range` header when resuming a download of this response. A
+// weak `ETag` can't be used for that, so we fall back to `Last-Modified` when that's all we have.
+pub(crate) fn validator(headers: &HeaderMap) -> Option<String> {
+    if let Some(etag) = headers.get(ETAG).and_then(|v| v.to_str().ok()) {
+        if !etag.starts_with("W/") {
+            return Some(etag.to_string());
+        }
+    }
+    headers
+        .get(LAST_MODIFIED)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+// Returns the first byte of the range in a `206 Partial Content` response, which looks like
+// `Content-Range: bytes 100-199/200`.
+pub(crate) fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+    let range = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (start, _) = range.strip_prefix("bytes ")?.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    #[rstest]
+    #[case::strong_etag(
+        Some(r#""abc""#),
+        Some("Tue, 01 Apr 2025 00:00:00 GMT"),
+        Some(r#""abc""#)
+    )]
+    #[case::weak_etag(
+        Some(r#"W/"abc""#),
+        Some("Tue, 01 Apr 2025 00:00:00 GMT"),
+        Some("Tue, 01 Apr 2025 00:00:00 GMT")
+    )]
+    #[case::weak_etag_only(Some(r#"W/"abc""#), None, None)]
+    #[case::last_modified_only(
+        None,
+        Some("Tue, 01 Apr 2025 00:00:00 GMT"),
+        Some("Tue, 01 Apr 2025 00:00:00 GMT")
+    )]
+    #[case::neither(None, None, None)]
+    fn validator(
+        #[case] etag: Option<&'static str>,
+        #[case] last_modified: Option<&'static str>,
+        #[case] expect: Option<&str>,
+    ) {
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = etag {
+            headers.insert(ETAG, HeaderValue::from_static(etag));
+        }
+        if let Some(last_modified) = last_modified {
+            headers.insert(LAST_MODIFIED, HeaderValue::from_static(last_modified));
+        }
+        assert_eq!(super::validator(&headers).as_deref(), expect);
+    }
+
+    #[rstest]
+    #[case::range("bytes 100-199/200", Some(100))]
+    #[case::unknown_length("bytes 0-99/*", Some(0))]
+    #[case::unsatisfied("bytes */200", None)]
+    #[case::other_unit("items 1-2/3", None)]
+    fn content_range_start(#[case] content_range: &'static str, #[case] expect: Option<u64>) {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_RANGE, HeaderValue::from_static(content_range));
+        assert_eq!(super::content_range_start(&headers), expect);
+    }
+
+    #[test]
+    fn resume() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let url = Url::parse("https://example.com/project.tar.gz")?;
+        let partial = PartialDownload::new(td.path(), &url, "project.tar.gz")?;
+        assert_eq!(partial.resume(), None);
+
+        partial.start(Some(r#""abc""#))?;
+        assert_eq!(partial.resume(), None, "an empty file is not resumed");
+
+        fs::write(partial.path(), "some bytes")?;
+        assert_eq!(
+            partial.resume(),
+            Some(Resume {
+                offset: 10,
+                validator: r#""abc""#.to_string(),
+            })
+        );
+
+        partial.start(None)?;
+        fs::write(partial.path(), "some bytes")?;
+        assert_eq!(
+            partial.resume(),
+            None,
+            "a download without a validator is not resumed"
+        );
+
+        let into = td.path().join("done");
+        partial.finish(&into)?;
+        assert_eq!(fs::read_to_string(&into)?, "some bytes");
+        assert!(
+            !td.path().join(partial_dir_name(&url)).exists(),
+            "the partial download's directory is removed"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn only_one_download_of_a_url_at_a_time() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let url = Url::parse("https://example.com/project.tar.gz")?;
+        let partial = PartialDownload::new(td.path(), &url, "project.tar.gz")?;
+
+        let lock_path = td.path().join(format!("{}.lock", partial_dir_name(&url)));
+        let other = File::open(&lock_path)?;
+        assert!(
+            FileExt::try_lock(&other).is_err(),
+            "the lock is held while the download is in progress"
+        );
+        drop(partial);
+        assert!(
+            FileExt::try_lock(&other).is_ok(),
+            "the lock is released when the download is done"
+        );
+
+        Ok(())
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn root_is_only_for_the_current_user() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        crate::test_log::init_logging();
+
+        let td = tempdir()?;
+        let root = td.path().join("partial");
+        let url = Url::parse("https://example.com/project.tar.gz")?;
+        PartialDownload::new(&root, &url, "project.tar.gz")?;
+        assert_eq!(fs::metadata(&root)?.permissions().mode() & 0o777, 0o700);
+
+        Ok(())
+    }
+
+    fn partial_dir_name(url: &Url) -> String {
+        let mut hasher = DefaultHasher::new();
+        url.as_str().hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
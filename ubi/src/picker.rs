@@ -2,22 +2,17 @@ use std::path::Path;
 
 use crate::{
     arch::{
-        aarch64_re, arm_re, cpu_64_bit_re, macos_aarch64_and_x86_64_re, macos_aarch64_only_re,
-        mips64_re, mips64le_re, mips_re, mipsle_re, ppc32_re, ppc64_re, ppc64le_re, riscv64_re,
-        s390x_re, sparc64_re, x86_32_re, x86_64_re, ALL_ARCHES_RE,
+        arch_re, cpu_64_bit_re, macos_aarch64_and_x86_64_re, macos_aarch64_only_re, ALL_ARCHES_RE,
     },
     extension::Extension,
-    os::{
-        android_re, freebsd_re, fuchsia, illumos_re, linux_re, macos_re, netbsd_re, solaris_re,
-        windows_re,
-    },
+    os::{android_re, os_re},
     ubi::Asset,
 };
 use anyhow::{anyhow, Context, Result};
 use itertools::Itertools;
 use lazy_regex::{regex, Lazy};
 use log::debug;
-use platforms::{Arch, Endian, Platform, OS};
+use platforms::{Arch, Platform, OS};
 use regex::Regex;
 
 #[derive(Debug)]
@@ -300,6 +295,9 @@ impl<'a> AssetPicker<'a> {
         // Apply --matching filter if there's multiple matches.
         let matches = self.maybe_filter_for_matching_string(matches)?;
 
+        let matches = Self::maybe_filter_out_linux_packages(matches);
+        let matches = self.maybe_filter_for_apk_on_musl(matches);
+
         // This comes before 64-bit filtering so that we pick assets with just "arm" in the name
         // (not "arm64") on macOS ARM over something with "x86-64" in the name.
         let (filtered, asset) = self.maybe_pick_asset_for_macos_arm(matches);
@@ -326,6 +324,56 @@ impl<'a> AssetPicker<'a> {
             .unwrap())
     }
 
+    // Many projects release distro packages alongside a plain archive with the same executable. We
+    // only want to install from a package when it's the only option, since the archive is what we
+    // would have picked before we supported packages.
+    fn maybe_filter_out_linux_packages(matches: Vec<Asset>) -> Vec<Asset> {
+        let (packages, others): (Vec<_>, Vec<_>) = matches.into_iter().partition(|a| {
+            Extension::from_path(Path::new(&a.name))
+                .ok()
+                .flatten()
+                .is_some_and(|e| e.is_linux_package())
+        });
+        if packages.is_empty() || others.is_empty() {
+            return packages.into_iter().chain(others).collect();
+        }
+
+        debug!(
+            "ignoring Linux packages in favor of other candidate assets: {:?}",
+            packages.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(),
+        );
+        others
+    }
+
+    // Alpine uses musl, so if the only candidates are Linux distro packages, an Alpine package is the
+    // best choice on a musl platform.
+    fn maybe_filter_for_apk_on_musl(&self, matches: Vec<Asset>) -> Vec<Asset> {
+        if !self.is_musl {
+            return matches;
+        }
+
+        let extensions = matches
+            .iter()
+            .map(|a| Extension::from_path(Path::new(&a.name)).ok().flatten())
+            .collect::<Vec<_>>();
+        if !extensions
+            .iter()
+            .all(|e| e.as_ref().is_some_and(Extension::is_linux_package))
+        {
+            return matches;
+        }
+        if !extensions.contains(&Some(Extension::Apk)) {
+            return matches;
+        }
+
+        debug!("all candidate assets are Linux packages, filtering for apk packages on this musl platform");
+        matches
+            .into_iter()
+            .zip(extensions)
+            .filter_map(|(a, e)| (e == Some(Extension::Apk)).then_some(a))
+            .collect()
+    }
+
     fn maybe_filter_for_64_bit_arch(&self, matches: Vec<Asset>) -> Vec<Asset> {
         if !matches!(
             self.platform.target_arch,
@@ -407,33 +455,9 @@ impl<'a> AssetPicker<'a> {
 
         // If new platforms are added, we _do_ want this `unreachable` to panic, since we need to
         // explicitly add support for new platforms.
-        #[allow(clippy::wildcard_enum_match_arm)]
-        match self.platform.target_os {
-            // The strings the regexes match are those supported by Rust
-            // (based on the platforms crate) and Go (based on
-            // https://gist.github.com/asukakenji/f15ba7e588ac42795f421b48b8aede63).
-            //
-            // There are some OS variants in the platforms package that don't
-            // correspond to any target supported by rustup. Those are
-            // commented out here.
-            //
-            //OS::Dragonfly => regex!(r"(?i:(?:\b|_)dragonfly(?:\b|_))"),
-            OS::Android => android_re(),
-            OS::FreeBSD => freebsd_re(),
-            OS::Fuchsia => fuchsia(),
-            //OS::Haiku => regex!(r"(?i:(?:\b|_)haiku(?:\b|_))"),
-            OS::IllumOS => illumos_re(),
-            OS::Linux => linux_re(),
-            OS::MacOS => macos_re(),
-            OS::NetBSD => netbsd_re(),
-            //OS::OpenBSD => regex!(r"(?i:(?:\b|_)openbsd(?:\b|_))"),
-            OS::Solaris => solaris_re(),
-            //OS::VxWorks => regex!(r"(?i:(?:\b|_)vxworks(?:\b|_))"),
-            OS::Windows => windows_re(),
-            _ => unreachable!(
-                "Cannot determine what type of compiled binary to use for this platform"
-            ),
-        }
+        os_re(self.platform.target_os).unwrap_or_else(|| {
+            unreachable!("Cannot determine what type of compiled binary to use for this platform")
+        })
     }
 
     fn arch_matcher(&self) -> &'static Lazy<Regex> {
@@ -443,29 +467,11 @@ impl<'a> AssetPicker<'a> {
             return macos_aarch64_and_x86_64_re();
         }
 
-        match (self.platform.target_arch, self.platform.target_endian) {
-            (Arch::AArch64, _) => aarch64_re(),
-            (Arch::Arm, _) => arm_re(),
-            (Arch::Mips, Endian::Little) => mipsle_re(),
-            (Arch::Mips, Endian::Big) => mips_re(),
-            (Arch::Mips64, Endian::Little) => mips64le_re(),
-            (Arch::Mips64, Endian::Big) => mips64_re(),
-            (Arch::PowerPc, _) => ppc32_re(),
-            (Arch::PowerPc64, Endian::Big) => ppc64_re(),
-            (Arch::PowerPc64, Endian::Little) => ppc64le_re(),
-            //(Arch::Riscv32, _) => regex!(r"(?i:(?:\b|_)riscv(?:32)?(?:\b|_))"),
-            (Arch::Riscv64, _) => riscv64_re(),
-            (Arch::S390X, _) => s390x_re(),
-            // Sparc is not supported by Go. 32-bit Sparc is not supported
-            // by Rust, AFAICT.
-            //(Arch::Sparc, _) => regex!(r"(?i:(?:\b|_)sparc(?:\b|_))"),
-            (Arch::Sparc64, _) => sparc64_re(),
-            (Arch::X86, _) => x86_32_re(),
-            (Arch::X86_64, _) => x86_64_re(),
-            _ => unreachable!(
+        arch_re(self.platform.target_arch, self.platform.target_endian).unwrap_or_else(|| {
+            unreachable!(
                 "Cannot determine what type of compiled binary to use for this CPU architecture"
-            ),
-        }
+            )
+        })
     }
 
     fn running_on_macos_arm(&self) -> bool {
@@ -650,6 +656,52 @@ mod test {
         None,
         1
     )]
+    #[case::x86_64_unknown_linux_musl_pick_the_apk_when_there_are_only_packages(
+        "x86_64-unknown-linux-musl",
+        // The "a" prefix makes sure the deb would be picked if we just sorted by name.
+        &["a-project_linux_amd64.deb", "project_linux_amd64.apk", "project_linux_amd64.rpm"],
+        None,
+        None,
+        1
+    )]
+    #[case::x86_64_unknown_linux_musl_pick_a_non_package_asset_over_the_apk(
+        "x86_64-unknown-linux-musl",
+        &["a-project-linux-amd64.tar.gz", "project_linux_amd64.apk"],
+        None,
+        None,
+        0
+    )]
+    #[case::x86_64_unknown_linux_musl_pick_the_tarball_over_goreleaser_packages(
+        "x86_64-unknown-linux-musl",
+        &[
+            "tool_1.0.0_linux_amd64.apk",
+            "tool_1.0.0_linux_amd64.deb",
+            "tool_1.0.0_linux_amd64.rpm",
+            "tool_1.0.0_linux_amd64.tar.gz",
+        ],
+        None,
+        None,
+        3
+    )]
+    #[case::x86_64_unknown_linux_gnu_pick_the_tarball_over_goreleaser_packages(
+        "x86_64-unknown-linux-gnu",
+        &[
+            "tool_1.0.0_linux_amd64.apk",
+            "tool_1.0.0_linux_amd64.deb",
+            "tool_1.0.0_linux_amd64.rpm",
+            "tool_1.0.0_linux_amd64.tar.gz",
+        ],
+        None,
+        None,
+        3
+    )]
+    #[case::x86_64_unknown_linux_gnu_does_not_prefer_the_apk(
+        "x86_64-unknown-linux-gnu",
+        &["a-project_linux_amd64.deb", "project_linux_amd64.apk"],
+        None,
+        None,
+        0
+    )]
     #[case::project_aarch64_unknown_linux_pick_the_non_Android_asset_when_not_on_Android(
         "aarch64-unknown-linux-gnu",
         &["project-aarch64-linux-android.tar.gz", "project-aarch64-unknown-linux.tar.gz"],
@@ -705,6 +757,8 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                size: None,
+                digest: None,
             })
             .collect::<Vec<_>>();
 
@@ -769,6 +823,8 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                size: None,
+                digest: None,
             })
             .collect::<Vec<_>>();
 
@@ -879,6 +935,8 @@ mod test {
             .map(|name| Asset {
                 name: (*name).to_string(),
                 url: url.clone(),
+                size: None,
+                digest: None,
             })
             .collect::<Vec<_>>();
 
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Information about a successful install, which is passed to each post-install hook.
+#[derive(Debug)]
+pub struct PostInstall {
+    path: PathBuf,
+    tag: Option<String>,
+    project: String,
+}
+
+impl PostInstall {
+    pub(crate) fn new(path: PathBuf, tag: Option<String>, project: String) -> Self {
+        Self { path, tag, project }
+    }
+
+    /// The path of the installed executable. When `extract_all` is set, this is the directory that
+    /// the archive was extracted into instead.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The tag of the release that was installed. This is `None` when installing from a URL, since
+    /// `ubi` never looks up the release in that case.
+    #[must_use]
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// The project that was installed, like `houseabsolute/precious`.
+    #[must_use]
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+}
+
+/// The error returned by [`Ubi::install_binary`](crate::Ubi::install_binary) when a post-install
+/// hook fails. When this is returned, the install itself succeeded, and the installed files are
+/// left in place.
+#[derive(Debug, Error)]
+#[error("post-install hook {index} failed for {}", path.display())]
+pub struct PostInstallHookError {
+    index: usize,
+    path: PathBuf,
+    #[source]
+    source: Box<dyn std::error::Error + Send + Sync + 'static>,
+}
+
+impl PostInstallHookError {
+    /// The 1-based position of the hook that failed, in the order the hooks were added.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The installed path that was passed to the hook.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+type HookFn<'a> = dyn Fn(&PostInstall) -> Result<()> + 'a;
+
+pub(crate) struct PostInstallHook<'a>(Box<HookFn<'a>>);
+
+impl<'a> PostInstallHook<'a> {
+    pub(crate) fn new(hook: impl Fn(&PostInstall) -> Result<()> + 'a) -> Self {
+        Self(Box::new(hook))
+    }
+}
+
+impl fmt::Debug for PostInstallHook<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PostInstallHook")
+    }
+}
+
+// Runs each hook in order, stopping at the first one that fails.
+pub(crate) fn run_hooks(hooks: &[PostInstallHook<'_>], info: &PostInstall) -> Result<()> {
+    for (i, hook) in hooks.iter().enumerate() {
+        (hook.0)(info).map_err(|e| PostInstallHookError {
+            index: i + 1,
+            path: info.path.clone(),
+            source: e.into(),
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::cell::RefCell;
+
+    #[test]
+    fn run_hooks_stops_at_first_failure() {
+        let ran = RefCell::new(vec![]);
+        let hooks = [
+            PostInstallHook::new(|info| {
+                ran.borrow_mut().push(info.project().to_string());
+                Ok(())
+            }),
+            PostInstallHook::new(|_| Err(anyhow!("exit status: 1"))),
+            PostInstallHook::new(|_| {
+                ran.borrow_mut().push(String::from("third"));
+                Ok(())
+            }),
+        ];
+        let info = PostInstall::new(
+            PathBuf::from("bin/precious"),
+            Some(String::from("v1.2.3")),
+            String::from("houseabsolute/precious"),
+        );
+
+        let err = run_hooks(&hooks, &info).unwrap_err();
+        let hook_err = err
+            .downcast_ref::<PostInstallHookError>()
+            .expect("error is a PostInstallHookError");
+        assert_eq!(hook_err.index(), 2);
+        assert_eq!(hook_err.path(), Path::new("bin/precious"));
+        assert_eq!(
+            format!("{err:#}"),
+            "post-install hook 2 failed for bin/precious: exit status: 1",
+        );
+        assert_eq!(*ran.borrow(), vec![String::from("houseabsolute/precious")]);
+    }
+}
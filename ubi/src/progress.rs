@@ -0,0 +1,210 @@
+use std::fmt;
+
+/// The progress of a release asset download, which is passed to the download progress hook.
+#[derive(Debug)]
+pub struct DownloadProgress<'a> {
+    asset_name: &'a str,
+    downloaded: u64,
+    total: Option<u64>,
+    finished: bool,
+}
+
+impl<'a> DownloadProgress<'a> {
+    pub(crate) fn new(asset_name: &'a str, downloaded: u64, total: Option<u64>) -> Self {
+        Self {
+            asset_name,
+            downloaded,
+            total,
+            finished: false,
+        }
+    }
+
+    /// The name of the release asset being downloaded, like `precious-Linux-x86_64-musl.tar.gz`.
+    #[must_use]
+    pub fn asset_name(&self) -> &str {
+        self.asset_name
+    }
+
+    /// The number of bytes downloaded so far. When a partial download is resumed, this includes
+    /// the bytes that were downloaded before. When a download is retried and the server can't
+    /// resume it, this starts over from 0.
+    #[must_use]
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded
+    }
+
+    /// The size of the asset in bytes, if it's known. This comes from the response's
+    /// `Content-Length` header, or from the asset's size in the forge's release info if the
+    /// response doesn't have one.
+    #[must_use]
+    pub fn total(&self) -> Option<u64> {
+        self.total
+    }
+
+    /// Returns true for the last call to the hook for a download. There is always exactly one such
+    /// call, even when the download fails or stops early because `ubi` found what it needed. In
+    /// those cases, [`downloaded`](Self::downloaded) can be less than [`total`](Self::total). When
+    /// a download fails partway through and is retried, the retries are part of the same download,
+    /// so this is only true once they're all done.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+type HookFn<'a> = dyn Fn(&DownloadProgress<'_>) + 'a;
+
+pub(crate) struct ProgressHook<'a>(Box<HookFn<'a>>);
+
+impl<'a> ProgressHook<'a> {
+    pub(crate) fn new(hook: impl Fn(&DownloadProgress<'_>) + 'a) -> Self {
+        Self(Box::new(hook))
+    }
+}
+
+impl fmt::Debug for ProgressHook<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+// Tracks the progress of one download, including any retries, and passes it to the hook, if there
+// is one. If the reporter is dropped before `finish` is called, it reports that the download is
+// finished then, so the hook always gets a last call.
+pub(crate) struct ProgressReporter<'h, 'a> {
+    hook: Option<&'h ProgressHook<'a>>,
+    asset_name: &'h str,
+    downloaded: u64,
+    total: Option<u64>,
+    finished: bool,
+}
+
+impl<'h, 'a> ProgressReporter<'h, 'a> {
+    pub(crate) fn new(hook: Option<&'h ProgressHook<'a>>, asset_name: &'h str) -> Self {
+        Self {
+            hook,
+            asset_name,
+            downloaded: 0,
+            total: None,
+            finished: false,
+        }
+    }
+
+    // This is called at the start of each attempt to download the asset. When resuming a download,
+    // `downloaded` is the number of bytes we already have.
+    pub(crate) fn start(&mut self, downloaded: u64, total: Option<u64>) {
+        self.downloaded = downloaded;
+        self.total = total;
+        self.report(false);
+    }
+
+    pub(crate) fn add(&mut self, bytes: usize) {
+        self.downloaded += bytes as u64;
+        self.report(false);
+    }
+
+    pub(crate) fn finish(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.report(true);
+        }
+    }
+
+    fn report(&self, finished: bool) {
+        if let Some(hook) = self.hook {
+            let mut progress = DownloadProgress::new(self.asset_name, self.downloaded, self.total);
+            progress.finished = finished;
+            (hook.0)(&progress);
+        }
+    }
+}
+
+impl Drop for ProgressReporter<'_, '_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn progress_reporter() {
+        let calls = RefCell::new(vec![]);
+        let hook = ProgressHook::new(|p| {
+            calls
+                .borrow_mut()
+                .push((p.downloaded(), p.total(), p.is_finished()));
+        });
+
+        let mut reporter = ProgressReporter::new(Some(&hook), "project.tar.gz");
+        reporter.start(10, Some(30));
+        reporter.add(15);
+        reporter.add(5);
+        reporter.finish();
+
+        assert_eq!(
+            *calls.borrow(),
+            vec![
+                (10, Some(30), false),
+                (25, Some(30), false),
+                (30, Some(30), false),
+                (30, Some(30), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn progress_reporter_finishes_when_dropped() {
+        let calls = RefCell::new(vec![]);
+        let hook = ProgressHook::new(|p| {
+            calls
+                .borrow_mut()
+                .push((p.downloaded(), p.total(), p.is_finished()));
+        });
+
+        {
+            let mut reporter = ProgressReporter::new(Some(&hook), "project.tar.gz");
+            reporter.start(0, Some(30));
+            reporter.add(10);
+        }
+
+        assert_eq!(
+            *calls.borrow(),
+            vec![
+                (0, Some(30), false),
+                (10, Some(30), false),
+                (10, Some(30), true),
+            ]
+        );
+    }
+    #[test]
+    fn progress_reporter_restarts_for_retries() {
+        let calls = RefCell::new(vec![]);
+        let hook = ProgressHook::new(|p| {
+            calls
+                .borrow_mut()
+                .push((p.downloaded(), p.total(), p.is_finished()));
+        });
+
+        let mut reporter = ProgressReporter::new(Some(&hook), "project.tar.gz");
+        reporter.start(0, None);
+        reporter.add(10);
+        reporter.start(10, Some(30));
+        reporter.add(20);
+        reporter.finish();
+
+        assert_eq!(
+            *calls.borrow(),
+            vec![
+                (0, None, false),
+                (10, None, false),
+                (10, Some(30), false),
+                (30, Some(30), false),
+                (30, Some(30), true),
+            ]
+        );
+    }
+}
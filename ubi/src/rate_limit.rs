@@ -0,0 +1,294 @@
+use crate::retry;
+use chrono::{DateTime, Utc};
+use reqwest::{header::HeaderMap, StatusCode};
+use std::{fmt::Write, time::Duration, time::SystemTime};
+use thiserror::Error;
+use url::Url;
+
+// GitHub and Forgejo use the `X-` prefix for these headers, while GitLab doesn't.
+const REMAINING_HEADERS: &[&str] = &["x-ratelimit-remaining", "ratelimit-remaining"];
+const RESET_HEADERS: &[&str] = &["x-ratelimit-reset", "ratelimit-reset"];
+
+// GitHub doesn't always say how long to wait after hitting a secondary rate limit. Its docs say to
+// wait at least a minute in that case.
+const SECONDARY_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// The error returned by [`Ubi::install_binary`](crate::Ubi::install_binary) when a request to the
+/// forge's API is rejected because it hit a rate limit. The message says when the limit resets, if
+/// the forge said, and which env var to set to a token to get a higher limit, if no token was
+/// used.
+#[derive(Debug, Error)]
+#[error("{}", self.message())]
+pub struct RateLimitError {
+    forge_name: &'static str,
+    url: Url,
+    secondary: bool,
+    reset_at: Option<DateTime<Utc>>,
+    // This is empty when the request used a token.
+    token_env_vars: &'static [&'static str],
+}
+
+impl RateLimitError {
+    /// When the rate limit resets, if the forge said.
+    #[must_use]
+    pub fn reset_at(&self) -> Option<SystemTime> {
+        self.reset_at.map(SystemTime::from)
+    }
+
+    /// Returns true if this is one of GitHub's secondary rate limits, which limit things like how
+    /// many requests can be made at once, rather than how many can be made per hour.
+    #[must_use]
+    pub fn is_secondary(&self) -> bool {
+        self.secondary
+    }
+
+    fn message(&self) -> String {
+        let mut msg = format!(
+            "the request to {} hit the {} API's {}rate limit.",
+            self.url,
+            self.forge_name,
+            if self.secondary { "secondary " } else { "" },
+        );
+        if let Some(reset_at) = self.reset_at {
+            let _ = write!(
+                msg,
+                " The limit resets at {}{}.",
+                reset_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                describe_wait(reset_at - Utc::now()),
+            );
+        } else if self.secondary {
+            msg.push_str(" Wait at least a minute before trying again.");
+        }
+        match self.token_env_vars {
+            [] => (),
+            [name] => {
+                let _ = write!(
+                    msg,
+                    " Set the {name} env var to an API token to get a higher rate limit."
+                );
+            }
+            names => {
+                let _ = write!(
+                    msg,
+                    " Set one of the {} env vars to an API token to get a higher rate limit.",
+                    names.join(" or "),
+                );
+            }
+        }
+        msg
+    }
+}
+
+fn describe_wait(wait: chrono::TimeDelta) -> String {
+    let secs = wait.num_seconds();
+    if secs <= 0 {
+        String::new()
+    } else if secs < 120 {
+        format!(", in {secs} seconds")
+    } else {
+        format!(", in about {} minutes", (secs + 59) / 60)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct RateLimit {
+    secondary: bool,
+    reset_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimit {
+    // Returns the rate limit that a response hit, if any. Forges respond with a 403 or 429 when a
+    // rate limit is hit. We tell these apart from other 403 responses by the headers that say how
+    // many requests are left, or by GitHub's message about secondary rate limits.
+    pub(crate) fn from_response(
+        status: StatusCode,
+        headers: &HeaderMap,
+        body: &str,
+    ) -> Option<Self> {
+        if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+
+        let exhausted = header_u64(headers, REMAINING_HEADERS) == Some(0);
+        let secondary = body.to_lowercase().contains("secondary rate limit");
+        if !exhausted && !secondary && status != StatusCode::TOO_MANY_REQUESTS {
+            return None;
+        }
+
+        // When there's a `Retry-After` header, that's how long we need to wait, even when the
+        // reset header says otherwise.
+        let reset_at = retry::retry_after(headers)
+            .and_then(|after| chrono::TimeDelta::from_std(after).ok())
+            .map(|after| Utc::now() + after)
+            .or_else(|| {
+                header_u64(headers, RESET_HEADERS)
+                    .and_then(|secs| i64::try_from(secs).ok())
+                    .and_then(|secs| DateTime::from_timestamp(secs, 0))
+            });
+        Some(RateLimit {
+            secondary,
+            reset_at,
+        })
+    }
+
+    // Returns how long we need to wait before the limit resets, if we know.
+    pub(crate) fn wait(&self) -> Option<Duration> {
+        match self.reset_at {
+            Some(reset_at) => Some((reset_at - Utc::now()).to_std().unwrap_or_default()),
+            None if self.secondary => Some(SECONDARY_LIMIT_WAIT),
+            None => None,
+        }
+    }
+
+    pub(crate) fn into_error(
+        self,
+        forge_name: &'static str,
+        url: Url,
+        token_env_vars: &'static [&'static str],
+    ) -> RateLimitError {
+        RateLimitError {
+            forge_name,
+            url,
+            secondary: self.secondary,
+            reset_at: self.reset_at,
+            token_env_vars,
+        }
+    }
+}
+
+fn header_u64(headers: &HeaderMap, names: &[&str]) -> Option<u64> {
+    names
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use rstest::rstest;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (k, v) in pairs {
+            headers.insert(*k, HeaderValue::from_str(v).unwrap());
+        }
+        headers
+    }
+
+    #[rstest]
+    #[case::not_forbidden(StatusCode::NOT_FOUND, &[("x-ratelimit-remaining", "0")], "", None)]
+    #[case::forbidden_for_other_reason(
+        StatusCode::FORBIDDEN,
+        &[("x-ratelimit-remaining", "10")],
+        "Resource not accessible by integration",
+        None
+    )]
+    #[case::github_primary(
+        StatusCode::FORBIDDEN,
+        &[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "1743465600")],
+        "API rate limit exceeded",
+        Some(RateLimit { secondary: false, reset_at: DateTime::from_timestamp(1_743_465_600, 0) })
+    )]
+    #[case::gitlab_primary(
+        StatusCode::TOO_MANY_REQUESTS,
+        &[("ratelimit-remaining", "0"), ("ratelimit-reset", "1743465600")],
+        "Retry later",
+        Some(RateLimit { secondary: false, reset_at: DateTime::from_timestamp(1_743_465_600, 0) })
+    )]
+    #[case::secondary_without_headers(
+        StatusCode::FORBIDDEN,
+        &[],
+        "You have exceeded a secondary rate limit.",
+        Some(RateLimit { secondary: true, reset_at: None })
+    )]
+    #[case::too_many_requests_without_headers(
+        StatusCode::TOO_MANY_REQUESTS,
+        &[],
+        "",
+        Some(RateLimit { secondary: false, reset_at: None })
+    )]
+    fn from_response(
+        #[case] status: StatusCode,
+        #[case] pairs: &[(&'static str, &str)],
+        #[case] body: &str,
+        #[case] expect: Option<RateLimit>,
+    ) {
+        assert_eq!(
+            RateLimit::from_response(status, &headers(pairs), body),
+            expect
+        );
+    }
+
+    #[test]
+    fn from_response_prefers_retry_after() {
+        let limit = RateLimit::from_response(
+            StatusCode::FORBIDDEN,
+            &headers(&[
+                ("x-ratelimit-remaining", "0"),
+                ("x-ratelimit-reset", "1743465600"),
+                ("retry-after", "30"),
+            ]),
+            "",
+        )
+        .unwrap();
+        let wait = limit.wait().unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+    }
+
+    #[rstest]
+    #[case::primary_without_token(
+        false,
+        DateTime::from_timestamp(1_743_465_600, 0),
+        &["GITHUB_TOKEN"],
+        "the request to https://api.github.com/repos/houseabsolute/ubi/releases/latest hit the GitHub API's rate limit. The limit resets at 2025-04-01 00:00:00 UTC. Set the GITHUB_TOKEN env var to an API token to get a higher rate limit."
+    )]
+    #[case::primary_with_token(
+        false,
+        DateTime::from_timestamp(1_743_465_600, 0),
+        &[],
+        "the request to https://api.github.com/repos/houseabsolute/ubi/releases/latest hit the GitHub API's rate limit. The limit resets at 2025-04-01 00:00:00 UTC."
+    )]
+    #[case::secondary(
+        true,
+        None,
+        &["GITHUB_TOKEN"],
+        "the request to https://api.github.com/repos/houseabsolute/ubi/releases/latest hit the GitHub API's secondary rate limit. Wait at least a minute before trying again. Set the GITHUB_TOKEN env var to an API token to get a higher rate limit."
+    )]
+    #[case::several_env_vars(
+        false,
+        None,
+        &["CI_TOKEN", "GITLAB_TOKEN"],
+        "the request to https://api.github.com/repos/houseabsolute/ubi/releases/latest hit the GitHub API's rate limit. Set one of the CI_TOKEN or GITLAB_TOKEN env vars to an API token to get a higher rate limit."
+    )]
+    fn error_message(
+        #[case] secondary: bool,
+        #[case] reset_at: Option<DateTime<Utc>>,
+        #[case] token_env_vars: &'static [&'static str],
+        #[case] expect: &str,
+    ) {
+        let err = RateLimit {
+            secondary,
+            reset_at,
+        }
+        .into_error(
+            "GitHub",
+            Url::parse("https://api.github.com/repos/houseabsolute/ubi/releases/latest").unwrap(),
+            token_env_vars,
+        );
+        assert_eq!(err.to_string(), expect);
+    }
+
+    #[rstest]
+    #[case::past(-10, "")]
+    #[case::seconds(30, ", in 30 seconds")]
+    #[case::minutes(600, ", in about 10 minutes")]
+    fn describe_wait(#[case] secs: i64, #[case] expect: &str) {
+        assert_eq!(
+            super::describe_wait(chrono::TimeDelta::seconds(secs)),
+            expect
+        );
+    }
+}
@@ -0,0 +1,292 @@
+// Requests to forges and downloads of release assets fail every so often for reasons that have
+// nothing to do with the request, like a connection reset or a 503 from an overloaded server. We
+// retry those with an exponential backoff. Only `GET` requests are retried, since those are the
+// only ones we know are safe to send more than once.
+use anyhow::Error;
+use log::info;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Client, Method, Request, Response, StatusCode,
+};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+pub(crate) const DEFAULT_MAX_RETRIES: u32 = 3;
+pub(crate) const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// The delay between retries never grows beyond this.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// If the server asks us to wait longer than this before retrying, we give up instead.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Retry {
+    max_retries: u32,
+    delay: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry::new(DEFAULT_MAX_RETRIES, DEFAULT_RETRY_DELAY)
+    }
+}
+
+impl Retry {
+    pub(crate) fn new(max_retries: u32, delay: Duration) -> Self {
+        Retry { max_retries, delay }
+    }
+
+    // Executes the request, retrying it if it fails with a connection error, a timeout, or a 502,
+    // 503, or 504 status. If we run out of retries, this returns the last response or error.
+    pub(crate) async fn execute(&self, client: &Client, req: Request) -> reqwest::Result<Response> {
+        let mut retries = 0;
+        let mut req = req;
+        loop {
+            // A request with a streaming body can't be cloned, but none of ours have one.
+            let next = if req.method() == Method::GET {
+                req.try_clone()
+            } else {
+                None
+            };
+            let url = req.url().clone();
+            let res = client.execute(req).await;
+            let (Some(next), Some(delay)) = (next, self.delay_for_response(&res, retries)) else {
+                return res;
+            };
+
+            retries += 1;
+            let reason = match &res {
+                Ok(resp) => format!("returned {}", resp.status()),
+                Err(e) => format!("failed: {e}"),
+            };
+            info!(
+                "The request to {url} {reason}. Retrying in {:.1} seconds (retry {retries} of {}).",
+                delay.as_secs_f64(),
+                self.max_retries,
+            );
+            tokio::time::sleep(delay).await;
+            req = next;
+        }
+    }
+
+    // Returns how long to wait before retrying after a download failed partway through, if we
+    // should retry it. `retries` is the number of times we've already retried.
+    pub(crate) fn delay_for_download_error(&self, err: &Error, retries: u32) -> Option<Duration> {
+        if retries >= self.max_retries || !is_interrupted_body(err) {
+            return None;
+        }
+        Some(self.backoff(retries))
+    }
+
+    pub(crate) fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    fn delay_for_response(
+        &self,
+        res: &reqwest::Result<Response>,
+        retries: u32,
+    ) -> Option<Duration> {
+        if retries >= self.max_retries {
+            return None;
+        }
+        match res {
+            Ok(resp) => {
+                if !is_transient_status(resp.status()) {
+                    return None;
+                }
+                match retry_after(resp.headers()) {
+                    Some(after) if after > MAX_RETRY_AFTER => None,
+                    Some(after) => Some(after),
+                    None => Some(self.backoff(retries)),
+                }
+            }
+            Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => {
+                Some(self.backoff(retries))
+            }
+            Err(_) => None,
+        }
+    }
+
+    // The delay doubles with each retry. We pick a random delay between half of that and all of it,
+    // so that many clients that failed at the same time don't all retry at the same time too.
+    fn backoff(&self, retries: u32) -> Duration {
+        let max = self
+            .delay
+            .saturating_mul(2u32.saturating_pow(retries))
+            .min(MAX_BACKOFF);
+        let half = max / 2;
+        // There's no random number generator in std, but `RandomState` is seeded randomly.
+        let random = RandomState::new().build_hasher().finish();
+        #[allow(clippy::cast_precision_loss)]
+        let fraction = random as f64 / u64::MAX as f64;
+        half + half.mul_f64(fraction)
+    }
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::BAD_GATEWAY
+        || status == StatusCode::SERVICE_UNAVAILABLE
+        || status == StatusCode::GATEWAY_TIMEOUT
+}
+
+// Returns true if the error came from reading the body of a response, for example because the
+// connection was reset partway through a download. Since we enable gzip support in `reqwest`, it
+// reports these as decode errors.
+fn is_interrupted_body(err: &Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_body() || e.is_decode() || e.is_timeout())
+}
+
+// The `Retry-After` header is either a number of seconds or an HTTP date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    // A date in the past means we can retry right away.
+    Some(
+        (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::seconds("120", Some(Duration::from_secs(120)))]
+    #[case::date_in_past("Tue, 01 Apr 2025 00:00:00 GMT", Some(Duration::ZERO))]
+    #[case::garbage("soon", None)]
+    fn retry_after(#[case] value: &'static str, #[case] expect: Option<Duration>) {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+        assert_eq!(super::retry_after(&headers), expect);
+    }
+
+    #[test]
+    fn retry_after_date_in_future() {
+        let date = chrono::Utc::now() + chrono::Duration::seconds(30);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_str(&date.to_rfc2822()).unwrap(),
+        );
+        let after = super::retry_after(&headers).unwrap();
+        assert!(after > Duration::from_secs(25) && after <= Duration::from_secs(30));
+    }
+
+    #[rstest]
+    #[case::first(0, Duration::from_millis(500), Duration::from_secs(1))]
+    #[case::second(1, Duration::from_secs(1), Duration::from_secs(2))]
+    #[case::third(2, Duration::from_secs(2), Duration::from_secs(4))]
+    #[case::capped(10, MAX_BACKOFF / 2, MAX_BACKOFF)]
+    fn backoff(#[case] retries: u32, #[case] min: Duration, #[case] max: Duration) {
+        let retry = Retry::default();
+        for _ in 0..20 {
+            let delay = retry.backoff(retries);
+            assert!(
+                delay >= min && delay <= max,
+                "{delay:?} is between {min:?} and {max:?}"
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::ok(200, None, 0)]
+    #[case::not_found(404, None, 0)]
+    #[case::server_error(500, None, 0)]
+    #[case::bad_gateway(502, None, 3)]
+    #[case::service_unavailable(503, None, 3)]
+    #[case::gateway_timeout(504, None, 3)]
+    #[case::retry_after(503, Some("0"), 3)]
+    #[case::retry_after_too_long(503, Some("3600"), 0)]
+    #[tokio::test]
+    async fn execute(
+        #[case] status: usize,
+        #[case] retry_after: Option<&str>,
+        #[case] expect_retries: usize,
+    ) -> anyhow::Result<()> {
+        crate::test_log::init_logging();
+
+        let mut server = mockito::Server::new_async().await;
+        let mut m = server.mock("GET", "/").with_status(status);
+        if let Some(retry_after) = retry_after {
+            m = m.with_header("retry-after", retry_after);
+        }
+        let m = m.expect(expect_retries + 1).create_async().await;
+
+        let client = Client::new();
+        let req = client.get(server.url()).build()?;
+        let resp = Retry::new(3, Duration::from_millis(1))
+            .execute(&client, req)
+            .await?;
+        assert_eq!(usize::from(resp.status().as_u16()), status);
+        m.assert_async().await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn execute_succeeds_after_retry() -> anyhow::Result<()> {
+        crate::test_log::init_logging();
+
+        // mockito uses the first matching mock that hasn't been hit as many times as it expects, so
+        // once the 503 mock has been hit twice, the next request gets a 200.
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("GET", "/")
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("GET", "/")
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new();
+        let req = client.get(server.url()).build()?;
+        let resp = Retry::new(3, Duration::from_millis(1))
+            .execute(&client, req)
+            .await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        unavailable.assert_async().await;
+        ok.assert_async().await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn execute_does_not_retry_post() -> anyhow::Result<()> {
+        crate::test_log::init_logging();
+
+        let mut server = mockito::Server::new_async().await;
+        let m = server
+            .mock("POST", "/")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        let client = Client::new();
+        let req = client.post(server.url()).build()?;
+        let resp = Retry::new(3, Duration::from_millis(1))
+            .execute(&client, req)
+            .await?;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        m.assert_async().await;
+
+        Ok(())
+    }
+}
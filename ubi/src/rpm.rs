@@ -0,0 +1,136 @@
+// An `.rpm` package starts with a fixed size "lead", followed by a signature header and a main
+// header, followed by the payload, which is a compressed cpio archive. We read the main header to
+// find out how the payload is compressed and skip over everything else.
+//
+// See https://rpm-software-management.github.io/rpm/manual/format_v4.html for details.
+use crate::installer::decompressing_reader;
+use anyhow::{anyhow, Context, Result};
+use log::debug;
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+const LEAD_LEN: usize = 96;
+const LEAD_MAGIC: &[u8; 4] = &[0xed, 0xab, 0xee, 0xdb];
+const HEADER_MAGIC: &[u8; 3] = &[0x8e, 0xad, 0xe8];
+const HEADER_INTRO_LEN: usize = 16;
+const INDEX_ENTRY_LEN: usize = 16;
+
+// These are the same limits that `rpm` itself uses.
+const MAX_HEADER_TAGS: u32 = 0xffff;
+const MAX_HEADER_DATA_LEN: u32 = 256 * 1024 * 1024;
+
+const RPMTAG_PAYLOADCOMPRESSOR: u32 = 1125;
+const RPM_STRING_TYPE: u32 = 6;
+
+struct Header {
+    // Each entry is the tag, type, offset into the store, and count.
+    index: Vec<(u32, u32, u32, u32)>,
+    store: Vec<u8>,
+}
+
+impl Header {
+    fn string_tag(&self, tag: u32) -> Option<String> {
+        let (_, _, offset, _) = self
+            .index
+            .iter()
+            .find(|(t, typ, _, _)| *t == tag && *typ == RPM_STRING_TYPE)?;
+        let value = self.store.get(usize::try_from(*offset).ok()?..)?;
+        let end = value.iter().position(|b| *b == 0)?;
+        Some(String::from_utf8_lossy(&value[..end]).into_owned())
+    }
+}
+
+// Returns a reader for the uncompressed cpio payload in the `.rpm` file.
+pub(crate) fn payload_reader(path: &Path, mut file: File) -> Result<Box<dyn Read>> {
+    let mut lead = [0u8; LEAD_LEN];
+    file.read_exact(&mut lead)
+        .with_context(|| format!("failed to read the lead of {}", path.display()))?;
+    if &lead[0..4] != LEAD_MAGIC {
+        return Err(anyhow!(
+            "{} is not a valid rpm package, it does not start with the rpm magic bytes",
+            path.display(),
+        ));
+    }
+
+    let signature = read_header(path, &mut file, "signature")?;
+    // The signature header is padded so that the main header starts on an 8 byte boundary.
+    let padding = (8 - signature.store.len() % 8) % 8;
+    file.seek(SeekFrom::Current(i64::try_from(padding)?))
+        .with_context(|| {
+            format!(
+                "failed to skip past the signature header in {}",
+                path.display()
+            )
+        })?;
+
+    let header = read_header(path, &mut file, "main")?;
+    // Packages built by very old versions of `rpm` don't set this tag, and these always used gzip.
+    let compressor = header
+        .string_tag(RPMTAG_PAYLOADCOMPRESSOR)
+        .unwrap_or_else(|| String::from("gzip"));
+    let ext = match compressor.as_str() {
+        "bzip2" => "bz2",
+        "gzip" => "gz",
+        "xz" => "xz",
+        "zstd" => "zst",
+        c => {
+            return Err(anyhow!(
+                "the rpm package at {} uses an unsupported payload compressor, {c}",
+                path.display(),
+            ))
+        }
+    };
+    debug!("decompressing the rpm payload with the `{ext}` decoder");
+
+    decompressing_reader(ext, file)
+}
+
+fn read_header(path: &Path, file: &mut File, name: &str) -> Result<Header> {
+    let mut intro = [0u8; HEADER_INTRO_LEN];
+    file.read_exact(&mut intro)
+        .with_context(|| format!("failed to read the {name} header from {}", path.display()))?;
+    if &intro[0..3] != HEADER_MAGIC {
+        return Err(anyhow!(
+            "the {name} header in {} does not start with the rpm header magic bytes",
+            path.display(),
+        ));
+    }
+
+    let tag_count = u32::from_be_bytes(intro[8..12].try_into()?);
+    let data_len = u32::from_be_bytes(intro[12..16].try_into()?);
+    if tag_count > MAX_HEADER_TAGS || data_len > MAX_HEADER_DATA_LEN {
+        return Err(anyhow!(
+            "the {name} header in {} is too large, it has {tag_count} tags and {data_len} bytes of data",
+            path.display(),
+        ));
+    }
+    debug!("found rpm {name} header with {tag_count} tags and {data_len} bytes of data");
+
+    let mut index_bytes = vec![0u8; usize::try_from(tag_count)? * INDEX_ENTRY_LEN];
+    file.read_exact(&mut index_bytes).with_context(|| {
+        format!(
+            "failed to read the {name} header index from {}",
+            path.display()
+        )
+    })?;
+    let index = index_bytes
+        .chunks_exact(INDEX_ENTRY_LEN)
+        .map(|entry| {
+            let field = |i: usize| u32::from_be_bytes(entry[i * 4..i * 4 + 4].try_into().unwrap());
+            (field(0), field(1), field(2), field(3))
+        })
+        .collect();
+
+    let mut store = vec![0u8; usize::try_from(data_len)?];
+    file.read_exact(&mut store).with_context(|| {
+        format!(
+            "failed to read the {name} header data from {}",
+            path.display()
+        )
+    })?;
+
+    Ok(Header { index, store })
+}
@@ -0,0 +1,348 @@
+// When we extract an entire archive, we check every member before writing anything for it. Each
+// archive crate has its own idea of what is safe to extract, so we apply the same rules to all of
+// them instead.
+use crate::{archive::ArchiveLink, cpio::S_IFMT};
+use std::{
+    io,
+    path::{Component, Path, PathBuf},
+};
+use thiserror::Error;
+
+const S_IFIFO: u32 = 0o010_000;
+const S_IFCHR: u32 = 0o020_000;
+const S_IFBLK: u32 = 0o060_000;
+const S_IFSOCK: u32 = 0o140_000;
+
+const S_ISUID: u32 = 0o4000;
+const S_ISGID: u32 = 0o2000;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum MemberKind<'a> {
+    File,
+    Dir,
+    // The target is relative to the directory containing the link.
+    Symlink(&'a Path),
+    // The target is relative to the root of the archive.
+    HardLink(&'a Path),
+    // Device files, FIFOs, and sockets. The string describes which one it is.
+    Special(&'static str),
+}
+
+impl MemberKind<'_> {
+    // Returns the kind of special file that the file type bits of `mode` describe, if any. This
+    // works for modes from zip, 7z, and cpio archives, which store the full `st_mode` value.
+    pub(crate) fn special_from_unix_mode(mode: u32) -> Option<Self> {
+        match mode & S_IFMT {
+            S_IFCHR => Some(MemberKind::Special("character device")),
+            S_IFBLK => Some(MemberKind::Special("block device")),
+            S_IFIFO => Some(MemberKind::Special("FIFO")),
+            S_IFSOCK => Some(MemberKind::Special("socket")),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum UnsafeMemberError {
+    #[error("the archive member {} has an absolute path", path.display())]
+    AbsolutePath { path: PathBuf },
+    #[error("the archive member {} has a `..` component in its path", path.display())]
+    ParentDir { path: PathBuf },
+    #[error(
+        "the archive member {} is a symlink to {}, which is outside of the archive root",
+        path.display(),
+        target.display()
+    )]
+    SymlinkEscapesRoot { path: PathBuf, target: PathBuf },
+    #[error(
+        "the archive member {} is a hard link to {}, which is outside of the archive root",
+        path.display(),
+        target.display()
+    )]
+    HardLinkEscapesRoot { path: PathBuf, target: PathBuf },
+    #[error("the archive member {} is a {kind}, which ubi will not extract", path.display())]
+    SpecialFile { path: PathBuf, kind: &'static str },
+    #[error("the archive member {} has the {bit} bit set", path.display())]
+    SetId { path: PathBuf, bit: &'static str },
+    #[error(
+        "the archive member {} is inside a symlink that leads outside of the archive root",
+        path.display()
+    )]
+    InsideEscapingSymlink { path: PathBuf },
+    #[error(
+        "the archive member {} would be written through a symlink extracted before it",
+        path.display()
+    )]
+    WritesThroughSymlink { path: PathBuf },
+    #[error("could not resolve the directory that the archive member {} goes in", path.display())]
+    ResolveParent {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+// Checks that an archive member is safe to extract. The `mode` is the member's Unix mode, if the
+// archive records one. On success, this returns the member's path relative to the directory we're
+// extracting into, without any `.` components. This is empty for the archive's root directory.
+pub(crate) fn check_member(
+    path: &Path,
+    kind: MemberKind<'_>,
+    mode: Option<u32>,
+) -> Result<PathBuf, UnsafeMemberError> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => relative.push(c),
+            Component::CurDir => (),
+            Component::ParentDir => {
+                return Err(UnsafeMemberError::ParentDir {
+                    path: path.to_path_buf(),
+                })
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(UnsafeMemberError::AbsolutePath {
+                    path: path.to_path_buf(),
+                })
+            }
+        }
+    }
+
+    match kind {
+        MemberKind::File | MemberKind::Dir => (),
+        MemberKind::Symlink(target) => {
+            if ArchiveLink::Symlink(target.to_path_buf())
+                .target_path(&relative)
+                .is_none()
+            {
+                return Err(UnsafeMemberError::SymlinkEscapesRoot {
+                    path: path.to_path_buf(),
+                    target: target.to_path_buf(),
+                });
+            }
+        }
+        MemberKind::HardLink(target) => {
+            if ArchiveLink::HardLink(target.to_path_buf())
+                .target_path(&relative)
+                .is_none()
+            {
+                return Err(UnsafeMemberError::HardLinkEscapesRoot {
+                    path: path.to_path_buf(),
+                    target: target.to_path_buf(),
+                });
+            }
+        }
+        MemberKind::Special(kind) => {
+            return Err(UnsafeMemberError::SpecialFile {
+                path: path.to_path_buf(),
+                kind,
+            })
+        }
+    }
+
+    if let Some(mode) = mode {
+        for (bit, name) in [(S_ISUID, "setuid"), (S_ISGID, "setgid")] {
+            if mode & bit != 0 {
+                return Err(UnsafeMemberError::SetId {
+                    path: path.to_path_buf(),
+                    bit: name,
+                });
+            }
+        }
+    }
+
+    Ok(relative)
+}
+
+// `check_member` only looks at one member at a time, so it can't catch a member that goes through
+// symlinks extracted before it. For example, `x -> .` followed by `x/y -> ..` puts a link to the
+// parent of `into` at `y`, so `x/y/evil` would be written outside of `into`. This resolves the
+// closest existing ancestor of the member's path, and checks that it is still inside `into`. It
+// also refuses to write to a path that is already a symlink. The `member_path` is the path that
+// `check_member` returned. Call this right before creating anything for a member.
+pub(crate) fn check_extraction_path(
+    into: &Path,
+    member_path: &Path,
+) -> Result<PathBuf, UnsafeMemberError> {
+    let path = into.join(member_path);
+    if path
+        .symlink_metadata()
+        .is_ok_and(|meta| meta.file_type().is_symlink())
+    {
+        return Err(UnsafeMemberError::WritesThroughSymlink {
+            path: member_path.to_path_buf(),
+        });
+    }
+
+    let resolve_err = |source| UnsafeMemberError::ResolveParent {
+        path: member_path.to_path_buf(),
+        source,
+    };
+    let root = into.canonicalize().map_err(resolve_err)?;
+    let mut ancestor = path.parent();
+    while let Some(dir) = ancestor {
+        if dir.symlink_metadata().is_ok() {
+            break;
+        }
+        ancestor = dir.parent();
+    }
+    if let Some(dir) = ancestor {
+        if !dir.canonicalize().map_err(resolve_err)?.starts_with(&root) {
+            return Err(UnsafeMemberError::InsideEscapingSymlink {
+                path: member_path.to_path_buf(),
+            });
+        }
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::file(
+        "project/bin/project",
+        MemberKind::File,
+        Some(0o100_755),
+        Ok("project/bin/project")
+    )]
+    #[case::leading_cur_dir("./project/bin", MemberKind::Dir, Some(0o755), Ok("project/bin"))]
+    #[case::root_dir("./", MemberKind::Dir, None, Ok(""))]
+    #[case::symlink_inside(
+        "project/bin/project",
+        MemberKind::Symlink(Path::new("../libexec/tool")),
+        None,
+        Ok("project/bin/project")
+    )]
+    #[case::hard_link_inside(
+        "project/bin/project",
+        MemberKind::HardLink(Path::new("project/libexec/tool")),
+        None,
+        Ok("project/bin/project")
+    )]
+    #[case::absolute_path(
+        "/etc/passwd",
+        MemberKind::File,
+        None,
+        Err("the archive member /etc/passwd has an absolute path")
+    )]
+    #[case::parent_dir(
+        "project/../../evil",
+        MemberKind::File,
+        None,
+        Err("the archive member project/../../evil has a `..` component in its path")
+    )]
+    #[case::parent_dir_inside_root(
+        "project/../project",
+        MemberKind::File,
+        None,
+        Err("the archive member project/../project has a `..` component in its path")
+    )]
+    #[case::symlink_escapes(
+        "project/evil",
+        MemberKind::Symlink(Path::new("../../etc/passwd")),
+        None,
+        Err("the archive member project/evil is a symlink to ../../etc/passwd, which is outside of the archive root")
+    )]
+    #[case::absolute_symlink(
+        "project/evil",
+        MemberKind::Symlink(Path::new("/etc/passwd")),
+        None,
+        Err("the archive member project/evil is a symlink to /etc/passwd, which is outside of the archive root")
+    )]
+    #[case::hard_link_escapes(
+        "project/evil",
+        MemberKind::HardLink(Path::new("../etc/passwd")),
+        None,
+        Err("the archive member project/evil is a hard link to ../etc/passwd, which is outside of the archive root")
+    )]
+    #[case::device(
+        "project/dev",
+        MemberKind::special_from_unix_mode(0o020_644).unwrap(),
+        None,
+        Err("the archive member project/dev is a character device, which ubi will not extract")
+    )]
+    #[case::setuid(
+        "project/bin/project",
+        MemberKind::File,
+        Some(0o104_755),
+        Err("the archive member project/bin/project has the setuid bit set")
+    )]
+    #[case::setgid(
+        "project/bin/project",
+        MemberKind::File,
+        Some(0o2755),
+        Err("the archive member project/bin/project has the setgid bit set")
+    )]
+    fn check_member(
+        #[case] path: &str,
+        #[case] kind: MemberKind<'_>,
+        #[case] mode: Option<u32>,
+        #[case] expect: Result<&str, &str>,
+    ) {
+        let res = super::check_member(Path::new(path), kind, mode);
+        match expect {
+            Ok(expect) => assert_eq!(res.unwrap(), PathBuf::from(expect)),
+            Err(msg) => assert_eq!(res.unwrap_err().to_string(), msg),
+        }
+    }
+
+    #[rstest]
+    #[case::file(0o100_644, None)]
+    #[case::dir(0o040_755, None)]
+    #[case::symlink(0o120_777, None)]
+    #[case::character_device(0o020_644, Some("character device"))]
+    #[case::block_device(0o060_644, Some("block device"))]
+    #[case::fifo(0o010_644, Some("FIFO"))]
+    #[case::socket(0o140_755, Some("socket"))]
+    fn special_from_unix_mode(#[case] mode: u32, #[case] expect: Option<&str>) {
+        let kind = MemberKind::special_from_unix_mode(mode);
+        match (kind, expect) {
+            (Some(MemberKind::Special(kind)), Some(expect)) => assert_eq!(kind, expect),
+            (None, None) => (),
+            (kind, expect) => panic!("got {kind:?}, expected {expect:?}"),
+        }
+    }
+
+    #[cfg(target_family = "unix")]
+    #[rstest]
+    #[case::new_file("project/bin/project", Ok(()))]
+    #[case::inside_symlink_to_dir_inside("lib/tool", Ok(()))]
+    #[case::symlink_itself(
+        "lib",
+        Err("the archive member lib would be written through a symlink extracted before it")
+    )]
+    #[case::inside_symlink_to_parent(
+        "up/evil",
+        Err(
+            "the archive member up/evil is inside a symlink that leads outside of the archive root"
+        )
+    )]
+    #[case::deep_inside_symlink_to_parent(
+        "up/new/evil",
+        Err("the archive member up/new/evil is inside a symlink that leads outside of the archive root")
+    )]
+    fn check_extraction_path(
+        #[case] member_path: &str,
+        #[case] expect: Result<(), &str>,
+    ) -> anyhow::Result<()> {
+        use std::os::unix::fs::symlink;
+
+        let td = tempfile::tempdir()?;
+        let into = td.path().join("into");
+        std::fs::create_dir_all(into.join("lib64"))?;
+        symlink("lib64", into.join("lib"))?;
+        symlink("..", into.join("up"))?;
+
+        let res = super::check_extraction_path(&into, Path::new(member_path));
+        match expect {
+            Ok(()) => assert_eq!(res?, into.join(member_path)),
+            Err(msg) => assert_eq!(res.unwrap_err().to_string(), msg),
+        }
+
+        Ok(())
+    }
+}
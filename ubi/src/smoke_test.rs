@@ -0,0 +1,194 @@
+// After installing an executable, we can run it to make sure that it actually works. This catches
+// downloads that install cleanly but can't run on this system, for example because they're linked
+// against a libc that isn't installed.
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use std::{
+    io::{self, Read},
+    path::Path,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub(crate) const DEFAULT_SMOKE_TEST_ARGS: &[&str] = &["--version"];
+pub(crate) const DEFAULT_SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+const MAX_SPAWN_TRIES: u32 = 5;
+
+#[derive(Debug)]
+pub(crate) struct SmokeTest {
+    args: Vec<String>,
+    timeout: Duration,
+    check_version: bool,
+}
+
+impl SmokeTest {
+    pub(crate) fn new(args: Vec<String>, timeout: Duration, check_version: bool) -> Self {
+        Self {
+            args,
+            timeout,
+            check_version,
+        }
+    }
+
+    // Runs the executable at `exe` and returns an error if it doesn't exit successfully within the
+    // timeout. If we're checking the version, the output must also contain the version from `tag`.
+    pub(crate) fn run(&self, exe: &Path, tag: Option<&str>) -> Result<()> {
+        let command = format!("{} {}", exe.display(), self.args.join(" "));
+        debug!("running `{command}` to check the installed executable");
+
+        let mut child = spawn(
+            Command::new(exe)
+                .args(&self.args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped()),
+        )
+        .with_context(|| format!("failed to run `{command}`"))?;
+
+        // We read the output in separate threads so that the child can't block on a full pipe while
+        // we wait for it to exit.
+        let stdout = read_in_thread(child.stdout.take());
+        let stderr = read_in_thread(child.stderr.take());
+
+        let start = Instant::now();
+        let status = loop {
+            if let Some(status) = child
+                .try_wait()
+                .with_context(|| format!("failed to wait for `{command}` to exit"))?
+            {
+                break status;
+            }
+            if start.elapsed() > self.timeout {
+                // If this fails, the child exited after we last checked, and there's nothing else
+                // to do about it.
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(anyhow!(
+                    "`{command}` did not exit within {} seconds",
+                    self.timeout.as_secs_f64(),
+                ));
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        let stdout = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+        if !status.success() {
+            return Err(anyhow!(
+                "`{command}` failed with {status}\nstdout:\n{stdout}\nstderr:\n{stderr}"
+            ));
+        }
+
+        if self.check_version {
+            let Some(version) = tag.map(version_from_tag).filter(|v| !v.is_empty()) else {
+                warn!("not checking the output of `{command}` for a version because the release tag does not contain one");
+                return Ok(());
+            };
+            if !(stdout.contains(version) || stderr.contains(version)) {
+                return Err(anyhow!(
+                    "the output of `{command}` does not contain the version from the release tag, {version}\nstdout:\n{stdout}\nstderr:\n{stderr}"
+                ));
+            }
+        }
+
+        debug!("`{command}` succeeded");
+        Ok(())
+    }
+}
+
+// Running an executable right after writing it can fail with "text file busy" if another thread
+// forked while the file was open for writing. This doesn't last, so we retry a few times.
+fn spawn(command: &mut Command) -> io::Result<Child> {
+    let mut tries = 0;
+    loop {
+        match command.spawn() {
+            Err(e) if e.kind() == io::ErrorKind::ExecutableFileBusy && tries < MAX_SPAWN_TRIES => {
+                tries += 1;
+                thread::sleep(POLL_INTERVAL);
+            }
+            res => return res,
+        }
+    }
+}
+
+fn read_in_thread(reader: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = vec![];
+        if let Some(mut reader) = reader {
+            // If we can't read the output, we'll just treat it as empty.
+            let _ = reader.read_to_end(&mut output);
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    })
+}
+
+// Tags are usually the version with a prefix like `v` or `release-`, so we strip everything before
+// the first digit.
+fn version_from_tag(tag: &str) -> &str {
+    tag.trim_start_matches(|c: char| !c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::v_prefix("v1.2.3", "1.2.3")]
+    #[case::no_prefix("1.2.3", "1.2.3")]
+    #[case::name_prefix("release-2024.01.02", "2024.01.02")]
+    #[case::no_digits("latest", "")]
+    fn version_from_tag(#[case] tag: &str, #[case] expect: &str) {
+        assert_eq!(super::version_from_tag(tag), expect);
+    }
+
+    #[cfg(target_family = "unix")]
+    #[rstest]
+    #[case::succeeds("echo 'tool 1.2.3'", false, Ok(()))]
+    #[case::version_matches("echo 'tool 1.2.3'", true, Ok(()))]
+    #[case::version_on_stderr("echo 'tool 1.2.3' >&2", true, Ok(()))]
+    #[case::version_does_not_match(
+        "echo 'tool 1.2.2'",
+        true,
+        Err("does not contain the version from the release tag, 1.2.3")
+    )]
+    #[case::fails(
+        "echo 'bad things happened' >&2; exit 2",
+        false,
+        Err("failed with exit status: 2")
+    )]
+    #[case::times_out("sleep 10", false, Err("did not exit within 0.5 seconds"))]
+    fn run(
+        #[case] script: &str,
+        #[case] check_version: bool,
+        #[case] expect: Result<(), &str>,
+    ) -> Result<()> {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        crate::test_log::init_logging();
+
+        let td = tempfile::tempdir()?;
+        let exe = td.path().join("tool");
+        fs::write(&exe, format!("#!/bin/sh\n{script}\n"))?;
+        fs::set_permissions(&exe, fs::Permissions::from_mode(0o755))?;
+
+        let smoke_test = SmokeTest::new(
+            vec![String::from("--version")],
+            Duration::from_millis(500),
+            check_version,
+        );
+        let res = smoke_test.run(&exe, Some("v1.2.3"));
+        match expect {
+            Ok(()) => res?,
+            Err(msg) => {
+                let err = res.unwrap_err().to_string();
+                assert!(err.contains(msg), "error `{err}` contains `{msg}`");
+            }
+        }
+
+        Ok(())
+    }
+}
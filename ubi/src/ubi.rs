@@ -1,13 +1,34 @@
-use crate::{forge::Forge, installer::Installer, picker::AssetPicker};
+use crate::{
+    cache::{CacheKey, DownloadCache},
+    digest::{DigestChecker, DigestMismatchError},
+    extension::Extension,
+    forge::Forge,
+    installer::{Installed, Installer},
+    partial_download::{self, PartialDownload, Resume},
+    picker::AssetPicker,
+    post_install::{self, PostInstall, PostInstallHook},
+    progress::{ProgressHook, ProgressReporter},
+    retry::Retry,
+    smoke_test::SmokeTest,
+};
 use anyhow::{anyhow, Context, Result};
-use log::debug;
+use log::{debug, info, warn};
 use reqwest::{
-    header::{HeaderValue, ACCEPT},
-    Client, StatusCode,
+    header::{HeaderValue, ACCEPT, ACCEPT_ENCODING, IF_RANGE, RANGE},
+    Client, Response, StatusCode,
 };
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::Write, path::PathBuf};
-use tempfile::{tempdir, TempDir};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tempfile::{tempdir_in, TempDir};
+use tokio::{
+    sync::mpsc::{self, Receiver},
+    task,
+};
 use url::Url;
 
 /// `Ubi` is the core of this library, and is used to download and install a binary. Use the
@@ -17,9 +38,20 @@ pub struct Ubi<'a> {
     forge: Forge,
     asset_url: Option<Url>,
     asset_picker: AssetPicker<'a>,
-    installer: Box<dyn Installer>,
+    installer: Arc<dyn Installer>,
     reqwest_client: Client,
     min_age_days: Option<u32>,
+    smoke_test: Option<SmokeTest>,
+    post_install_hooks: Vec<PostInstallHook<'a>>,
+    download_progress_hook: Option<ProgressHook<'a>>,
+    // If this is true, tarballs are extracted as they're downloaded.
+    stream_download: bool,
+    // Interrupted downloads are kept here so that we can resume them.
+    partial_download_dir: PathBuf,
+    retry: Retry,
+    download_cache: Option<DownloadCache>,
+    // The tag of the release we picked an asset from, if we got it from the forge's API.
+    tag: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
@@ -27,6 +59,10 @@ pub struct Ubi<'a> {
 pub(crate) struct Asset {
     pub(crate) name: String,
     pub(crate) url: Url,
+    // Not all forges tell us how big an asset is.
+    pub(crate) size: Option<u64>,
+    // GitHub gives us a digest like `sha256:...` for newer assets. Other forges don't.
+    pub(crate) digest: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +70,10 @@ struct AssetHelper {
     name: String,
     url: Option<Url>,
     browser_download_url: Option<Url>,
+    #[serde(default)]
+    size: Option<u64>,
+    #[serde(default)]
+    digest: Option<String>,
 }
 
 impl TryFrom<AssetHelper> for Asset {
@@ -50,6 +90,8 @@ impl TryFrom<AssetHelper> for Asset {
         Ok(Asset {
             name: helper.name,
             url,
+            size: helper.size,
+            digest: helper.digest,
         })
     }
 }
@@ -64,13 +106,21 @@ pub(crate) struct Download {
 
 impl<'a> Ubi<'a> {
     /// Create a new Ubi instance.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         forge: Forge,
         asset_url: Option<Url>,
         asset_picker: AssetPicker<'a>,
-        installer: Box<dyn Installer>,
+        installer: Arc<dyn Installer>,
         reqwest_client: Client,
         min_age_days: Option<u32>,
+        smoke_test: Option<SmokeTest>,
+        post_install_hooks: Vec<PostInstallHook<'a>>,
+        download_progress_hook: Option<ProgressHook<'a>>,
+        stream_download: bool,
+        partial_download_dir: PathBuf,
+        retry: Retry,
+        download_cache: Option<DownloadCache>,
     ) -> Ubi<'a> {
         Ubi {
             forge,
@@ -79,6 +129,14 @@ impl<'a> Ubi<'a> {
             installer,
             reqwest_client,
             min_age_days,
+            smoke_test,
+            post_install_hooks,
+            download_progress_hook,
+            stream_download,
+            partial_download_dir,
+            retry,
+            download_cache,
+            tag: None,
         }
     }
 
@@ -100,10 +158,49 @@ impl<'a> Ubi<'a> {
     /// * Unable to find an executable with the right name in a downloaded archive.
     /// * Unable to write the executable to the specified directory.
     /// * Unable to set executable permissions on the installed binary.
+    /// * The smoke test, if enabled, failed. In this case, the previously installed binary, if any,
+    ///   is restored.
+    /// * A post-install hook failed. In this case, the error is a [`PostInstallHookError`](crate::PostInstallHookError),
+    ///   which you can get with [`anyhow::Error::downcast_ref`]. The installed files are left in
+    ///   place.
     pub async fn install_binary(&mut self) -> Result<()> {
         let asset = self.asset().await?;
-        let download = self.download_asset(&self.reqwest_client, asset).await?;
-        self.installer.install(&download)
+        let installed = if let Some(ext) = self.streamable_extension(&asset) {
+            self.stream_asset(asset, ext).await?
+        } else {
+            let download = self.download_asset(&self.reqwest_client, asset).await?;
+            self.installer.install(&download)?
+        };
+        let path = match installed {
+            Installed::Exe(installed) => {
+                if let Some(smoke_test) = &self.smoke_test {
+                    if let Err(e) = smoke_test.run(&installed.path, self.tag.as_deref()) {
+                        let path = installed.path.clone();
+                        installed.roll_back()?;
+                        return Err(e.context(format!(
+                            "the smoke test for the executable installed at {} failed, so it was removed",
+                            path.display()
+                        )));
+                    }
+                }
+                let path = installed.path.clone();
+                installed.commit()?;
+                path
+            }
+            Installed::Archive(dir) => dir,
+        };
+
+        if self.post_install_hooks.is_empty() {
+            return Ok(());
+        }
+        post_install::run_hooks(
+            &self.post_install_hooks,
+            &PostInstall::new(
+                path,
+                self.tag.clone(),
+                self.forge.project_name().to_string(),
+            ),
+        )
     }
 
     pub(crate) async fn asset(&mut self) -> Result<Asset> {
@@ -112,44 +209,321 @@ impl<'a> Ubi<'a> {
             return Ok(Asset {
                 name: url.path().split('/').next_back().unwrap().to_string(),
                 url: url.clone(),
+                size: None,
+                digest: None,
             });
         }
 
-        let assets = if let Some(min_age) = self.min_age_days {
+        let release = if let Some(min_age) = self.min_age_days {
             // Minimum age mode: fetch with age filtering
             self.forge
-                .fetch_assets_with_min_age(&self.reqwest_client, min_age)
+                .fetch_release_with_min_age(&self.reqwest_client, min_age)
                 .await?
         } else {
             // Normal mode: fetch latest
-            self.forge.fetch_assets(&self.reqwest_client).await?
+            self.forge.fetch_release(&self.reqwest_client).await?
         };
+        self.tag = release.tag_name;
 
-        let asset = self.asset_picker.pick_asset(assets)?;
+        let asset = self.asset_picker.pick_asset(release.assets)?;
         debug!("picked asset named {}", asset.name);
         Ok(asset)
     }
 
+    // Returns the asset's extension if we should extract it as it's downloaded. We don't stream an
+    // asset that's in the download cache, since there's nothing to download.
+    fn streamable_extension(&self, asset: &Asset) -> Option<Extension> {
+        if !self.stream_download
+            || self
+                .cache_key(asset)
+                .is_some_and(|(cache, key)| cache.contains(&key))
+        {
+            return None;
+        }
+        Extension::from_path(Path::new(&asset.name))
+            .ok()
+            .flatten()
+            .filter(Extension::can_stream)
+    }
+
+    // The installer reads the tarball on a blocking thread, while we feed it the chunks of the
+    // response as they arrive. If the installer finishes before the download does, which happens
+    // when it finds the executable partway through the tarball, we stop downloading, unless we need
+    // the rest of the download to check its digest.
+    async fn stream_asset(&self, asset: Asset, ext: Extension) -> Result<Installed> {
+        debug!("streaming asset from {}", asset.url);
+
+        let mut resp = self
+            .asset_response(&self.reqwest_client, &asset, None)
+            .await?;
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER_CHUNKS);
+        let installer = Arc::clone(&self.installer);
+        let name = asset.name.clone();
+        let extractor = task::spawn_blocking(move || {
+            installer.install_from_stream(&name, &ext, Box::new(ChunkReader::new(rx)))
+        });
+
+        let mut checker = DigestChecker::new(asset.digest.as_deref());
+        let mut progress = ProgressReporter::new(self.download_progress_hook.as_ref(), &asset.name);
+        progress.start(0, resp.content_length().or(asset.size));
+        let mut installer_done = false;
+        let mut download_err = None;
+        loop {
+            match resp.chunk().await {
+                // This waits when the installer falls behind, which keeps us from buffering the
+                // whole download in memory. The send fails if the installer has stopped reading.
+                Ok(Some(c)) => {
+                    progress.add(c.len());
+                    if let Some(checker) = &mut checker {
+                        checker.update(&c);
+                    }
+                    if !installer_done && tx.send(Ok(c.to_vec())).await.is_err() {
+                        if checker.is_none() {
+                            break;
+                        }
+                        installer_done = true;
+                    }
+                }
+                Ok(None) => {
+                    // If the installer is still reading, the error tells it not to install
+                    // anything.
+                    if let Some(Err(e)) = checker.take().map(|c| c.check(&asset.name)) {
+                        let _ = tx.send(Err(io::Error::other(e.to_string()))).await;
+                        download_err = Some(anyhow::Error::new(e).context(format!(
+                            "the asset downloaded from {} is not the one the release lists",
+                            asset.url
+                        )));
+                    }
+                    break;
+                }
+                Err(e) => {
+                    // The installer can't finish without the rest of the tarball, so we tell it to
+                    // stop and then return the download error instead of whatever it returns.
+                    let _ = tx.send(Err(io::Error::other("the download failed"))).await;
+                    download_err = Some(anyhow::Error::new(e).context(format!(
+                        "failed to read chunk while downloading asset from {}",
+                        asset.url
+                    )));
+                    break;
+                }
+            }
+        }
+        // We're done downloading whether it succeeded, failed, or the installer stopped early.
+        progress.finish();
+        drop(tx);
+
+        let res = extractor.await.map_err(|_| {
+            anyhow!(
+                "the thread extracting {} as it was downloaded panicked",
+                asset.name
+            )
+        })?;
+        if let Some(e) = download_err {
+            // The installer may have finished before the download failed or turned out to be bad.
+            if let Ok(Installed::Exe(installed)) = res {
+                installed.roll_back()?;
+            }
+            return Err(e);
+        }
+        res
+    }
+
     async fn download_asset(&self, client: &Client, asset: Asset) -> Result<Download> {
+        let cache_key = self.cache_key(&asset);
+        if let Some((cache, key)) = &cache_key {
+            if let Some(path) = cache.get(key) {
+                return download_from_cache(&path, &asset, &self.partial_download_dir);
+            }
+        }
+
+        let download = self.download_asset_with_retries(client, &asset).await?;
+        if let Some((cache, key)) = &cache_key {
+            // The download is still good if we can't cache it, so this isn't an error, unless the
+            // download doesn't match its digest.
+            if let Err(e) = cache.put(key, &download.archive_path) {
+                if e.is::<DigestMismatchError>() {
+                    return Err(e.context(format!(
+                        "the asset downloaded from {} is not the one the release lists",
+                        asset.url
+                    )));
+                }
+                warn!("could not add {} to the download cache: {e:#}", asset.name);
+            }
+        }
+        Ok(download)
+    }
+
+    // We only cache assets from a release we looked up, since we know its tag. An asset URL could
+    // point at something that changes over time, like the latest release.
+    fn cache_key<'s>(&'s self, asset: &'s Asset) -> Option<(&'s DownloadCache, CacheKey<'s>)> {
+        let cache = self.download_cache.as_ref()?;
+        let Some(tag) = self.tag.as_deref() else {
+            debug!(
+                "not using the download cache for {} because we do not know its release tag",
+                asset.name
+            );
+            return None;
+        };
+        Some((
+            cache,
+            CacheKey {
+                forge_type: self.forge.forge_type(),
+                project_name: self.forge.project_name(),
+                tag,
+                asset_name: &asset.name,
+                digest: asset.digest.as_deref(),
+            },
+        ))
+    }
+
+    async fn download_asset_with_retries(
+        &self,
+        client: &Client,
+        asset: &Asset,
+    ) -> Result<Download> {
+        // The retries share one progress reporter, so the hook sees them all as one download.
+        let mut progress = ProgressReporter::new(self.download_progress_hook.as_ref(), &asset.name);
+        let mut retries = 0;
+        loop {
+            match self.try_download_asset(client, asset, &mut progress).await {
+                Err(e) => {
+                    let Some(delay) = self.retry.delay_for_download_error(&e, retries) else {
+                        return Err(e);
+                    };
+                    retries += 1;
+                    info!(
+                        "The download of {} failed partway through: {e:#}. Retrying in {:.1} seconds (retry {retries} of {}).",
+                        asset.url,
+                        delay.as_secs_f64(),
+                        self.retry.max_retries(),
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    // The download is written to a partial download file first, which is only moved to the temp dir
+    // once it's complete. If the download fails partway through, the next attempt resumes it with a
+    // `Range` request. Servers that don't support ranges send the whole asset, which we handle the
+    // same way as a fresh download.
+    async fn try_download_asset(
+        &self,
+        client: &Client,
+        asset: &Asset,
+        progress: &mut ProgressReporter<'_, '_>,
+    ) -> Result<Download> {
         debug!("downloading asset from {}", asset.url);
 
-        let mut req_builder = client.get(asset.url.clone()).header(
-            ACCEPT,
-            HeaderValue::from_str("application/octet-stream")
-                .context("failed to create header value for Accept header")?,
-        );
+        let partial = PartialDownload::new(&self.partial_download_dir, &asset.url, &asset.name)?;
+        let resume = partial.resume();
+        let mut resp = self.asset_response(client, asset, resume.as_ref()).await?;
+        let (mut downloaded_file, offset) = match resume {
+            Some(resume) if resp.status() == StatusCode::PARTIAL_CONTENT => {
+                if partial_download::content_range_start(resp.headers()) == Some(resume.offset) {
+                    info!(
+                        "resuming the download of {} after {} bytes",
+                        asset.url, resume.offset
+                    );
+                    (partial.append()?, resume.offset)
+                } else {
+                    debug!(
+                        "the server did not resume the download at byte {}, so we will download the whole asset",
+                        resume.offset
+                    );
+                    resp = self.asset_response(client, asset, None).await?;
+                    let validator = partial_download::validator(resp.headers());
+                    (partial.start(validator.as_deref())?, 0)
+                }
+            }
+            Some(_) if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE => {
+                debug!(
+                    "the server could not resume the download, so we will download the whole asset"
+                );
+                resp = self.asset_response(client, asset, None).await?;
+                let validator = partial_download::validator(resp.headers());
+                (partial.start(validator.as_deref())?, 0)
+            }
+            Some(_) | None => {
+                let validator = partial_download::validator(resp.headers());
+                (partial.start(validator.as_deref())?, 0)
+            }
+        };
+        debug!("partial download path is {}", partial.path().display());
+
+        let total = resp.content_length().map(|len| len + offset).or(asset.size);
+        progress.start(offset, total);
+
+        while let Some(c) = resp.chunk().await.with_context(|| {
+            format!(
+                "failed to read chunk while downloading asset from {}",
+                asset.url
+            )
+        })? {
+            downloaded_file.write_all(c.as_ref()).with_context(|| {
+                format!("failed to write chunk to {}", partial.path().display())
+            })?;
+            progress.add(c.len());
+        }
+        drop(downloaded_file);
+        progress.finish();
+
+        // We make the temp dir in the same directory as the partial download so that moving the
+        // completed download into it is a rename.
+        let td = tempdir_in(&self.partial_download_dir)
+            .context("failed to create temporary directory for download")?;
+        let mut archive_path = td.path().to_path_buf();
+        archive_path.push(&asset.name);
+        debug!("archive path is {}", archive_path.to_string_lossy());
+        partial.finish(&archive_path)?;
+
+        Ok(Download {
+            _temp_dir: td,
+            archive_path,
+        })
+    }
+
+    // When `resume` is set, this asks for the rest of a partial download. In that case, a `206
+    // Partial Content` or `416 Range Not Satisfiable` response is returned as is, and the caller
+    // decides what to do with it.
+    async fn asset_response(
+        &self,
+        client: &Client,
+        asset: &Asset,
+        resume: Option<&Resume>,
+    ) -> Result<Response> {
+        // A range is in terms of the bytes the server sends, so we ask it not to compress the
+        // response. Otherwise the bytes we write wouldn't line up with the range we ask for when
+        // resuming.
+        let mut req_builder = client
+            .get(asset.url.clone())
+            .header(
+                ACCEPT,
+                HeaderValue::from_str("application/octet-stream")
+                    .context("failed to create header value for Accept header")?,
+            )
+            .header(ACCEPT_ENCODING, "identity");
+        if let Some(resume) = resume {
+            req_builder = req_builder
+                .header(RANGE, format!("bytes={}-", resume.offset))
+                .header(IF_RANGE, &resume.validator);
+        }
         req_builder = self.forge.maybe_add_token_header(req_builder)?;
         let req = req_builder
             .build()
             .with_context(|| format!("failed to build HTTP request for {}", asset.url))?;
 
-        let mut resp = self.reqwest_client.execute(req).await.with_context(|| {
+        let resp = self.retry.execute(client, req).await.with_context(|| {
             format!(
                 "failed to execute HTTP request to download asset from {}",
                 asset.url
             )
         })?;
-        if resp.status() != StatusCode::OK {
+        let resumable = resume.is_some()
+            && (resp.status() == StatusCode::PARTIAL_CONTENT
+                || resp.status() == StatusCode::RANGE_NOT_SATISFIABLE);
+        if resp.status() != StatusCode::OK && !resumable {
             let mut msg = format!("error requesting {}: {}", asset.url, resp.status());
             if let Ok(t) = resp.text().await {
                 msg.push('\n');
@@ -158,34 +532,67 @@ impl<'a> Ubi<'a> {
             return Err(anyhow!(msg));
         }
 
-        let td = tempdir().context("failed to create temporary directory for download")?;
-        let mut archive_path = td.path().to_path_buf();
-        archive_path.push(&asset.name);
-        debug!("archive path is {}", archive_path.to_string_lossy());
+        Ok(resp)
+    }
+}
 
-        {
-            let mut downloaded_file = File::create(&archive_path).with_context(|| {
-                format!(
-                    "failed to create file at {} for downloaded asset",
-                    archive_path.display()
-                )
-            })?;
-            while let Some(c) = resp.chunk().await.with_context(|| {
-                format!(
-                    "failed to read chunk while downloading asset from {}",
-                    asset.url
-                )
-            })? {
-                downloaded_file.write_all(c.as_ref()).with_context(|| {
-                    format!("failed to write chunk to {}", archive_path.display())
-                })?;
-            }
+// The installer works on the file in the download's temp dir, so we copy the cached asset there
+// rather than handing it the file in the cache.
+fn download_from_cache(cached: &Path, asset: &Asset, temp_root: &Path) -> Result<Download> {
+    info!("installing {} from the download cache", asset.name);
+    partial_download::prepare_dir(temp_root)?;
+    let td = tempdir_in(temp_root).context("failed to create temporary directory for download")?;
+    let archive_path = td.path().join(&asset.name);
+    fs::copy(cached, &archive_path).with_context(|| {
+        format!(
+            "failed to copy {} from the download cache to {}",
+            cached.display(),
+            archive_path.display()
+        )
+    })?;
+    Ok(Download {
+        _temp_dir: td,
+        archive_path,
+    })
+}
+
+// The number of response chunks that can be waiting for the installer when we stream a download.
+const STREAM_BUFFER_CHUNKS: usize = 64;
+
+// Reads the chunks of a download that are sent from the task that's downloading it. This must be
+// used on a thread outside of the async runtime, since it blocks while waiting for a chunk.
+struct ChunkReader {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ChunkReader {
+    fn new(rx: Receiver<io::Result<Vec<u8>>>) -> Self {
+        Self {
+            rx,
+            chunk: vec![],
+            pos: 0,
         }
+    }
+}
 
-        Ok(Download {
-            _temp_dir: td,
-            archive_path,
-        })
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.rx.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
+                }
+                // The sender is dropped once the download is complete.
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
     }
 }
 
@@ -193,6 +600,8 @@ impl<'a> Ubi<'a> {
 mod tests {
     use super::*;
     use rstest::rstest;
+    use std::fs;
+    use tempfile::tempdir;
 
     struct AssetTextInput {
         url: Option<&'static str>,
@@ -246,6 +655,8 @@ mod tests {
             name: "asset.tar.gz".to_string(),
             url: input.url.map(Url::parse).transpose()?,
             browser_download_url: input.browser_download_url.map(Url::parse).transpose()?,
+            size: None,
+            digest: None,
         };
         let asset = Asset::try_from(helper);
 
@@ -259,4 +670,549 @@ mod tests {
 
         Ok(())
     }
+
+    #[rstest]
+    #[case::with_size(
+        r#"{"name":"a.tar.gz","url":"https://example.com/a.tar.gz","size":1234}"#,
+        Some(1234)
+    )]
+    #[case::without_size(r#"{"name":"a.tar.gz","url":"https://example.com/a.tar.gz"}"#, None)]
+    fn asset_size(#[case] json: &str, #[case] expect: Option<u64>) -> Result<()> {
+        let asset: Asset = serde_json::from_str(json)?;
+        assert_eq!(asset.size, expect);
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::exe("project.tar.gz", false, "project")]
+    #[case::exe_from_apk("project.apk", false, "project")]
+    #[case::extract_all("project.tar.gz", true, "bin/project")]
+    #[case::not_a_tarball("project.zip", false, "project")]
+    #[tokio::test]
+    async fn install_binary_with_stream_download(
+        #[case] asset_name: &str,
+        #[case] extract_all: bool,
+        #[case] expect_installed: &str,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let mut server = mockito::Server::new_async().await;
+        let m = server
+            .mock(
+                "GET",
+                format!("/test/project/releases/download/v1.0.0/{asset_name}").as_str(),
+            )
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_body(std::fs::read(Path::new("test-data").join(asset_name))?)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let td = tempdir()?;
+        let url = format!(
+            "{}/test/project/releases/download/v1.0.0/{asset_name}",
+            server.url()
+        );
+        let mut builder = crate::UbiBuilder::new()
+            .url(&url)
+            .install_dir(td.path())
+            .stream_download();
+        if extract_all {
+            builder = builder.extract_all();
+        }
+        builder.build()?.install_binary().await?;
+
+        m.assert_async().await;
+        assert!(td.path().join(expect_installed).is_file());
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::exe(false, true)]
+    #[case::exe_bad_digest(false, false)]
+    #[case::extract_all(true, true)]
+    #[case::extract_all_bad_digest(true, false)]
+    #[tokio::test]
+    async fn install_binary_with_stream_download_checks_digest(
+        #[case] extract_all: bool,
+        #[case] digest_matches: bool,
+    ) -> Result<()> {
+        use sha2::{Digest, Sha256};
+
+        crate::test_log::init_logging();
+
+        let asset = fs::read(Path::new("test-data").join("project.tar.gz"))?;
+        let digest = if digest_matches {
+            format!("sha256:{:x}", Sha256::digest(&asset))
+        } else {
+            format!("sha256:{}", "0".repeat(64))
+        };
+        let mut server = mockito::Server::new_async().await;
+        let (release, path) = release_with_digest_mock(&mut server, &digest, 1).await;
+        let download = server
+            .mock("GET", path)
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_body(&asset)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let td = tempdir()?;
+        let installed = td.path().join(if extract_all {
+            "bin/project"
+        } else {
+            "project"
+        });
+        // The executable that was already installed is put back if the download is bad.
+        if !extract_all {
+            fs::write(&installed, "old project")?;
+        }
+        let url = server.url();
+        let mut builder = crate::UbiBuilder::new()
+            .project("test/project")
+            .api_base_url(&url)
+            .token("token")
+            .platform(platforms::Platform::find("x86_64-unknown-linux-musl").unwrap())
+            .install_dir(td.path())
+            .stream_download();
+        if extract_all {
+            builder = builder.extract_all();
+        }
+        let res = builder.build()?.install_binary().await;
+
+        release.assert_async().await;
+        download.assert_async().await;
+        if digest_matches {
+            res?;
+            assert_ne!(fs::read(&installed)?, b"old project");
+        } else {
+            let err = format!("{:#}", res.unwrap_err());
+            assert!(
+                err.contains(&format!("does not match its digest, {digest}")),
+                "error `{err}` says the digest does not match"
+            );
+            if extract_all {
+                assert!(!installed.exists(), "nothing was installed");
+            } else {
+                assert_eq!(fs::read_to_string(&installed)?, "old project");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::resumed(StatusCode::PARTIAL_CONTENT)]
+    #[case::range_ignored(StatusCode::OK)]
+    #[case::range_not_satisfiable(StatusCode::RANGE_NOT_SATISFIABLE)]
+    #[tokio::test]
+    async fn install_binary_resumes_partial_download(
+        #[case] range_status: StatusCode,
+    ) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let asset = fs::read(Path::new("test-data").join("project.tar.gz"))?;
+        let offset = asset.len() / 2;
+
+        let mut server = mockito::Server::new_async().await;
+        let path = "/test/project/releases/download/v1.0.0/project.tar.gz";
+        let url = Url::parse(&format!("{}{path}", server.url()))?;
+
+        let partial_dir = tempdir()?;
+        let partial = PartialDownload::new(partial_dir.path(), &url, "project.tar.gz")?;
+        partial.start(Some(r#""abc""#))?;
+        fs::write(partial.path(), &asset[..offset])?;
+        // This releases the lock so that the install below can resume the download.
+        drop(partial);
+
+        let mut range_mock = server
+            .mock("GET", path)
+            .match_header("range", format!("bytes={offset}-").as_str())
+            .match_header("if-range", r#""abc""#)
+            .with_status(range_status.as_u16().into());
+        range_mock = if range_status == StatusCode::PARTIAL_CONTENT {
+            range_mock
+                .with_header(
+                    "content-range",
+                    &format!("bytes {offset}-{}/{}", asset.len() - 1, asset.len()),
+                )
+                .with_body(&asset[offset..])
+        } else if range_status == StatusCode::OK {
+            range_mock.with_body(&asset)
+        } else {
+            range_mock
+        };
+        let range_mock = range_mock.expect(1).create_async().await;
+        let full_mock = server
+            .mock("GET", path)
+            .match_header("range", mockito::Matcher::Missing)
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_body(&asset)
+            .expect(usize::from(
+                range_status == StatusCode::RANGE_NOT_SATISFIABLE,
+            ))
+            .create_async()
+            .await;
+
+        let td = tempdir()?;
+        crate::UbiBuilder::new()
+            .url(url.as_str())
+            .install_dir(td.path())
+            .partial_download_dir(partial_dir.path())
+            .build()?
+            .install_binary()
+            .await?;
+
+        range_mock.assert_async().await;
+        full_mock.assert_async().await;
+        assert!(td.path().join("project").is_file());
+        // Only the download's lock file is left behind.
+        assert_eq!(
+            fs::read_dir(partial_dir.path())?
+                .filter(|e| e.as_ref().is_ok_and(|e| e.path().is_dir()))
+                .count(),
+            0,
+            "the partial download is removed once the download is complete"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn install_binary_keeps_failed_download() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let mut server = mockito::Server::new_async().await;
+        let path = "/test/project/releases/download/v1.0.0/project.tar.gz";
+        let url = Url::parse(&format!("{}{path}", server.url()))?;
+        // The server stops sending the body partway through.
+        let _m = server
+            .mock("GET", path)
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_header("etag", r#""abc""#)
+            .with_chunked_body(|w| {
+                w.write_all(b"partial")?;
+                w.flush()?;
+                // This gives the client time to read the chunk before the connection is dropped.
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                Err(io::Error::other("the connection was reset"))
+            })
+            .create_async()
+            .await;
+
+        let partial_dir = tempdir()?;
+        let td = tempdir()?;
+        let res = crate::UbiBuilder::new()
+            .url(url.as_str())
+            .install_dir(td.path())
+            .partial_download_dir(partial_dir.path())
+            .max_retries(0)
+            .build()?
+            .install_binary()
+            .await;
+        assert!(res.is_err());
+
+        let partial = PartialDownload::new(partial_dir.path(), &url, "project.tar.gz")?;
+        assert_eq!(
+            partial.resume(),
+            Some(Resume {
+                offset: 7,
+                validator: r#""abc""#.to_string(),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn install_binary_retries_failed_download() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let asset = fs::read(Path::new("test-data").join("project.tar.gz"))?;
+        let offset = asset.len() / 2;
+
+        let mut server = mockito::Server::new_async().await;
+        let path = "/test/project/releases/download/v1.0.0/project.tar.gz";
+        let unavailable = server
+            .mock("GET", path)
+            .with_status(StatusCode::SERVICE_UNAVAILABLE.as_u16().into())
+            .expect(1)
+            .create_async()
+            .await;
+        let first_half = asset[..offset].to_vec();
+        let interrupted = server
+            .mock("GET", path)
+            .match_header("range", mockito::Matcher::Missing)
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_header("etag", r#""abc""#)
+            .with_chunked_body(move |w| {
+                w.write_all(&first_half)?;
+                w.flush()?;
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                Err(io::Error::other("the connection was reset"))
+            })
+            .expect(1)
+            .create_async()
+            .await;
+        let resumed = server
+            .mock("GET", path)
+            .match_header("range", format!("bytes={offset}-").as_str())
+            .with_status(StatusCode::PARTIAL_CONTENT.as_u16().into())
+            .with_header(
+                "content-range",
+                &format!("bytes {offset}-{}/{}", asset.len() - 1, asset.len()),
+            )
+            .with_body(&asset[offset..])
+            .expect(1)
+            .create_async()
+            .await;
+
+        let calls = std::cell::RefCell::new(vec![]);
+        let partial_dir = tempdir()?;
+        let td = tempdir()?;
+        crate::UbiBuilder::new()
+            .url(&format!("{}{path}", server.url()))
+            .install_dir(td.path())
+            .partial_download_dir(partial_dir.path())
+            .retry_delay(std::time::Duration::from_millis(1))
+            .download_progress_hook(|p| {
+                calls
+                    .borrow_mut()
+                    .push((p.downloaded(), p.total(), p.is_finished()));
+            })
+            .build()?
+            .install_binary()
+            .await?;
+
+        unavailable.assert_async().await;
+        interrupted.assert_async().await;
+        resumed.assert_async().await;
+        assert!(td.path().join("project").is_file());
+
+        // The retries are reported as one download that picks up where the interrupted one
+        // stopped, and that only finishes once.
+        let calls = calls.borrow();
+        let total = Some(asset.len() as u64);
+        assert_eq!(calls.first(), Some(&(0, None, false)));
+        assert!(calls.contains(&(offset as u64, total, false)));
+        assert_eq!(calls.iter().filter(|(_, _, finished)| *finished).count(), 1);
+        assert_eq!(calls.last(), Some(&(asset.len() as u64, total, true)));
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::to_temp_file(false)]
+    #[case::streamed(true)]
+    #[tokio::test]
+    async fn install_binary_reports_download_progress(#[case] stream: bool) -> Result<()> {
+        crate::test_log::init_logging();
+
+        let asset = fs::read(Path::new("test-data").join("project.tar.gz"))?;
+        let mut server = mockito::Server::new_async().await;
+        let path = "/test/project/releases/download/v1.0.0/project.tar.gz";
+        let m = server
+            .mock("GET", path)
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_body(&asset)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let calls = std::cell::RefCell::new(vec![]);
+        let partial_dir = tempdir()?;
+        let td = tempdir()?;
+        let url = format!("{}{path}", server.url());
+        let mut builder = crate::UbiBuilder::new()
+            .url(&url)
+            .install_dir(td.path())
+            .partial_download_dir(partial_dir.path())
+            .download_progress_hook(|p| {
+                assert_eq!(p.asset_name(), "project.tar.gz");
+                calls
+                    .borrow_mut()
+                    .push((p.downloaded(), p.total(), p.is_finished()));
+            });
+        if stream {
+            builder = builder.stream_download();
+        }
+        builder.build()?.install_binary().await?;
+        m.assert_async().await;
+
+        let calls = calls.borrow();
+        let total = Some(asset.len() as u64);
+        assert_eq!(calls.first(), Some(&(0, total, false)));
+        // The hook is told that the download is finished exactly once, at the end.
+        assert_eq!(calls.iter().filter(|(_, _, finished)| *finished).count(), 1);
+        assert!(calls.last().is_some_and(|(_, _, finished)| *finished));
+        if stream {
+            // The installer stops reading once it finds the executable, so we may not download
+            // the whole asset.
+            assert!(calls
+                .iter()
+                .all(|(downloaded, _, _)| *downloaded <= asset.len() as u64));
+        } else {
+            assert_eq!(calls.last(), Some(&(asset.len() as u64, total, true)));
+        }
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::cached(false)]
+    #[case::cached_file_corrupted(true)]
+    #[tokio::test]
+    async fn install_binary_uses_download_cache(#[case] corrupt_cache: bool) -> Result<()> {
+        use sha2::{Digest, Sha256};
+
+        crate::test_log::init_logging();
+
+        let asset = fs::read(Path::new("test-data").join("project.tar.gz"))?;
+        let digest = format!("sha256:{:x}", Sha256::digest(&asset));
+        let mut server = mockito::Server::new_async().await;
+        let (release, path) = release_with_digest_mock(&mut server, &digest, 2).await;
+        let download = server
+            .mock("GET", path)
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_body(&asset)
+            .expect(if corrupt_cache { 2 } else { 1 })
+            .create_async()
+            .await;
+
+        let cache_dir = tempdir()?;
+        let partial_dir = tempdir()?;
+        let api_base_url = server.url();
+        for i in 0..2 {
+            if i == 1 && corrupt_cache {
+                let cached = walkdir::WalkDir::new(cache_dir.path())
+                    .into_iter()
+                    .filter_map(Result::ok)
+                    .find(|e| e.file_type().is_file())
+                    .expect("the asset was cached");
+                fs::write(cached.path(), "corrupted")?;
+            }
+            let td = tempdir()?;
+            cached_release_builder(
+                &api_base_url,
+                td.path(),
+                partial_dir.path(),
+                cache_dir.path(),
+            )
+            .build()?
+            .install_binary()
+            .await?;
+            assert!(td.path().join("project").is_file());
+        }
+
+        release.assert_async().await;
+        download.assert_async().await;
+        let stats = crate::DownloadCache::new(cache_dir.path()).stats()?;
+        assert_eq!(stats.assets(), 1);
+        assert_eq!(stats.bytes(), asset.len() as u64);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn install_binary_rejects_download_that_does_not_match_digest() -> Result<()> {
+        crate::test_log::init_logging();
+
+        let asset = fs::read(Path::new("test-data").join("project.tar.gz"))?;
+        let digest = format!("sha256:{}", "0".repeat(64));
+        let mut server = mockito::Server::new_async().await;
+        let (release, path) = release_with_digest_mock(&mut server, &digest, 1).await;
+        let download = server
+            .mock("GET", path)
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_body(&asset)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let cache_dir = tempdir()?;
+        let partial_dir = tempdir()?;
+        let td = tempdir()?;
+        let api_base_url = server.url();
+        let res = cached_release_builder(
+            &api_base_url,
+            td.path(),
+            partial_dir.path(),
+            cache_dir.path(),
+        )
+        .build()?
+        .install_binary()
+        .await;
+
+        let err = format!("{:#}", res.unwrap_err());
+        assert!(
+            err.contains(&format!("does not match its digest, {digest}")),
+            "error `{err}` says the digest does not match"
+        );
+        release.assert_async().await;
+        download.assert_async().await;
+        assert!(!td.path().join("project").exists());
+        assert_eq!(
+            crate::DownloadCache::new(cache_dir.path())
+                .stats()?
+                .assets(),
+            0
+        );
+
+        Ok(())
+    }
+
+    async fn release_with_digest_mock(
+        server: &mut mockito::ServerGuard,
+        digest: &str,
+        expect: usize,
+    ) -> (mockito::Mock, &'static str) {
+        let path = "/test/project/releases/download/v1.0.0/project-Linux-x86_64.tar.gz";
+        let release = server
+            .mock("GET", "/repos/test/project/releases/latest")
+            .with_status(StatusCode::OK.as_u16().into())
+            .with_body(format!(
+                r#"{{"tag_name":"v1.0.0","published_at":"2025-01-01T00:00:00Z","assets":[{{"name":"project-Linux-x86_64.tar.gz","url":"{}{path}","digest":"{digest}"}}]}}"#,
+                server.url(),
+            ))
+            .expect(expect)
+            .create_async()
+            .await;
+        (release, path)
+    }
+
+    fn cached_release_builder<'a>(
+        api_base_url: &'a str,
+        install_dir: &'a Path,
+        partial_dir: &'a Path,
+        cache_dir: &'a Path,
+    ) -> crate::UbiBuilder<'a> {
+        crate::UbiBuilder::new()
+            .project("test/project")
+            .api_base_url(api_base_url)
+            .token("token")
+            .platform(platforms::Platform::find("x86_64-unknown-linux-musl").unwrap())
+            .install_dir(install_dir)
+            .partial_download_dir(partial_dir)
+            .download_cache_dir(cache_dir)
+    }
+
+    #[test]
+    fn chunk_reader() {
+        let (tx, rx) = mpsc::channel(4);
+        tx.blocking_send(Ok(b"ab".to_vec())).unwrap();
+        tx.blocking_send(Ok(vec![])).unwrap();
+        tx.blocking_send(Ok(b"cde".to_vec())).unwrap();
+        drop(tx);
+        let mut out = String::new();
+        ChunkReader::new(rx).read_to_string(&mut out).unwrap();
+        assert_eq!(out, "abcde");
+
+        let (tx, rx) = mpsc::channel(4);
+        tx.blocking_send(Ok(b"ab".to_vec())).unwrap();
+        tx.blocking_send(Err(io::Error::other("the download failed")))
+            .unwrap();
+        let err = ChunkReader::new(rx).read_to_end(&mut vec![]).unwrap_err();
+        assert_eq!(err.to_string(), "the download failed");
+    }
 }